@@ -14,10 +14,13 @@
 
 //! The top-level application type.
 
+use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
+use raw_window_handle::{HasRawDisplayHandle, RawDisplayHandle};
+
 use crate::backend::application as backend;
 use crate::clipboard::Clipboard;
 use crate::error::Error;
@@ -40,6 +43,12 @@ pub trait AppHandler {
     /// Called when a menu item is selected.
     #[allow(unused_variables)]
     fn command(&mut self, id: u32) {}
+
+    /// Called with an event sent from another thread with [`AppHandle::send_event`].
+    ///
+    /// Use [`Box::downcast`] to get back the type that was sent.
+    #[allow(unused_variables)]
+    fn user_event(&mut self, event: Box<dyn Any + Send>) {}
 }
 
 /// The top level application object.
@@ -192,17 +201,62 @@ impl Application {
     }
 }
 
+unsafe impl HasRawDisplayHandle for Application {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.backend_app.raw_display_handle()
+    }
+}
+
 /// A handle that can enqueue tasks on the application loop.
 #[derive(Clone)]
 pub struct AppHandle(backend::AppHandle);
 
 impl AppHandle {
+    /// Run `callback` on the main thread, with the handler of the application if it has one.
+    ///
+    /// This wakes the application loop straight away, so use it for work which can't wait. Work
+    /// which can should use [`add_idle_callback`] instead.
+    ///
+    /// [`add_idle_callback`]: AppHandle::add_idle_callback
     pub fn run_on_main<F>(&self, callback: F)
     where
         F: FnOnce(Option<&mut dyn AppHandler>) + Send + 'static,
     {
         self.0.run_on_main(callback)
     }
+
+    /// Run `callback` on the main thread once the application loop is otherwise idle, with the
+    /// handler of the application.
+    ///
+    /// Unlike [`run_on_main`], this doesn't wake the loop, so the callback waits until the events
+    /// which are already queued have been handled. This suits work which isn't tied to a window,
+    /// like updating global state, where an [`IdleHandle`] can't be used. If the application has
+    /// no handler, the callback is dropped.
+    ///
+    /// This is currently only implemented on Wayland, elsewhere it's the same as [`run_on_main`].
+    ///
+    /// [`run_on_main`]: AppHandle::run_on_main
+    /// [`IdleHandle`]: crate::IdleHandle
+    pub fn add_idle_callback<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut dyn AppHandler) + Send + 'static,
+    {
+        self.0.add_idle_callback(callback)
+    }
+
+    /// Send `event` to the [`AppHandler::user_event`] of the application, on the main thread.
+    ///
+    /// This is a typed alternative to [`run_on_main`] for work done on other threads. Events are
+    /// delivered in the order they were sent. If the application has no handler, or its loop has
+    /// already stopped, the event is dropped.
+    ///
+    /// [`run_on_main`]: AppHandle::run_on_main
+    pub fn send_event<T: Any + Send>(&self, event: T) {
+        self.run_on_main(move |handler| match handler {
+            Some(handler) => handler.user_event(Box::new(event)),
+            None => tracing::warn!("dropping user event, the application has no handler"),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -19,6 +19,7 @@ use gtk::gio::{ApplicationFlags, Cancellable};
 use gtk::Application as GtkApplication;
 
 use gtk::prelude::{ApplicationExt, GtkApplicationExt};
+use raw_window_handle::{HasRawDisplayHandle, RawDisplayHandle, XcbDisplayHandle};
 
 use crate::application::AppHandler;
 
@@ -103,6 +104,14 @@ impl crate::platform::linux::ApplicationExt for crate::Application {
     }
 }
 
+unsafe impl HasRawDisplayHandle for Application {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        tracing::error!("HasRawDisplayHandle trait not implemented for gtk.");
+        // TODO: We can retrieve it from GdkX11 and GdkWayland.
+        RawDisplayHandle::Xcb(XcbDisplayHandle::empty())
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct AppHandle;
 
@@ -111,6 +120,18 @@ impl AppHandle {
     where
         F: FnOnce(Option<&mut dyn AppHandler>) + Send + 'static,
     {
-        todo!()
+        tracing::warn!("AppHandle::run_on_main is unimplemented on GTK");
+    }
+
+    pub fn add_idle_callback<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut dyn AppHandler) + Send + 'static,
+    {
+        // This goes through `run_on_main`, so it works once that does. A glib idle source
+        // would then be the place to wait for the loop to go quiet.
+        self.run_on_main(move |handler| match handler {
+            Some(handler) => callback(handler),
+            None => tracing::warn!("dropping idle callback, the application has no handler"),
+        })
     }
 }
@@ -48,6 +48,7 @@ use raw_window_handle::{
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
 
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::{ClickCounter, IdleCallback};
 use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
 use crate::error::Error as ShellError;
@@ -56,9 +57,10 @@ use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
 // use crate::piet::ImageFormat;
 use crate::region::Region;
 use crate::scale::{Scalable, Scale, ScaledArea};
+use crate::screen::Monitor;
 use crate::text::{simulate_input, Event};
 use crate::window::{
-    self, FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    self, DragIcon, FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 
 use super::application::Application;
@@ -258,6 +260,21 @@ impl WindowBuilder {
         self
     }
 
+    pub fn max_size(self, _size: Size) -> Self {
+        warn!("max_size is unimplemented on GTK");
+        self
+    }
+
+    pub fn app_id(self, _app_id: impl Into<String>) -> Self {
+        warn!("app_id is unimplemented on GTK");
+        self
+    }
+
+    pub fn parent(self, _parent: &WindowHandle) -> Self {
+        warn!("parent is unimplemented on GTK");
+        self
+    }
+
     pub fn resizable(mut self, resizable: bool) -> Self {
         self.resizable = resizable;
         self
@@ -1004,6 +1021,37 @@ impl WindowHandle {
         }
     }
 
+    pub fn lock_pointer(&self) {
+        warn!("lock_pointer is unimplemented on GTK");
+    }
+
+    pub fn unlock_pointer(&self) {}
+
+    pub fn begin_resize_drag(&self, _edge: window::ResizeEdge) {
+        warn!("begin_resize_drag is unimplemented on GTK");
+    }
+
+    pub fn set_min_size(&self, _size: Size) {
+        warn!("set_min_size is unimplemented on GTK");
+    }
+
+    pub fn set_max_size(&self, _size: Option<Size>) {
+        warn!("set_max_size is unimplemented on GTK");
+    }
+
+    pub fn set_app_id(&self, _app_id: &str) {
+        warn!("set_app_id is unimplemented on GTK");
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool, _monitor: Option<Monitor>) {
+        if let Some(state) = self.state.upgrade() {
+            match fullscreen {
+                true => state.window.fullscreen(),
+                false => state.window.unfullscreen(),
+            }
+        }
+    }
+
     pub fn set_position(&self, mut position: Point) {
         if let Some(state) = self.state.upgrade() {
             if let Some(parent_state) = &state.parent {
@@ -1085,7 +1133,7 @@ impl WindowHandle {
     }
 
     pub fn set_window_state(&mut self, size_state: window::WindowState) {
-        use window::WindowState::{Maximized, Minimized, Restored};
+        use window::WindowState::{Fullscreen, Maximized, Minimized, Restored};
         let cur_size_state = self.get_window_state();
         if let Some(state) = self.state.upgrade() {
             match (size_state, cur_size_state) {
@@ -1094,14 +1142,22 @@ impl WindowHandle {
                 (Minimized, _) => state.window.iconify(),
                 (Restored, Maximized) => state.window.unmaximize(),
                 (Restored, Minimized) => state.window.deiconify(),
+                (Fullscreen, _) => state.window.fullscreen(),
+                (Restored, Fullscreen) => state.window.unfullscreen(),
                 (Restored, Restored) => (), // Unreachable
             }
         }
     }
 
     pub fn get_window_state(&self) -> window::WindowState {
-        use window::WindowState::{Maximized, Minimized, Restored};
+        use window::WindowState::{Fullscreen, Maximized, Minimized, Restored};
         if let Some(state) = self.state.upgrade() {
+            if let Some(window) = state.window.window() {
+                let fullscreen = gtk::gdk::WindowState::FULLSCREEN;
+                if (window.state() & fullscreen) == fullscreen {
+                    return Fullscreen;
+                }
+            }
             if state.window.is_maximized() {
                 return Maximized;
             } else if let Some(window) = state.window.parent_window() {
@@ -1206,6 +1262,25 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("set_cursor_visible is unimplemented on GTK");
+    }
+
+    pub fn set_input_region(&self, _region: Option<Region>) {
+        warn!("set_input_region is unimplemented on GTK");
+    }
+
+    pub fn start_drag(&self, _formats: &[ClipboardFormat], _icon: Option<DragIcon>) {
+        warn!("start_drag is unimplemented on GTK");
+    }
+
+    pub fn is_visible(&self) -> bool {
+        match self.state.upgrade() {
+            Some(state) => state.window.is_visible(),
+            None => false,
+        }
+    }
+
     pub fn make_cursor(&self, _desc: &CursorDesc) -> Option<Cursor> {
         if let Some(state) = self.state.upgrade() {
             if let Some(gdk_window) = state.window.window() {
@@ -1508,6 +1583,7 @@ fn make_key_event(key: &EventKey, repeat: bool, state: KeyState) -> KeyEvent {
         key,
         code,
         location,
+        hardware_keycode: Some(hardware_keycode.into()),
         mods,
         repeat,
         is_composing,
@@ -27,6 +27,7 @@ use lazy_static::lazy_static;
 use objc::declare::ClassDecl;
 use objc::runtime::{Class, Object, Sel};
 use objc::{class, msg_send, sel, sel_impl};
+use raw_window_handle::{AppKitDisplayHandle, HasRawDisplayHandle, RawDisplayHandle};
 
 use crate::application::AppHandler;
 use crate::common_util::{shared_queue, SharedDequeuer, SharedEnqueuer};
@@ -165,6 +166,12 @@ impl crate::platform::mac::ApplicationExt for crate::Application {
 
 type MainThreadCb = Box<dyn FnOnce(Option<&mut dyn AppHandler>) + Send>;
 
+unsafe impl HasRawDisplayHandle for Application {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        RawDisplayHandle::AppKit(AppKitDisplayHandle::empty())
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct AppHandle {
     enqueuer: SharedEnqueuer<MainThreadCb>,
@@ -188,6 +195,18 @@ impl AppHandle {
             }
         }
     }
+
+    pub fn add_idle_callback<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut dyn AppHandler) + Send + 'static,
+    {
+        // AppKit gives us no hook for when the run loop goes quiet, so these run with the other
+        // main thread callbacks, from `performSelectorOnMainThread`.
+        self.run_on_main(move |handler| match handler {
+            Some(handler) => callback(handler),
+            None => tracing::warn!("dropping idle callback, the application has no handler"),
+        })
+    }
 }
 
 struct DelegateState {
@@ -329,6 +329,7 @@ impl KeyboardState {
                 key,
                 code,
                 location,
+                hardware_keycode: Some(key_code.into()),
                 mods,
                 repeat,
                 is_composing,
@@ -57,15 +57,18 @@ use super::keyboard::{make_modifiers, KeyboardState};
 use super::menu::Menu;
 use super::text_input::NSRange;
 use super::util::{assert_main_thread, make_nsstring};
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::IdleCallback;
 use crate::dialog::{FileDialogOptions, FileDialogType};
 use crate::keyboard_types::KeyState;
 use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
 use crate::region::Region;
 use crate::scale::Scale;
+use crate::screen::Monitor;
 use crate::text::{Event, InputHandler};
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel, WindowState,
+    DragIcon, FileDialogToken, IdleToken, ResizeEdge, TextFieldToken, TimerToken, WinHandler,
+    WindowLevel, WindowState,
 };
 use crate::Error;
 
@@ -229,6 +232,21 @@ impl WindowBuilder {
         self
     }
 
+    // TODO: Implement this
+    pub fn max_size(self, _size: Size) -> Self {
+        self
+    }
+
+    // TODO: Implement this
+    pub fn app_id(self, _app_id: impl Into<String>) -> Self {
+        self
+    }
+
+    pub fn parent(self, _parent: &WindowHandle) -> Self {
+        // TODO: Implement this
+        self
+    }
+
     pub fn resizable(mut self, resizable: bool) -> Self {
         self.resizable = resizable;
         self
@@ -1202,6 +1220,20 @@ impl WindowHandle {
         }
     }
 
+    // TODO: Implement this
+    pub fn set_cursor_visible(&self, _visible: bool) {}
+
+    // TODO: Implement this
+    pub fn set_input_region(&self, _region: Option<Region>) {}
+
+    // TODO: Implement this
+    pub fn start_drag(&self, _formats: &[ClipboardFormat], _icon: Option<DragIcon>) {}
+
+    // TODO: Implement this
+    pub fn is_visible(&self) -> bool {
+        true
+    }
+
     pub fn make_cursor(&self, _cursor_desc: &CursorDesc) -> Option<Cursor> {
         tracing::warn!("Custom cursors are not yet supported in the macOS backend");
         None
@@ -1327,6 +1359,26 @@ impl WindowHandle {
     // TODO: Implement this
     pub fn show_titlebar(&self, _show_titlebar: bool) {}
 
+    // TODO: Implement this
+    pub fn lock_pointer(&self) {}
+
+    pub fn unlock_pointer(&self) {}
+
+    // TODO: Implement this
+    pub fn begin_resize_drag(&self, _edge: ResizeEdge) {}
+
+    // TODO: Implement this
+    pub fn set_min_size(&self, _size: Size) {}
+
+    // TODO: Implement this
+    pub fn set_max_size(&self, _size: Option<Size>) {}
+
+    // TODO: Implement this
+    pub fn set_app_id(&self, _app_id: &str) {}
+
+    // TODO: Implement this
+    pub fn set_fullscreen(&self, _fullscreen: bool, _monitor: Option<Monitor>) {}
+
     // Need to translate mac y coords, as they start from bottom left
     pub fn set_position(&self, mut position: Point) {
         // TODO: Maybe @cmyr can get this into a state where modal windows follow the parent?
@@ -1452,6 +1504,9 @@ impl WindowHandle {
                     let () = msg_send![window, deminiaturize: self];
                 }
                 (WindowState::Restored, WindowState::Restored) => {} // Can't be reached
+                // TODO: Implement this
+                (WindowState::Fullscreen, _) | (WindowState::Restored, WindowState::Fullscreen) => {
+                }
             }
         }
     }
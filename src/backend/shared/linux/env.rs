@@ -22,18 +22,48 @@ pub fn locale() -> String {
 
     // from gettext manual
     // https://www.gnu.org/software/gettext/manual/html_node/Locale-Environment-Variables.html#Locale-Environment-Variables
-    let mut locale = locale_env_var("LANGUAGE")
-        // the LANGUAGE value is priority list separated by :
-        // See: https://www.gnu.org/software/gettext/manual/html_node/The-LANGUAGE-variable.html#The-LANGUAGE-variable
-        .and_then(|locale| locale.split(':').next().map(String::from))
-        .or_else(|| locale_env_var("LC_ALL"))
+    let locale = locale_env_var("LC_ALL")
         .or_else(|| locale_env_var("LC_MESSAGES"))
-        .or_else(|| locale_env_var("LANG"))
-        .unwrap_or_else(|| "en-US".to_string());
+        .or_else(|| locale_env_var("LANG"));
+    // LANGUAGE takes priority over the others, but gettext ignores it if the locale is unset or
+    // "C", and so do we.
+    // See: https://www.gnu.org/software/gettext/manual/html_node/The-LANGUAGE-variable.html#The-LANGUAGE-variable
+    let locale = match locale {
+        Some(locale) if !is_c_locale(&locale) => locale_env_var("LANGUAGE")
+            // the LANGUAGE value is priority list separated by :
+            .and_then(|locale| locale.split(':').next().map(String::from))
+            .unwrap_or(locale),
+        _ => return "en-US".to_string(),
+    };
+    normalize(&locale)
+}
+
+fn is_c_locale(locale: &str) -> bool {
+    locale == "C" || locale == "POSIX" || locale.starts_with("C.")
+}
+
+/// Turn a POSIX locale like `de_DE.UTF-8@euro` into a BCP 47 tag like `de-DE`.
+fn normalize(locale: &str) -> String {
+    // The codeset and modifier have no equivalent in a language tag.
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    if locale.is_empty() || is_c_locale(locale) {
+        return "en-US".to_string();
+    }
+    locale.replace('_', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // This is done because the locale parsing library we use expects an unicode locale, but these vars have an ISO locale
-    if let Some(idx) = locale.chars().position(|c| c == '.' || c == '@') {
-        locale.truncate(idx);
+    #[test]
+    fn normalize_posix_locales() {
+        assert_eq!(normalize("de_DE.UTF-8"), "de-DE");
+        assert_eq!(normalize("sr_RS@latin"), "sr-RS");
+        assert_eq!(normalize("pt_BR"), "pt-BR");
+        assert_eq!(normalize("fr"), "fr");
+        assert_eq!(normalize("C.UTF-8"), "en-US");
+        assert_eq!(normalize("POSIX"), "en-US");
+        assert_eq!(normalize(".UTF-8"), "en-US");
     }
-    locale
 }
@@ -1,6 +1,6 @@
 #![allow(non_upper_case_globals)]
 
-use keyboard_types::Key;
+use keyboard_types::{Key, Location};
 
 use super::xkbcommon_sys::*;
 
@@ -193,3 +193,25 @@ pub fn map_key(keysym: u32) -> Key {
         _ => Unidentified,
     }
 }
+
+/// The location of the key which produced `keysym`, if the keysym tells us.
+///
+/// Only keys which come in more than one place have a location, the rest are left to the
+/// physical key.
+pub fn map_location(keysym: u32) -> Option<Location> {
+    match keysym {
+        XKB_KEY_Control_L | XKB_KEY_Shift_L | XKB_KEY_Alt_L | XKB_KEY_Super_L | XKB_KEY_Meta_L
+        | XKB_KEY_Hyper_L => Some(Location::Left),
+        XKB_KEY_Control_R | XKB_KEY_Shift_R | XKB_KEY_Alt_R | XKB_KEY_Super_R | XKB_KEY_Meta_R
+        | XKB_KEY_Hyper_R => Some(Location::Right),
+        XKB_KEY_KP_0 | XKB_KEY_KP_1 | XKB_KEY_KP_2 | XKB_KEY_KP_3 | XKB_KEY_KP_4 | XKB_KEY_KP_5
+        | XKB_KEY_KP_6 | XKB_KEY_KP_7 | XKB_KEY_KP_8 | XKB_KEY_KP_9 | XKB_KEY_KP_Add
+        | XKB_KEY_KP_Begin | XKB_KEY_KP_Decimal | XKB_KEY_KP_Delete | XKB_KEY_KP_Divide
+        | XKB_KEY_KP_Down | XKB_KEY_KP_End | XKB_KEY_KP_Enter | XKB_KEY_KP_Equal
+        | XKB_KEY_KP_F1 | XKB_KEY_KP_F2 | XKB_KEY_KP_F3 | XKB_KEY_KP_F4 | XKB_KEY_KP_Home
+        | XKB_KEY_KP_Insert | XKB_KEY_KP_Left | XKB_KEY_KP_Multiply | XKB_KEY_KP_Next
+        | XKB_KEY_KP_Prior | XKB_KEY_KP_Right | XKB_KEY_KP_Separator | XKB_KEY_KP_Space
+        | XKB_KEY_KP_Subtract | XKB_KEY_KP_Tab | XKB_KEY_KP_Up => Some(Location::Numpad),
+        _ => None,
+    }
+}
@@ -22,6 +22,7 @@ use crate::{
 };
 use keyboard_types::{Code, Key};
 use std::convert::TryFrom;
+use std::ffi::CString;
 use std::os::raw::c_char;
 use xkbcommon_sys::*;
 
@@ -92,7 +93,7 @@ impl Context {
         if state.is_null() {
             return None;
         }
-        Some(State::new(keymap, state))
+        Some(State::new(keymap, state, self.compose_state()))
     }
 
     #[cfg(feature = "wayland")]
@@ -101,7 +102,37 @@ impl Context {
         if state.is_null() {
             return None;
         }
-        Some(State::new(keymap, state))
+        Some(State::new(keymap, state, self.compose_state()))
+    }
+
+    /// Load the compose sequences for the user's locale, and start composing with them.
+    ///
+    /// Returns `None` if the locale has no compose sequences, then keys are never composed.
+    fn compose_state(&self) -> Option<ComposeState> {
+        // The same variables, in the same order, as `setlocale` looks at.
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|locale| !locale.is_empty()))
+            .unwrap_or_else(|| "C".into());
+        let locale = CString::new(locale).ok()?;
+        unsafe {
+            let table = xkb_compose_table_new_from_locale(
+                self.0,
+                locale.as_ptr(),
+                XKB_COMPOSE_COMPILE_NO_FLAGS,
+            );
+            if table.is_null() {
+                tracing::debug!("no compose sequences for the locale {:?}", locale);
+                return None;
+            }
+            let state = xkb_compose_state_new(table, XKB_COMPOSE_STATE_NO_FLAGS);
+            // The state keeps its own reference to the table.
+            xkb_compose_table_unref(table);
+            if state.is_null() {
+                return None;
+            }
+            Some(ComposeState(state))
+        }
     }
     /// Create a keymap from some given data.
     ///
@@ -163,6 +194,14 @@ impl Drop for Context {
 
 pub struct Keymap(*mut xkb_keymap);
 
+impl Keymap {
+    /// Whether holding down the key with this scancode should repeat it.
+    #[cfg(feature = "wayland")]
+    pub fn repeats(&self, scancode: u32) -> bool {
+        unsafe { xkb_keymap_key_repeats(self.0, scancode) != 0 }
+    }
+}
+
 impl Clone for Keymap {
     fn clone(&self) -> Self {
         Self(unsafe { xkb_keymap_ref(self.0) })
@@ -180,6 +219,66 @@ impl Drop for Keymap {
 pub struct State {
     state: *mut xkb_state,
     mods: ModsIndices,
+    compose: Option<ComposeState>,
+}
+
+/// Where we are in a compose sequence, like a dead key followed by the letter it accents.
+pub struct ComposeState(*mut xkb_compose_state);
+
+impl ComposeState {
+    /// Feed in the keysym of a pressed key, returning the key to send instead of it, if any.
+    ///
+    /// Keys in the middle of a sequence, or which cancel one, become [`Key::Dead`]. The key that
+    /// finishes a sequence becomes what it composed.
+    fn feed(&mut self, keysym: u32) -> Option<Key> {
+        unsafe {
+            if xkb_compose_state_feed(self.0, keysym) == XKB_COMPOSE_FEED_IGNORED {
+                // Modifiers don't take part in sequences.
+                return None;
+            }
+            match xkb_compose_state_get_status(self.0) {
+                XKB_COMPOSE_COMPOSING => Some(Key::Dead),
+                XKB_COMPOSE_CANCELLED => {
+                    xkb_compose_state_reset(self.0);
+                    Some(Key::Dead)
+                }
+                XKB_COMPOSE_COMPOSED => {
+                    let key = self.composed();
+                    xkb_compose_state_reset(self.0);
+                    Some(key)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    /// The key a finished sequence composed.
+    fn composed(&self) -> Key {
+        let mut buf = [0u8; 64];
+        let len = unsafe {
+            xkb_compose_state_get_utf8(self.0, buf.as_mut_ptr() as *mut c_char, buf.len())
+        };
+        // The length doesn't include the nul, and is what it would have been if it was cut off.
+        let len = (len.max(0) as usize).min(buf.len() - 1);
+        match std::str::from_utf8(&buf[..len]) {
+            Ok(text) if !text.is_empty() => Key::Character(text.into()),
+            _ => keycodes::map_key(unsafe { xkb_compose_state_get_one_sym(self.0) }),
+        }
+    }
+}
+
+impl Clone for ComposeState {
+    fn clone(&self) -> Self {
+        Self(unsafe { xkb_compose_state_ref(self.0) })
+    }
+}
+
+impl Drop for ComposeState {
+    fn drop(&mut self) {
+        unsafe {
+            xkb_compose_state_unref(self.0);
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -203,7 +302,7 @@ pub struct ActiveModifiers {
 }
 
 impl State {
-    pub fn new(keymap: &Keymap, state: *mut xkb_state) -> Self {
+    pub fn new(keymap: &Keymap, state: *mut xkb_state, compose: Option<ComposeState>) -> Self {
         let keymap = keymap.0;
         let mod_idx = |str: &'static [u8]| unsafe {
             xkb_keymap_mod_get_index(keymap, str.as_ptr() as *mut c_char)
@@ -218,6 +317,7 @@ impl State {
                 caps_lock: mod_idx(XKB_MOD_NAME_CAPS),
                 num_lock: mod_idx(XKB_MOD_NAME_NUM),
             },
+            compose,
         }
     }
 
@@ -239,15 +339,36 @@ impl State {
         let code = u16::try_from(scancode)
             .map(hardware_keycode_to_code)
             .unwrap_or(Code::Unidentified);
-        let key = self.get_logical_key(scancode);
-        // TODO this is lazy - really should use xkb i.e. augment the get_logical_key method.
-        let location = code_to_location(code);
+        let keysym = self.key_get_one_sym(scancode);
+        let mut key = self.get_logical_key(keysym);
+        // The keysym knows about remapped modifiers, but not every key has one.
+        let location = keycodes::map_location(keysym).unwrap_or_else(|| code_to_location(code));
+
+        // Editors should ignore the keys of an unfinished compose sequence, and only insert what
+        // it composes.
+        let mut is_composing = false;
+        if state == KeyState::Down {
+            if let Some(composed) = self.compose.as_mut().and_then(|c| c.feed(keysym)) {
+                is_composing = composed == Key::Dead;
+                key = composed;
+            }
+        }
 
-        // TODO not sure how to get this
-        let is_composing = false;
+        KeyEvent {
+            state,
+            key,
+            code,
+            location,
+            hardware_keycode: Some(scancode),
+            mods: self.modifiers(),
+            repeat,
+            is_composing,
+        }
+    }
 
+    /// The currently active modifiers, including latched and locked ones.
+    pub fn modifiers(&self) -> Modifiers {
         let mut mods = Modifiers::empty();
-        // Update xkb's state (e.g. return capitals if we've pressed shift)
         unsafe {
             // compiler will unroll this loop
             for (idx, mod_) in [
@@ -263,19 +384,10 @@ impl State {
                 }
             }
         }
-        KeyEvent {
-            state,
-            key,
-            code,
-            location,
-            mods,
-            repeat,
-            is_composing,
-        }
+        mods
     }
 
-    fn get_logical_key(&mut self, scancode: u32) -> Key {
-        let keysym = self.key_get_one_sym(scancode);
+    fn get_logical_key(&mut self, keysym: u32) -> Key {
         let mut key = keycodes::map_key(keysym);
         if matches!(key, Key::Unidentified) {
             if let Some(s) = self.key_get_utf8(keysym) {
@@ -313,6 +425,7 @@ impl Clone for State {
         Self {
             state: unsafe { xkb_state_ref(self.state) },
             mods: self.mods,
+            compose: self.compose.clone(),
         }
     }
 }
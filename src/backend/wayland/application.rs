@@ -15,8 +15,8 @@
 #![allow(clippy::single_match)]
 
 use super::{
-    clipboard, display, error::Error, events::WaylandSource, keyboard, outputs, pointers, surfaces,
-    window::WindowHandle,
+    clipboard, display, error::Error, events::WaylandSource, keyboard, outputs, pointers,
+    seat::Seat, surfaces, window::WindowHandle,
 };
 
 use crate::{backend, mouse, AppHandler, TimerToken};
@@ -26,24 +26,34 @@ use calloop;
 use std::{
     cell::{Cell, RefCell},
     collections::{BTreeMap, BinaryHeap},
+    os::unix::io::RawFd,
     rc::Rc,
     time::{Duration, Instant},
 };
 
 use crate::backend::shared::linux;
-use wayland_client::protocol::wl_keyboard::WlKeyboard;
+use raw_window_handle::{HasRawDisplayHandle, RawDisplayHandle, WaylandDisplayHandle};
 use wayland_client::protocol::wl_registry;
 use wayland_client::{
     self as wl,
     protocol::{
         wl_compositor::WlCompositor,
-        wl_pointer::WlPointer,
-        wl_seat::{self, WlSeat},
+        wl_region::WlRegion,
+        wl_seat::WlSeat,
         wl_shm::{self, WlShm},
+        wl_subcompositor::WlSubcompositor,
         wl_surface::WlSurface,
     },
 };
-use wayland_cursor::CursorTheme;
+use wayland_protocols::presentation_time::client::wp_presentation::{self, WpPresentation};
+use wayland_protocols::staging::xdg_activation::v1::client::xdg_activation_v1::XdgActivationV1;
+use wayland_protocols::unstable::pointer_constraints::v1::client::zwp_pointer_constraints_v1::ZwpPointerConstraintsV1;
+use wayland_protocols::unstable::pointer_gestures::v1::client::zwp_pointer_gestures_v1::ZwpPointerGesturesV1;
+use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1;
+use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_manager_v2::ZwpTabletManagerV2;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3;
+use wayland_protocols::unstable::xdg_decoration::v1::client::zxdg_decoration_manager_v1::ZxdgDecorationManagerV1;
+use wayland_protocols::viewporter::client::wp_viewporter::WpViewporter;
 use wayland_protocols::wlr::unstable::layer_shell::v1::client::zwlr_layer_shell_v1::ZwlrLayerShellV1;
 use wayland_protocols::xdg_shell::client::xdg_positioner::XdgPositioner;
 use wayland_protocols::xdg_shell::client::xdg_surface;
@@ -93,6 +103,16 @@ pub struct Application {
 pub(crate) struct Data {
     pub(super) wayland: std::rc::Rc<display::Environment>,
     pub(super) zwlr_layershell_v1: Option<wl::Main<ZwlrLayerShellV1>>,
+    pub(super) zxdg_decoration_manager_v1: Option<wl::Main<ZxdgDecorationManagerV1>>,
+    pub(super) wl_subcompositor: Option<wl::Main<WlSubcompositor>>,
+    pub(super) zwp_text_input_manager_v3: Option<wl::Main<ZwpTextInputManagerV3>>,
+    pub(super) zwp_tablet_manager_v2: Option<wl::Main<ZwpTabletManagerV2>>,
+    pub(super) zwp_pointer_gestures_v1: Option<wl::Main<ZwpPointerGesturesV1>>,
+    pub(super) zwp_pointer_constraints_v1: Option<wl::Main<ZwpPointerConstraintsV1>>,
+    pub(super) zwp_relative_pointer_manager_v1: Option<wl::Main<ZwpRelativePointerManagerV1>>,
+    pub(super) xdg_activation_v1: Option<wl::Main<XdgActivationV1>>,
+    pub(super) wp_viewporter: Option<wl::Main<WpViewporter>>,
+    pub(super) wp_presentation: Option<wl::Main<WpPresentation>>,
     pub(super) wl_compositor: wl::Main<WlCompositor>,
     pub(super) wl_shm: wl::Main<WlShm>,
     /// A map of wayland object IDs to outputs.
@@ -114,6 +134,8 @@ pub(crate) struct Data {
     pub(super) shutdown: Cell<bool>,
     /// The currently active surface, if any (by wayland object ID)
     pub(super) active_surface_id: RefCell<std::collections::VecDeque<u64>>,
+    /// The window with keyboard focus, if any, which gets the key events.
+    pub(super) keyboard_focus: Cell<Option<u64>>,
     // Stuff for timers
     /// A calloop event source for timers. We always set it to fire at the next set timer, if any.
     pub(super) timer_handle: calloop::timer::TimerHandle<TimerToken>,
@@ -132,14 +154,26 @@ pub(crate) struct Data {
     /// reference to the pointer events manager.
     pub(super) pointer: pointers::Pointer,
     /// reference to the keyboard events manager.
-    keyboard: keyboard::Manager,
-    clipboard: clipboard::Manager,
+    pub(super) keyboard: keyboard::Manager,
+    pub(super) clipboard: clipboard::Manager,
     // wakeup events when outputs are added/removed.
     outputsqueue: RefCell<Option<calloop::channel::Channel<outputs::Event>>>,
+    /// The handler given to `Application::run`, which gets the callbacks from `AppHandle`.
+    pub(super) handler: RefCell<Option<Box<dyn AppHandler>>>,
+    /// Callbacks sent from other threads with `AppHandle::run_on_main`.
+    appqueue: RefCell<Option<calloop::channel::Channel<AppCallback>>>,
+    appsender: calloop::channel::Sender<AppCallback>,
+    /// Callbacks added with `AppHandle::add_idle_callback`, which don't wake the loop.
+    idle_callbacks: std::sync::Arc<std::sync::Mutex<Vec<IdleCallback>>>,
 }
 
 impl Application {
     pub fn new() -> Result<Self, Error> {
+        Self::connect(wl::Display::connect_to_env()?)
+    }
+
+    /// Set up the application on an existing connection to a compositor.
+    pub(super) fn connect(connection: wl::Display) -> Result<Self, Error> {
         tracing::info!("wayland application initiated");
 
         // Global objects that can come and go (so we must handle them dynamically).
@@ -156,10 +190,12 @@ impl Application {
         // it is populated by the server. Doesn't take ownership of the registry, we are
         // responsible for keeping it alive.
         let weak_seats = Rc::downgrade(&seats);
+        // Seats added after startup need the application to listen to them.
+        let late_appdata: Rc<RefCell<std::sync::Weak<Data>>> = Default::default();
 
         display::GlobalEventDispatch::subscribe(
             &dispatcher,
-            move |event: &'_ wl::GlobalEvent,
+            with_cloned!(late_appdata; move |event: &'_ wl::GlobalEvent,
                   registry: &'_ wl::Attached<wl_registry::WlRegistry>,
                   _ctx: &'_ wl::DispatchData| {
                 match event {
@@ -177,34 +213,49 @@ impl Application {
 
                         tracing::debug!("seat detected {:?} {:?} {:?}", interface, id, version);
 
-                        // 7 is the max version supported by wayland-rs 0.29.5
+                        // 7 is the max version supported by wayland-rs 0.29.5. High resolution
+                        // scrolling (`axis_value120`) needs version 8, so until we upgrade, those
+                        // wheels are reported through the `axis` events instead.
                         let version = version.min(7);
                         let new_seat = registry.bind::<WlSeat>(version, id);
+                        let seat = Rc::new(RefCell::new(Seat::new(new_seat)));
                         let prev_seat = weak_seats
                             .upgrade()
                             .unwrap()
                             .borrow_mut()
-                            .insert(id, Rc::new(RefCell::new(Seat::new(new_seat))));
+                            .insert(id, seat.clone());
                         assert!(
                             prev_seat.is_none(),
                             "internal: wayland should always use new IDs"
                         );
 
-                        // TODO: This code handles only app startup, but seats can come and go on the fly,
-                        // so we have to handle that in the future
-
-                        // Defer setting up the pointer/keyboard event handling until we've
-                        // finished constructing the `Application`. That way we can pass it as a
-                        // parameter.
+                        // During startup, setting up the pointer/keyboard event handling is
+                        // deferred until we've finished constructing the `Application`. That way
+                        // we can pass it as a parameter.
+                        if let Some(appdata) = late_appdata.borrow().upgrade() {
+                            Seat::listen(&appdata, id, &seat);
+                        }
                     }
-                    wl::GlobalEvent::Removed { .. } => {
-                        // nothing to do.
+                    wl::GlobalEvent::Removed { id, interface } => {
+                        if interface.as_str() != "wl_seat" {
+                            return;
+                        }
+
+                        tracing::debug!("seat removed {:?} {:?}", interface, id);
+                        let seat = match weak_seats.upgrade() {
+                            Some(seats) => seats.borrow_mut().remove(id),
+                            None => return,
+                        };
+                        if let (Some(seat), Some(appdata)) = (seat, late_appdata.borrow().upgrade())
+                        {
+                            seat.borrow_mut().release(&appdata, *id);
+                        }
                     }
                 };
-            },
+            }),
         );
 
-        let env = display::new(dispatcher)?;
+        let env = display::connect(dispatcher, connection)?;
         display::print(&env.registry);
 
         let zwlr_layershell_v1 = env
@@ -218,6 +269,137 @@ impl Application {
                 Some,
             );
 
+        // Without the decoration manager, we always draw the decorations ourselves.
+        let zxdg_decoration_manager_v1 = env
+            .registry
+            .instantiate_exact::<ZxdgDecorationManagerV1>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate decoration manager {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        let wl_subcompositor = env
+            .registry
+            .instantiate_exact::<WlSubcompositor>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate subcompositor {:?}", e);
+                    None
+                },
+                Some,
+            );
+        // We draw the fallback decorations in a subsurface, so say once here if neither way of
+        // getting a titlebar works, rather than for every window.
+        if zxdg_decoration_manager_v1.is_none() && wl_subcompositor.is_none() {
+            tracing::warn!(
+                "the compositor supports neither server side decorations nor subsurfaces, windows will have no titlebar"
+            );
+        }
+
+        // Without text inputs there's no IME support, but typing still works.
+        let zwp_text_input_manager_v3 = env
+            .registry
+            .instantiate_exact::<ZwpTextInputManagerV3>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate text input manager {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        let zwp_tablet_manager_v2 = env
+            .registry
+            .instantiate_exact::<ZwpTabletManagerV2>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate tablet manager {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        let zwp_pointer_gestures_v1 = env
+            .registry
+            .instantiate_exact::<ZwpPointerGesturesV1>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate pointer gestures {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        // Both are needed for pointer locking, see `WindowHandle::lock_pointer`.
+        let zwp_pointer_constraints_v1 = env
+            .registry
+            .instantiate_exact::<ZwpPointerConstraintsV1>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate pointer constraints {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        let zwp_relative_pointer_manager_v1 = env
+            .registry
+            .instantiate_exact::<ZwpRelativePointerManagerV1>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate relative pointer manager {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        // Without activation, windows can't ask to be raised and focused.
+        let xdg_activation_v1 = env
+            .registry
+            .instantiate_exact::<XdgActivationV1>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate activation {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        // Without viewports, the buffer scale tells the compositor how big our buffers are.
+        let wp_viewporter = env
+            .registry
+            .instantiate_exact::<WpViewporter>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate viewporter {:?}", e);
+                    None
+                },
+                Some,
+            );
+
+        let wp_presentation = env
+            .registry
+            .instantiate_exact::<WpPresentation>(1)
+            .map_or_else(
+                |e| {
+                    tracing::info!("unable to instantiate presentation {:?}", e);
+                    None
+                },
+                Some,
+            );
+        if let Some(presentation) = &wp_presentation {
+            // We only pass on differences between times, so the clock doesn't matter.
+            presentation.quick_assign(|_, event, _| match event {
+                wp_presentation::Event::ClockId { clk_id } => {
+                    tracing::debug!("presentation clock {}", clk_id)
+                }
+                _ => tracing::warn!("unhandled presentation event {:?}", event),
+            });
+        }
+
         let wl_compositor = env
             .registry
             .instantiate_range::<WlCompositor>(1, 5)
@@ -227,19 +409,28 @@ impl Application {
             .instantiate_exact::<WlShm>(1)
             .map_err(|e| Error::global("wl_shm", 1, e))?;
 
-        let timer_source = calloop::timer::Timer::new().unwrap();
+        let timer_source = calloop::timer::Timer::new().map_err(Error::fatal)?;
         let timer_handle = timer_source.handle();
+        let (appsender, appqueue) = calloop::channel::channel();
 
-        // TODO the cursor theme size needs more refinement, it should probably be the size needed to
-        // draw sharp cursors on the largest scaled monitor.
-        let pointer = pointers::Pointer::new(
-            CursorTheme::load(64, &wl_shm),
-            wl_compositor.create_surface(),
-        );
+        let pointer = pointers::Pointer::new(wl_shm.clone());
+
+        let keyboard = keyboard::Manager::default();
+        let clipboard = clipboard::Manager::new(&env.display, &env.registry, keyboard.serial())?;
 
         // We need to have keyboard events set up for our seats before the next roundtrip.
         let appdata = std::sync::Arc::new(Data {
             zwlr_layershell_v1,
+            zxdg_decoration_manager_v1,
+            wl_subcompositor,
+            zwp_text_input_manager_v3,
+            zwp_tablet_manager_v2,
+            zwp_pointer_gestures_v1,
+            zwp_pointer_constraints_v1,
+            zwp_relative_pointer_manager_v1,
+            xdg_activation_v1,
+            wp_viewporter,
+            wp_presentation,
             wl_compositor,
             wl_shm: wl_shm.clone(),
             outputs: Rc::new(RefCell::new(BTreeMap::new())),
@@ -248,15 +439,20 @@ impl Application {
             formats: RefCell::new(vec![]),
             shutdown: Cell::new(false),
             active_surface_id: RefCell::new(std::collections::VecDeque::with_capacity(20)),
+            keyboard_focus: Cell::new(None),
             timer_handle,
             timer_source: RefCell::new(Some(timer_source)),
             timers: RefCell::new(BinaryHeap::new()),
             display_flushed: RefCell::new(false),
             pointer,
-            keyboard: keyboard::Manager::default(),
-            clipboard: clipboard::Manager::new(&env.display, &env.registry)?,
+            keyboard,
+            clipboard,
             roundtrip_requested: RefCell::new(false),
             outputsqueue: RefCell::new(Some(outputqueue)),
+            handler: RefCell::new(None),
+            appqueue: RefCell::new(Some(appqueue)),
+            appsender,
+            idle_callbacks: Default::default(),
             wayland: std::rc::Rc::new(env),
         });
 
@@ -269,51 +465,12 @@ impl Application {
             }
         }));
 
-        // Setup seat event listeners with our application
+        // Setup seat event listeners with our application, seats which come later are set up as
+        // they're added.
         for (id, seat) in appdata.seats.borrow().iter() {
-            let id = *id; // move into closure.
-            let wl_seat = seat.borrow().wl_seat.clone();
-            wl_seat.quick_assign(with_cloned!(seat, appdata; move |d1, event, d3| {
-                tracing::debug!("seat events {:?} {:?} {:?}", d1, event, d3);
-                let mut seat = seat.borrow_mut();
-                appdata.clipboard.attach(&mut seat);
-                match event {
-                    wl_seat::Event::Capabilities { capabilities } => {
-                        seat.capabilities = capabilities;
-                        if capabilities.contains(wl_seat::Capability::Keyboard)
-                            && seat.keyboard.is_none()
-                        {
-                            seat.keyboard = Some(appdata.keyboard.attach(id, seat.wl_seat.clone()));
-                        }
-                        if capabilities.contains(wl_seat::Capability::Pointer)
-                            && seat.pointer.is_none()
-                        {
-                            let pointer = seat.wl_seat.get_pointer();
-                            appdata.pointer.attach(pointer.detach());
-                            pointer.quick_assign({
-                                let app = appdata.clone();
-                                move |pointer, event, _| {
-                                    pointers::Pointer::consume(app.clone(), pointer.detach(), event);
-                                }
-                            });
-                            seat.pointer = Some(pointer);
-                        }
-
-                        // TODO: We should react to capability removal,
-                        // "if a seat regains the pointer capability
-                        // and a client has a previously obtained wl_pointer object
-                        // of version 4 or less, that object may start sending pointer events again.
-                        // This behavior is considered a misinterpretation of the intended behavior
-                        // and must not be relied upon by the client",
-                        // versions 5 up guarantee that events will not be sent for sure
-                    }
-                    wl_seat::Event::Name { name } => {
-                        seat.name = name;
-                    }
-                    _ => tracing::info!("seat quick assign unknown event {:?}", event), // ignore future events
-                }
-            }));
+            Seat::listen(&appdata, *id, seat);
         }
+        late_appdata.replace(std::sync::Arc::downgrade(&appdata));
 
         // Let wayland finish setup before we allow the client to start creating windows etc.
         appdata.sync()?;
@@ -321,8 +478,9 @@ impl Application {
         Ok(Application { data: appdata })
     }
 
-    pub fn run(mut self, _handler: Option<Box<dyn AppHandler>>) {
+    pub fn run(mut self, handler: Option<Box<dyn AppHandler>>) {
         tracing::info!("wayland event loop initiated");
+        self.data.handler.replace(handler);
         // NOTE if we want to call this function more than once, we will need to put the timer
         // source back.
         let timer_source = self.data.timer_source.borrow_mut().take().unwrap();
@@ -355,7 +513,11 @@ impl Application {
                         }
                         outputs::Event::Removed(output) => {
                             tracing::debug!("output removed {:?} {:?}", output.gid, output.id());
-                            appdata.outputs.borrow_mut().remove(&output.id());
+                            let output = appdata
+                                .outputs
+                                .borrow_mut()
+                                .remove(&output.id())
+                                .unwrap_or(output);
                             for (_, win) in appdata.handles_iter() {
                                 surfaces::Outputs::removed(&win, &output);
                             }
@@ -365,6 +527,13 @@ impl Application {
             })
             .unwrap();
 
+        handle
+            .insert_source(
+                self.data.appqueue.take().unwrap(),
+                |evt, _ignored, appdata| run_on_main(&appdata.handler, evt),
+            )
+            .unwrap();
+
         handle
             .insert_source(timer_source, move |token, _metadata, appdata| {
                 tracing::trace!("timer source {:?}", token);
@@ -388,6 +557,7 @@ impl Application {
                 return;
             }
 
+            appdata.run_idle_callbacks();
             Data::idle_repaint(handle.clone());
         });
 
@@ -410,7 +580,45 @@ impl Application {
     }
 
     pub fn get_handle(&self) -> Option<AppHandle> {
-        None
+        Some(AppHandle {
+            sender: self.data.appsender.clone(),
+            idle_callbacks: self.data.idle_callbacks.clone(),
+        })
+    }
+}
+
+impl crate::platform::linux::ApplicationExt for crate::Application {
+    fn primary_clipboard(&self) -> crate::Clipboard {
+        clipboard::Clipboard::primary(&self.backend_app.data.clipboard).into()
+    }
+}
+
+impl crate::platform::wayland::WaylandApplicationExt for crate::Application {
+    fn connection_fd(&self) -> RawFd {
+        self.backend_app.data.wayland.display.get_connection_fd()
+    }
+
+    fn dispatch_pending(&self) -> Result<(), crate::Error> {
+        self.backend_app
+            .data
+            .dispatch_pending()
+            .map_err(|e| Error::fatal(e).into())
+    }
+
+    fn roundtrip(&self) -> Result<(), crate::Error> {
+        self.backend_app
+            .data
+            .roundtrip()
+            .map_err(|e| Error::fatal(e).into())
+    }
+
+    fn active_window(&self) -> Option<crate::WindowHandle> {
+        self.backend_app.data.focused_window().map(Into::into)
+    }
+
+    fn set_multi_click(&self, interval: std::time::Duration, distance: f64) {
+        let limits = pointers::MultiClick { interval, distance };
+        self.backend_app.data.pointer.set_multi_click(limits);
     }
 }
 
@@ -423,6 +631,10 @@ impl surfaces::Compositor for Data {
         self.wl_compositor.create_surface()
     }
 
+    fn create_region(&self) -> wl::Main<WlRegion> {
+        self.wl_compositor.create_region()
+    }
+
     fn shared_mem(&self) -> wl::Main<WlShm> {
         self.wl_shm.clone()
     }
@@ -438,9 +650,32 @@ impl surfaces::Compositor for Data {
     fn zwlr_layershell_v1(&self) -> Option<wl::Main<ZwlrLayerShellV1>> {
         self.zwlr_layershell_v1.clone()
     }
+
+    fn zxdg_decoration_manager_v1(&self) -> Option<wl::Main<ZxdgDecorationManagerV1>> {
+        self.zxdg_decoration_manager_v1.clone()
+    }
+
+    fn wl_subcompositor(&self) -> Option<wl::Main<WlSubcompositor>> {
+        self.wl_subcompositor.clone()
+    }
+
+    fn wp_viewporter(&self) -> Option<wl::Main<WpViewporter>> {
+        self.wp_viewporter.clone()
+    }
+
+    fn wp_presentation(&self) -> Option<wl::Main<WpPresentation>> {
+        self.wp_presentation.clone()
+    }
 }
 
 impl Data {
+    /// The display handle for renderers, which only needs the connection, not a window.
+    pub(super) fn raw_display_handle(&self) -> RawDisplayHandle {
+        let mut handle = WaylandDisplayHandle::empty();
+        handle.display = self.wayland.display.get_display_ptr() as *mut _;
+        RawDisplayHandle::Wayland(handle)
+    }
+
     pub(crate) fn set_cursor(&self, cursor: &mouse::Cursor) {
         self.pointer.replace(cursor);
     }
@@ -459,6 +694,55 @@ impl Data {
         Ok(())
     }
 
+    /// Read and dispatch whatever the compositor has sent, without blocking, then run the work
+    /// that our event loop would run afterwards, and flush our requests.
+    fn dispatch_pending(self: &std::sync::Arc<Self>) -> std::io::Result<()> {
+        let queue = self.wayland.queue.clone();
+        let mut appdata = self.clone();
+        loop {
+            if let Some(guard) = queue.borrow().prepare_read() {
+                if let Err(e) = guard.read_events() {
+                    if e.kind() != std::io::ErrorKind::WouldBlock {
+                        return Err(e);
+                    }
+                }
+            }
+            if super::events::dispatch_pending(&mut queue.borrow_mut(), &mut appdata)? == 0 {
+                break;
+            }
+        }
+        self.after_dispatch()
+    }
+
+    /// Wait for the compositor to handle all of our requests, dispatching what it sends back,
+    /// then run the work that our event loop would run afterwards.
+    pub(super) fn roundtrip(self: &std::sync::Arc<Self>) -> std::io::Result<()> {
+        let mut appdata = self.clone();
+        // Our event handlers could call this, while the queue is already dispatching.
+        let mut queue = self.wayland.queue.try_borrow_mut().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "unable to roundtrip while dispatching events",
+            )
+        })?;
+        super::events::roundtrip(&mut queue, &mut appdata)?;
+        drop(queue);
+        self.after_dispatch()
+    }
+
+    /// Request animation frames and run idle callbacks, then flush our requests.
+    fn after_dispatch(&self) -> std::io::Result<()> {
+        for (_id, winhandle) in self.handles_iter() {
+            winhandle.request_anim_frame();
+            winhandle.run_idle();
+        }
+
+        match self.wayland.display.flush() {
+            Err(e) if e.kind() != std::io::ErrorKind::WouldBlock => Err(e),
+            _ => Ok(()),
+        }
+    }
+
     fn current_window_id(&self) -> u64 {
         static DEFAULT: u64 = 0_u64;
         *self.active_surface_id.borrow().get(0).unwrap_or(&DEFAULT)
@@ -471,6 +755,26 @@ impl Data {
             .cloned()
     }
 
+    /// The window with keyboard focus, if any.
+    pub(super) fn focused_window(&self) -> Option<WindowHandle> {
+        let id = self.keyboard_focus.get()?;
+        self.handles.borrow().get(&id).cloned()
+    }
+
+    /// Find the window which owns the given surface, if any.
+    pub(super) fn find_window(&self, surface: &WlSurface) -> Option<WindowHandle> {
+        self.handles_iter()
+            .map(|(_, w)| w)
+            .find(|w| matches!(w.data(), Some(data) if data.owns_surface(surface)))
+    }
+
+    /// Find the decorations which own the given surface, if any.
+    pub(super) fn find_frame(&self, surface: &WlSurface) -> Option<surfaces::decorations::Frame> {
+        self.handles_iter()
+            .filter_map(|(_, w)| w.frame())
+            .find(|frame| frame.owns_surface(surface))
+    }
+
     fn handle_timer_event(&self, _token: TimerToken) {
         // Don't borrow the timers in case the callbacks want to add more.
         let mut expired_timers = Vec::with_capacity(1);
@@ -485,18 +789,18 @@ impl Data {
             let win = match self.handles.borrow().get(&expired.id()).cloned() {
                 Some(s) => s,
                 None => {
-                    // NOTE this might be expected
-                    tracing::warn!(
-                        "received event for surface that doesn't exist any more {:?} {:?}",
-                        expired,
+                    // The window was closed before its timer fired, so there is nobody left to
+                    // tell about it.
+                    tracing::trace!(
+                        "dropping timer {:?} for closed window {:?}",
+                        expired.token(),
                         expired.id()
                     );
                     continue;
                 }
             };
-            // re-entrancy
             if let Some(data) = win.data() {
-                data.handler.borrow_mut().timer(expired.token())
+                data.with_handler(|handler| handler.timer(expired.token()));
             }
         }
 
@@ -521,6 +825,27 @@ impl Data {
         self.handles.borrow().clone().into_iter()
     }
 
+    /// Run the callbacks from `AppHandle::add_idle_callback`, now that the events which were
+    /// waiting have been handled.
+    fn run_idle_callbacks(&self) {
+        // Callbacks added while these run wait for the next time the loop goes quiet.
+        let callbacks = std::mem::take(&mut *self.idle_callbacks.lock().unwrap());
+        if callbacks.is_empty() {
+            return;
+        }
+        match &mut *self.handler.borrow_mut() {
+            Some(handler) => {
+                for callback in callbacks {
+                    callback(&mut **handler);
+                }
+            }
+            None => tracing::warn!(
+                "dropping {} idle callbacks, the application has no handler",
+                callbacks.len()
+            ),
+        }
+    }
+
     fn idle_repaint(loophandle: calloop::LoopHandle<'_, std::sync::Arc<Data>>) {
         loophandle.insert_idle({
             move |appdata| {
@@ -556,35 +881,89 @@ impl From<std::sync::Arc<Data>> for surfaces::CompositorHandle {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Seat {
-    pub(super) wl_seat: wl::Main<WlSeat>,
-    name: String,
-    capabilities: wl_seat::Capability,
-    keyboard: Option<wl::Main<WlKeyboard>>,
-    pointer: Option<wl::Main<WlPointer>>,
-}
-
-impl Seat {
-    fn new(wl_seat: wl::Main<WlSeat>) -> Self {
-        Self {
-            wl_seat,
-            name: "".into(),
-            capabilities: wl_seat::Capability::empty(),
-            keyboard: None,
-            pointer: None,
-        }
+unsafe impl HasRawDisplayHandle for Application {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.data.raw_display_handle()
     }
 }
 
+type AppCallback = Box<dyn FnOnce(Option<&mut dyn AppHandler>) + Send>;
+type IdleCallback = Box<dyn FnOnce(&mut dyn AppHandler) + Send>;
+
 #[derive(Clone)]
-pub struct AppHandle;
+pub struct AppHandle {
+    sender: calloop::channel::Sender<AppCallback>,
+    idle_callbacks: std::sync::Arc<std::sync::Mutex<Vec<IdleCallback>>>,
+}
 
 impl AppHandle {
-    pub fn run_on_main<F>(&self, _callback: F)
+    pub fn run_on_main<F>(&self, callback: F)
     where
         F: FnOnce(Option<&mut dyn AppHandler>) + Send + 'static,
     {
-        todo!()
+        if self.sender.send(Box::new(callback)).is_err() {
+            tracing::warn!("unable to run callback, the event loop has stopped");
+        }
+    }
+
+    pub fn add_idle_callback<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut dyn AppHandler) + Send + 'static,
+    {
+        // The loop wakes up often enough by itself, so this only has to queue the callback.
+        self.idle_callbacks.lock().unwrap().push(Box::new(callback));
+    }
+}
+
+/// Give a callback from `AppHandle::run_on_main` the application's handler.
+fn run_on_main(
+    handler: &RefCell<Option<Box<dyn AppHandler>>>,
+    evt: calloop::channel::Event<AppCallback>,
+) {
+    if let calloop::channel::Event::Msg(callback) = evt {
+        match &mut *handler.borrow_mut() {
+            Some(handler) => callback(Some(&mut **handler)),
+            None => callback(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(Rc<Cell<Option<u32>>>);
+
+    impl AppHandler for Recorder {
+        fn command(&mut self, id: u32) {
+            self.0.set(Some(id));
+        }
+    }
+
+    #[test]
+    fn run_on_main_reaches_handler() {
+        let seen = Rc::new(Cell::new(None));
+        let mut handler: RefCell<Option<Box<dyn AppHandler>>> =
+            RefCell::new(Some(Box::new(Recorder(seen.clone()))));
+
+        let (sender, channel) = calloop::channel::channel();
+        let mut eventloop = calloop::EventLoop::try_new().unwrap();
+        eventloop
+            .handle()
+            .insert_source(channel, |evt, _ignored, handler| run_on_main(handler, evt))
+            .unwrap();
+
+        let handle = AppHandle {
+            sender,
+            idle_callbacks: Default::default(),
+        };
+        std::thread::spawn(move || {
+            handle.run_on_main(|handler| handler.expect("no handler").command(7));
+        })
+        .join()
+        .unwrap();
+
+        eventloop.dispatch(Duration::ZERO, &mut handler).unwrap();
+        assert_eq!(seen.get(), Some(7));
     }
 }
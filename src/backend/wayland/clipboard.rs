@@ -15,22 +15,65 @@
 //! Interactions with the system pasteboard on wayland compositors.
 use super::application;
 use super::error as waylanderr;
+use super::seat;
+use super::surfaces::buffers;
+use super::window::WindowHandle;
 use crate::clipboard::{ClipboardFormat, FormatId};
-use std::io::Read;
+use crate::kurbo::Point;
+use crate::window::{DragIcon, DropAction};
+use nix::poll::{PollFd, PollFlags};
+use std::io::{Read, Write};
 use wayland_client as wl;
 use wayland_client::protocol::wl_data_device;
 use wayland_client::protocol::wl_data_device_manager;
 use wayland_client::protocol::wl_data_offer;
 use wayland_client::protocol::wl_data_source;
+use wayland_client::protocol::wl_shm;
+use wayland_client::protocol::wl_surface;
+use wayland_protocols::unstable::primary_selection::v1::client::{
+    zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1,
+    zwp_primary_selection_device_v1, zwp_primary_selection_offer_v1,
+    zwp_primary_selection_source_v1,
+};
+
+/// An offer from another client, on either the clipboard or the primary selection.
+#[derive(Debug, Clone)]
+enum OfferObject {
+    Clipboard(wl_data_offer::WlDataOffer),
+    Primary(zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1),
+}
+
+impl OfferObject {
+    fn id(&self) -> u32 {
+        match self {
+            OfferObject::Clipboard(o) => o.as_ref().id(),
+            OfferObject::Primary(o) => o.as_ref().id(),
+        }
+    }
+
+    fn receive(&self, mimetype: String, fd: std::os::unix::io::RawFd) {
+        match self {
+            OfferObject::Clipboard(o) => o.receive(mimetype, fd),
+            OfferObject::Primary(o) => o.receive(mimetype, fd),
+        }
+    }
+
+    fn destroy(&self) {
+        match self {
+            OfferObject::Clipboard(o) => o.destroy(),
+            OfferObject::Primary(o) => o.destroy(),
+        }
+    }
+}
 
 #[derive(Clone)]
 struct Offer {
-    wobj: wl::Main<wl_data_offer::WlDataOffer>,
+    wobj: OfferObject,
     mimetype: String,
 }
 
 impl Offer {
-    fn new(d: wl::Main<wl_data_offer::WlDataOffer>, mimetype: impl Into<String>) -> Self {
+    fn new(d: OfferObject, mimetype: impl Into<String>) -> Self {
         Self {
             wobj: d,
             mimetype: mimetype.into(),
@@ -74,18 +117,278 @@ impl From<Vec<Offer>> for Data {
     }
 }
 
+/// The data for each mimetype we offer.
+type Contents = std::rc::Rc<Vec<(String, Vec<u8>)>>;
+
+/// Which selection to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Selection {
+    /// The regular clipboard.
+    Clipboard,
+    /// The primary selection, which is pasted with the middle mouse button.
+    Primary,
+}
+
+/// The state of either the clipboard or the primary selection.
+#[derive(Default)]
+struct SelectionState {
+    /// What other clients offer.
+    offers: std::rc::Rc<std::cell::RefCell<Data>>,
+    /// What we've put on the selection, while we still own it.
+    source: std::rc::Rc<std::cell::RefCell<Option<Contents>>>,
+}
+
+impl SelectionState {
+    fn offered(&self, offer: Offer) {
+        self.offers.borrow().pending.borrow_mut().push(offer);
+    }
+
+    /// Take the mime types of a pending offer which isn't for the selection, i.e. a drag.
+    fn take_offered(&self, id: u32) -> Vec<String> {
+        let data = self.offers.borrow();
+        let mut pending = data.pending.borrow_mut();
+        let mut mimetypes = Vec::new();
+        pending.retain(|offer| {
+            if offer.wobj.id() != id {
+                return true;
+            }
+            mimetypes.push(offer.mimetype.clone());
+            false
+        });
+        mimetypes
+    }
+
+    /// The offer with the given id has become the selection.
+    fn selected(&self, id: Option<u32>) {
+        let data = self.offers.borrow();
+        tracing::debug!(
+            "current data offers {:?} {:?}",
+            data.current.borrow().len(),
+            data.pending.borrow().len()
+        );
+        // Only keep the mime types of the offer which became the selection.
+        let (selected, unused): (Vec<_>, Vec<_>) = data
+            .pending
+            .take()
+            .into_iter()
+            .partition(|offer| Some(offer.wobj.id()) == id);
+        // We're done with the previous selection, and with any offers which didn't become it.
+        let mut destroyed = Vec::new();
+        for offer in data.current.borrow().iter().chain(&unused) {
+            let id = offer.wobj.id();
+            // There is an entry for each mime type of an offer.
+            if !destroyed.contains(&id) {
+                offer.wobj.destroy();
+                destroyed.push(id);
+            }
+        }
+        drop(data);
+        let upd = Data::from(selected);
+        tracing::debug!(
+            "updated data offers {:?} {:?}",
+            upd.current.borrow().len(),
+            upd.pending.borrow().len()
+        );
+        self.offers.replace(upd);
+    }
+}
+
+impl std::fmt::Debug for SelectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SelectionState")
+            .field("offers", &self.offers.borrow())
+            .field("owned", &self.source.borrow().is_some())
+            .finish()
+    }
+}
+
+/// Send our `contents` for `mimetype` to `fd`.
+fn send(contents: &Contents, mimetype: String, fd: std::os::unix::io::RawFd) {
+    // Closed when dropped, which tells the receiver we're done.
+    let mut io: std::fs::File = unsafe { std::os::unix::io::FromRawFd::from_raw_fd(fd) };
+    match contents.iter().find(|(offered, _)| *offered == mimetype) {
+        Some((_, data)) => {
+            if let Err(cause) = io.write_all(data) {
+                tracing::error!("clipboard unable to send content {:?}", cause);
+            }
+        }
+        None => tracing::warn!("clipboard asked for unoffered {:?}", mimetype),
+    }
+}
+
+/// Why we couldn't get the contents of a selection.
+#[derive(Debug)]
+enum ReceiveError {
+    /// Nothing on the selection is offered as the requested type.
+    NoSelection,
+    /// The selection's owner didn't finish sending the contents in time.
+    Timeout,
+    Io(std::io::Error),
+}
+
+impl From<nix::Error> for ReceiveError {
+    fn from(err: nix::Error) -> Self {
+        Self::Io(err.into())
+    }
+}
+
+impl From<std::io::Error> for ReceiveError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Someone else owns the selection now, so forget `contents` (unless they were already replaced).
+fn disown(source: &std::cell::RefCell<Option<Contents>>, contents: &Contents) {
+    let mut current = source.borrow_mut();
+    if matches!(&*current, Some(c) if std::rc::Rc::ptr_eq(c, contents)) {
+        current.take();
+    }
+}
+
+/// Upload `icon` to a new surface, to show under the pointer during a drag from a window with
+/// the given `scale`. The surface still needs committing, once it has the drag icon role.
+fn drag_icon(
+    appdata: &application::Data,
+    icon: &DragIcon,
+    scale: i32,
+) -> Result<wl::Main<wl_surface::WlSurface>, nix::Error> {
+    let (width, height) = (icon.width as i32, icon.height as i32);
+    // The buffer has to be a whole number of points, so odd sizes are shown a pixel per point.
+    let scale = match width % scale == 0 && height % scale == 0 {
+        true => scale,
+        false => 1,
+    };
+    let mut shm = buffers::Shm::new(appdata.wl_shm.clone())?;
+    shm.resize(width * height * 4)?;
+    // Argb8888 is little endian, so each pixel is stored as BGRA.
+    let mut pixels = unsafe { shm.mmap(0, shm.size()) };
+    for (dst, src) in pixels.chunks_exact_mut(4).zip(icon.pixels.chunks_exact(4)) {
+        dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+    }
+    drop(pixels);
+
+    let pool = shm.create_pool();
+    let buffer = pool.create_buffer(0, width, height, width * 4, wl_shm::Format::Argb8888);
+    pool.destroy();
+    // The compositor holds on to the pixels until it releases the buffer.
+    buffer.quick_assign(|buffer, _event, _ignored| buffer.destroy());
+
+    let surface = appdata.wl_compositor.create_surface();
+    surface.set_buffer_scale(scale);
+    // The hot spot is in pixels, but the surface is positioned in points.
+    let hot = (icon.hot.to_vec2() / scale as f64).to_point();
+    surface.attach(Some(&buffer), -hot.x as i32, -hot.y as i32);
+    surface.damage(0, 0, width / scale, height / scale);
+    Ok(surface)
+}
+
+/// Something another client is dragging over one of our windows.
+struct Drag {
+    offer: wl_data_offer::WlDataOffer,
+    window: WindowHandle,
+    /// Serial of the enter event, needed to accept the offer.
+    serial: u32,
+    mimetypes: Vec<String>,
+    pos: Point,
+    /// The mimetype the window would like if the drag is dropped.
+    accepted: Option<String>,
+}
+
+impl Drag {
+    /// Ask the window whether it would take a drop at `pos`, and tell the source.
+    fn moved(&mut self, pos: Point) {
+        self.pos = pos;
+        let mimetypes = &self.mimetypes;
+        let accepted = self
+            .window
+            .data()
+            .and_then(|data| data.with_handler(|handler| handler.dnd_move(pos, mimetypes)))
+            .flatten()
+            .filter(|mimetype| mimetypes.contains(mimetype));
+        if accepted != self.accepted {
+            self.offer.accept(self.serial, accepted.clone());
+            self.accepted = accepted;
+        }
+    }
+
+    fn left(self) {
+        if let Some(data) = self.window.data() {
+            data.with_handler(|handler| handler.dnd_leave());
+        }
+        self.offer.destroy();
+    }
+
+    fn dropped(self, manager: &Manager) {
+        let mimetype = match self.accepted.clone() {
+            Some(mimetype) => mimetype,
+            None => return self.left(),
+        };
+        // Reading from our own drag would wait on ourselves, so answer directly.
+        let own = manager
+            .inner
+            .dragging
+            .borrow()
+            .as_ref()
+            .and_then(|contents| {
+                contents
+                    .iter()
+                    .find(|(offered, _)| *offered == mimetype)
+                    .map(|(_, data)| data.clone())
+            });
+        let offer = Offer::new(OfferObject::Clipboard(self.offer.clone()), mimetype.clone());
+        let contents = match own.map(Ok).unwrap_or_else(|| manager.initiate(offer)) {
+            Ok(contents) if !contents.is_empty() => contents,
+            Ok(_) => return self.left(),
+            Err(cause) => {
+                tracing::warn!("unable to retrieve the dropped {:?}: {:?}", mimetype, cause);
+                return self.left();
+            }
+        };
+        self.offer.finish();
+        self.offer.destroy();
+        if let Some(data) = self.window.data() {
+            data.with_handler(|handler| handler.dnd_drop(self.pos, &mimetype, contents));
+        }
+    }
+}
+
 struct Inner {
     display: wl::Display,
     wobj: wl::Main<wl_data_device_manager::WlDataDeviceManager>,
-    wdsobj: wl::Main<wl_data_source::WlDataSource>,
-    devices: std::rc::Rc<std::cell::RefCell<Data>>,
+    /// The primary selection manager, if the compositor supports it.
+    primary_wobj: Option<wl::Main<ZwpPrimarySelectionDeviceManagerV1>>,
+    /// The data device of each seat.
+    data_devices: std::cell::RefCell<Vec<wl::Main<wl_data_device::WlDataDevice>>>,
+    /// The primary selection device of each seat.
+    primary_devices: std::cell::RefCell<
+        Vec<wl::Main<zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1>>,
+    >,
+    /// Serial of the latest keyboard input, which setting the selection requires.
+    serial: std::rc::Rc<std::cell::Cell<u32>>,
+    clipboard: SelectionState,
+    primary: SelectionState,
+    drag: std::cell::RefCell<Option<Drag>>,
+    /// What we're dragging out of one of our windows, while the drag goes on.
+    dragging: std::rc::Rc<std::cell::RefCell<Option<Contents>>>,
+}
+
+impl Inner {
+    fn selection(&self, selection: Selection) -> &SelectionState {
+        match selection {
+            Selection::Clipboard => &self.clipboard,
+            Selection::Primary => &self.primary,
+        }
+    }
 }
 
 impl std::fmt::Debug for Inner {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("")
             .field("wobj", &self.wobj)
-            .field("wdsobj", &self.wdsobj)
+            .field("primary_wobj", &self.primary_wobj)
+            .field("clipboard", &self.clipboard)
+            .field("primary", &self.primary)
             .finish()
     }
 }
@@ -96,9 +399,13 @@ pub struct Manager {
 }
 
 impl Manager {
+    /// How long to wait for the selection's owner to send us its contents.
+    const RECEIVE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
     pub(super) fn new(
         display: &wl::Display,
         gm: &wl::GlobalManager,
+        serial: std::rc::Rc<std::cell::Cell<u32>>,
     ) -> Result<Self, waylanderr::Error> {
         let m = gm
             .instantiate_exact::<wl_data_device_manager::WlDataDeviceManager>(3)
@@ -108,25 +415,43 @@ impl Manager {
             tracing::info!("clipboard {:?} event {:?}", i, event);
         });
 
-        let ds = m.create_data_source();
-        ds.quick_assign(|i, event, _ignored| {
-            tracing::info!("clipboard {:?} event {:?}", i, event);
-        });
+        // The primary selection is optional, we just won't have one without it.
+        let primary = match gm.instantiate_exact::<ZwpPrimarySelectionDeviceManagerV1>(1) {
+            Ok(primary) => Some(primary),
+            Err(cause) => {
+                tracing::info!("primary selection unavailable {:?}", cause);
+                None
+            }
+        };
 
         Ok(Self {
             inner: std::rc::Rc::new(Inner {
                 wobj: m,
-                wdsobj: ds,
+                primary_wobj: primary,
                 display: display.clone(),
-                devices: Default::default(),
+                data_devices: Default::default(),
+                primary_devices: Default::default(),
+                serial,
+                clipboard: Default::default(),
+                primary: Default::default(),
+                drag: Default::default(),
+                dragging: Default::default(),
             }),
         })
     }
 
-    pub fn attach<'a>(&'a self, seat: &'a mut application::Seat) {
+    pub(super) fn attach<'a>(
+        &'a self,
+        appdata: &std::sync::Arc<application::Data>,
+        seat: &'a mut seat::Seat,
+    ) {
+        if seat.data_device.is_some() {
+            return;
+        }
         let device = self.inner.wobj.get_data_device(&seat.wl_seat);
         device.quick_assign({
             let m = self.inner.clone();
+            let appdata = appdata.clone();
             move |i, event, _ignored| match event {
                 wl_data_device::Event::DataOffer { id } => {
                     let offer = id;
@@ -134,86 +459,386 @@ impl Manager {
                         let m = m.clone();
                         move |i, event, _ignored| match event {
                             wl_data_offer::Event::Offer { mime_type } => {
-                                let data = m.devices.borrow_mut();
-                                let offer = Offer::new(i, mime_type);
-                                data.pending.borrow_mut().push(offer);
+                                let offer =
+                                    Offer::new(OfferObject::Clipboard(i.detach()), mime_type);
+                                m.clipboard.offered(offer);
                             }
+                            // We always ask for a copy, whatever the source would prefer.
+                            wl_data_offer::Event::SourceActions { .. }
+                            | wl_data_offer::Event::Action { .. } => {}
                             _ => tracing::warn!("clipboard unhandled {:?} event {:?}", i, event),
                         }
                     });
                 }
                 wl_data_device::Event::Selection { id } => {
-                    if id.is_some() {
-                        let data = m.devices.borrow();
-                        tracing::debug!(
-                            "current data offers {:?} {:?}",
-                            data.current.borrow().len(),
-                            data.pending.borrow().len()
-                        );
-                        let upd = Data::from(data.pending.take());
-                        drop(data);
-                        tracing::debug!(
-                            "updated data offers {:?} {:?}",
-                            upd.current.borrow().len(),
-                            upd.pending.borrow().len()
-                        );
-                        m.devices.replace(upd);
-                    } else {
-                        let upd = Data::from(Vec::new());
-                        m.devices.replace(upd);
+                    m.clipboard.selected(id.map(|id| id.as_ref().id()));
+                }
+                wl_data_device::Event::Enter {
+                    serial,
+                    surface,
+                    x,
+                    y,
+                    id,
+                } => {
+                    if let Some(drag) = m.drag.take() {
+                        drag.left();
+                    }
+                    // Drags within a client needn't have a source, and we never start those.
+                    let offer = match id {
+                        Some(offer) => offer,
+                        None => return,
+                    };
+                    let window = match appdata.find_window(&surface) {
+                        Some(w) => w,
+                        None => {
+                            tracing::debug!("drag entered an unknown surface {:?}", surface);
+                            offer.destroy();
+                            return;
+                        }
+                    };
+                    let mimetypes = m.clipboard.take_offered(offer.as_ref().id());
+                    offer.set_actions(
+                        wl_data_device_manager::DndAction::Copy
+                            | wl_data_device_manager::DndAction::Move,
+                        wl_data_device_manager::DndAction::Copy,
+                    );
+                    let mut drag = Drag {
+                        offer,
+                        window,
+                        serial,
+                        mimetypes,
+                        pos: Point::ZERO,
+                        accepted: None,
+                    };
+                    drag.moved(Point::new(x, y));
+                    m.drag.replace(Some(drag));
+                }
+                wl_data_device::Event::Motion { x, y, .. } => {
+                    // Don't hold the borrow while the handler runs.
+                    if let Some(mut drag) = m.drag.take() {
+                        drag.moved(Point::new(x, y));
+                        m.drag.replace(Some(drag));
+                    }
+                }
+                wl_data_device::Event::Leave => {
+                    if let Some(drag) = m.drag.take() {
+                        drag.left();
+                    }
+                }
+                wl_data_device::Event::Drop => {
+                    if let Some(drag) = m.drag.take() {
+                        drag.dropped(&Manager { inner: m.clone() });
                     }
                 }
                 _ => tracing::warn!("clipboard unhandled {:?} event {:?}", i, event),
             }
         });
+        self.inner.data_devices.borrow_mut().push(device.clone());
+        seat.data_device = Some(device);
+
+        let primary = match &self.inner.primary_wobj {
+            Some(primary) => primary,
+            None => return,
+        };
+        let device = primary.get_device(&seat.wl_seat);
+        device.quick_assign({
+            let m = self.inner.clone();
+            move |i, event, _ignored| match event {
+                zwp_primary_selection_device_v1::Event::DataOffer { offer } => {
+                    offer.quick_assign({
+                        let m = m.clone();
+                        move |i, event, _ignored| match event {
+                            zwp_primary_selection_offer_v1::Event::Offer { mime_type } => {
+                                let offer = Offer::new(OfferObject::Primary(i.detach()), mime_type);
+                                m.primary.offered(offer);
+                            }
+                            _ => tracing::warn!("clipboard unhandled {:?} event {:?}", i, event),
+                        }
+                    });
+                }
+                zwp_primary_selection_device_v1::Event::Selection { id } => {
+                    m.primary.selected(id.map(|id| id.as_ref().id()));
+                }
+                _ => tracing::warn!("clipboard unhandled {:?} event {:?}", i, event),
+            }
+        });
+        self.inner.primary_devices.borrow_mut().push(device.clone());
+        seat.primary_selection_device = Some(device);
     }
 
-    fn initiate(&self, o: Offer) -> Option<Vec<u8>> {
-        tracing::debug!("retrieving {:?} {:?}", o.wobj, o.mimetype);
-        let (fdread, fdwrite) = match nix::unistd::pipe2(nix::fcntl::OFlag::O_CLOEXEC) {
-            Ok(pipe) => pipe,
-            Err(cause) => {
-                tracing::error!("clipboard failed to request data {:?}", cause);
-                return None;
+    /// Stop using the devices of a seat which has gone away.
+    pub(super) fn detach(&self, seat: &mut seat::Seat) {
+        if let Some(device) = seat.data_device.take() {
+            self.inner
+                .data_devices
+                .borrow_mut()
+                .retain(|d| !d.as_ref().equals(device.as_ref()));
+            device.release();
+        }
+        if let Some(device) = seat.primary_selection_device.take() {
+            self.inner
+                .primary_devices
+                .borrow_mut()
+                .retain(|d| !d.as_ref().equals(device.as_ref()));
+            device.destroy();
+        }
+    }
+
+    /// Take ownership of the selection, offering `contents` (pairs of mimetype and data).
+    pub(super) fn put(&self, selection: Selection, contents: Vec<(String, Vec<u8>)>) {
+        let contents = std::rc::Rc::new(contents);
+        let state = self.inner.selection(selection);
+        let serial = self.inner.serial.get();
+        match selection {
+            Selection::Clipboard => {
+                let source = self.inner.wobj.create_data_source();
+                for (mimetype, _) in contents.iter() {
+                    source.offer(mimetype.clone());
+                }
+                source.quick_assign({
+                    let owned = state.source.clone();
+                    let contents = contents.clone();
+                    move |source, event, _ignored| match event {
+                        wl_data_source::Event::Send { mime_type, fd } => {
+                            send(&contents, mime_type, fd)
+                        }
+                        wl_data_source::Event::Cancelled => {
+                            disown(&owned, &contents);
+                            source.destroy();
+                        }
+                        _ => tracing::debug!("clipboard {:?} event {:?}", source, event),
+                    }
+                });
+                for device in self.inner.data_devices.borrow().iter() {
+                    device.set_selection(Some(&source), serial);
+                }
             }
-        };
+            Selection::Primary => {
+                let primary = match &self.inner.primary_wobj {
+                    Some(primary) => primary,
+                    None => return,
+                };
+                let source = primary.create_source();
+                for (mimetype, _) in contents.iter() {
+                    source.offer(mimetype.clone());
+                }
+                source.quick_assign({
+                    let owned = state.source.clone();
+                    let contents = contents.clone();
+                    move |source, event, _ignored| match event {
+                        zwp_primary_selection_source_v1::Event::Send { mime_type, fd } => {
+                            send(&contents, mime_type, fd)
+                        }
+                        zwp_primary_selection_source_v1::Event::Cancelled => {
+                            disown(&owned, &contents);
+                            source.destroy();
+                        }
+                        _ => tracing::debug!("clipboard {:?} event {:?}", source, event),
+                    }
+                });
+                for device in self.inner.primary_devices.borrow().iter() {
+                    device.set_selection(Some(&source), serial);
+                }
+            }
+        }
+        state.source.replace(Some(contents));
+    }
 
-        o.wobj.receive(o.mimetype.to_string(), fdwrite);
-        if let Err(cause) = self.inner.display.flush() {
-            tracing::error!("clipboard failed to request data {:?}", cause);
-            return None;
+    /// Start dragging `contents` (pairs of mimetype and data) out of `window`, from its
+    /// `surface` at `scale`, with `icon` under the pointer.
+    ///
+    /// There is only one pointer to drag with, so this does nothing while a drag goes on.
+    pub(super) fn start_drag(
+        &self,
+        appdata: &application::Data,
+        window: &WindowHandle,
+        surface: &wl_surface::WlSurface,
+        scale: i32,
+        contents: Vec<(String, Vec<u8>)>,
+        icon: Option<DragIcon>,
+    ) {
+        if self.inner.dragging.borrow().is_some() {
+            tracing::warn!("unable to start a drag while another one goes on");
+            return;
         }
+        // The drag is started by the press of the seat which drags.
+        let (seat, serial) = match appdata.pointer.button_press() {
+            Some(press) => press,
+            None => {
+                tracing::warn!("unable to start a drag without a button press");
+                return;
+            }
+        };
+        let device = appdata.seats.borrow().values().find_map(|s| {
+            let s = s.borrow();
+            match s.wl_seat.as_ref().equals(seat.as_ref()) {
+                true => s.data_device.clone(),
+                false => None,
+            }
+        });
+        let device = match device {
+            Some(device) => device,
+            None => {
+                tracing::warn!("unable to start a drag without a data device");
+                return;
+            }
+        };
+        let icon = icon
+            .filter(|icon| icon.width > 0 && icon.height > 0)
+            .and_then(|icon| match drag_icon(appdata, &icon, scale) {
+                Ok(surface) => Some(surface),
+                Err(cause) => {
+                    tracing::warn!("unable to create the drag icon: {:?}", cause);
+                    None
+                }
+            });
 
-        if let Err(cause) = nix::unistd::close(fdwrite) {
-            tracing::error!("clipboard failed to request data {:?}", cause);
-            return None;
+        let contents = std::rc::Rc::new(contents);
+        let source = self.inner.wobj.create_data_source();
+        for (mimetype, _) in contents.iter() {
+            source.offer(mimetype.clone());
         }
+        source.set_actions(
+            wl_data_device_manager::DndAction::Copy | wl_data_device_manager::DndAction::Move,
+        );
+        source.quick_assign({
+            let dragging = self.inner.dragging.clone();
+            let contents = contents.clone();
+            let window = window.clone();
+            let icon = icon.clone();
+            let action = std::cell::Cell::new(None);
+            move |source, event, _ignored| {
+                let finished = match event {
+                    wl_data_source::Event::Send { mime_type, fd } => {
+                        return send(&contents, mime_type, fd);
+                    }
+                    wl_data_source::Event::Action { dnd_action } => {
+                        if dnd_action.contains(wl_data_device_manager::DndAction::Move) {
+                            action.set(Some(DropAction::Move));
+                        } else if dnd_action.contains(wl_data_device_manager::DndAction::Copy) {
+                            action.set(Some(DropAction::Copy));
+                        } else {
+                            action.set(None);
+                        }
+                        return;
+                    }
+                    wl_data_source::Event::DndFinished => action.get(),
+                    // The drop wasn't accepted, or the compositor gave up on the drag.
+                    wl_data_source::Event::Cancelled => None,
+                    _ => return tracing::debug!("clipboard {:?} event {:?}", source, event),
+                };
+                disown(&dragging, &contents);
+                source.destroy();
+                if let Some(icon) = &icon {
+                    icon.destroy();
+                }
+                if let Some(data) = window.data() {
+                    data.with_handler(|handler| handler.dnd_finished(finished));
+                }
+            }
+        });
+        device.start_drag(
+            Some(&source),
+            surface,
+            icon.as_ref().map(|icon| icon.detach()).as_ref(),
+            serial,
+        );
+        if let Some(icon) = &icon {
+            icon.commit();
+        }
+        self.inner.dragging.replace(Some(contents));
+    }
 
-        let mut data = Vec::new();
+    /// Ask the owner of the selection for its contents, and wait (for a while) for them to
+    /// arrive.
+    fn initiate(&self, o: Offer) -> Result<Vec<u8>, ReceiveError> {
+        tracing::debug!("retrieving {:?} {:?}", o.wobj, o.mimetype);
+        let (fdread, fdwrite) = nix::unistd::pipe2(nix::fcntl::OFlag::O_CLOEXEC)?;
+        // Owned straight away, so the read end is closed however we leave.
         let mut io: std::fs::File = unsafe { std::os::unix::io::FromRawFd::from_raw_fd(fdread) };
-        let transferred = match io.read_to_end(&mut data) {
-            Err(cause) => {
-                tracing::error!("clipboard unable to retrieve pasted content {:?}", cause);
-                return None;
+
+        o.wobj.receive(o.mimetype.to_string(), fdwrite);
+        let flushed = self.inner.display.flush();
+        // Our copy of the write end has to go, or we'd never see the end of the data.
+        nix::unistd::close(fdwrite)?;
+        flushed?;
+
+        let mut data = Vec::new();
+        // The source client might never answer, so don't wait for it forever.
+        let deadline = std::time::Instant::now() + Self::RECEIVE_TIMEOUT;
+        let mut chunk = [0; 4096];
+        loop {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            let mut fds = [PollFd::new(fdread, PollFlags::POLLIN)];
+            match nix::poll::poll(&mut fds, remaining.as_millis() as i32) {
+                Ok(0) => return Err(ReceiveError::Timeout),
+                Ok(_) | Err(nix::errno::Errno::EINTR) => {}
+                Err(cause) => return Err(cause.into()),
             }
-            Ok(transferred) => transferred,
-        };
 
-        tracing::debug!("transferred {:?} bytes", transferred);
+            match io.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(len) => data.extend_from_slice(&chunk[..len]),
+                Err(cause) if cause.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(cause) => return Err(cause.into()),
+            }
+        }
+
+        tracing::debug!("transferred {:?} bytes", data.len());
+        Ok(data)
+    }
 
-        match transferred {
-            0 => None,
-            _ => Some(data),
+    /// The mimetypes currently available on the selection.
+    pub(super) fn mimetypes(&self, selection: Selection) -> Vec<String> {
+        let state = self.inner.selection(selection);
+        match &*state.source.borrow() {
+            Some(contents) => contents
+                .iter()
+                .map(|(mimetype, _)| mimetype.clone())
+                .collect(),
+            None => state
+                .offers
+                .borrow()
+                .current
+                .borrow()
+                .iter()
+                .map(|offer| offer.mimetype.clone())
+                .collect(),
         }
     }
 
-    pub(super) fn receive(&self, mimetype: impl Into<String>) -> Option<Vec<u8>> {
+    pub(super) fn receive(
+        &self,
+        selection: Selection,
+        mimetype: impl Into<String>,
+    ) -> Option<Vec<u8>> {
         let mimetype: String = mimetype.into();
-        if let Some(offer) = self.inner.devices.borrow().receive(&mimetype) {
-            return self.initiate(offer);
+        let state = self.inner.selection(selection);
+        // Reading from our own selection would wait on ourselves, so answer directly.
+        if let Some(contents) = &*state.source.borrow() {
+            return contents
+                .iter()
+                .find(|(offered, _)| offered.starts_with(&mimetype))
+                .map(|(_, data)| data.clone());
         }
 
-        None
+        let offer = state.offers.borrow().receive(&mimetype);
+        let received = match offer {
+            Some(offer) => self.initiate(offer),
+            None => Err(ReceiveError::NoSelection),
+        };
+        match received {
+            Ok(data) if !data.is_empty() => Some(data),
+            Ok(_) | Err(ReceiveError::NoSelection) => None,
+            Err(ReceiveError::Timeout) => {
+                tracing::warn!("clipboard timed out retrieving {:?}", mimetype);
+                None
+            }
+            Err(ReceiveError::Io(cause)) => {
+                tracing::error!("clipboard unable to retrieve {:?}: {:?}", mimetype, cause);
+                None
+            }
+        }
     }
 }
 
@@ -221,50 +846,93 @@ impl Manager {
 #[derive(Debug, Clone)]
 pub struct Clipboard {
     inner: Manager,
+    selection: Selection,
 }
 
 impl From<&Manager> for Clipboard {
     fn from(m: &Manager) -> Self {
-        Self { inner: m.clone() }
+        Self {
+            inner: m.clone(),
+            selection: Selection::Clipboard,
+        }
     }
 }
 
 impl Clipboard {
+    /// The primary selection. If the compositor doesn't support it, this is always empty.
+    pub(super) fn primary(m: &Manager) -> Self {
+        Self {
+            inner: m.clone(),
+            selection: Selection::Primary,
+        }
+    }
+
     const UTF8: &'static str = "text/plain;charset=utf-8";
     const TEXT: &'static str = "text/plain";
     const UTF8_STRING: &'static str = "UTF8_STRING";
 
     /// Put a string onto the system clipboard.
     pub fn put_string(&mut self, s: impl AsRef<str>) {
-        let _s = s.as_ref().to_string();
-        self.inner.inner.wdsobj.offer(Clipboard::UTF8.to_string());
+        let data = s.as_ref().as_bytes();
+        self.inner.put(
+            self.selection,
+            [Clipboard::UTF8, Clipboard::UTF8_STRING, Clipboard::TEXT]
+                .iter()
+                .map(|mimetype| (mimetype.to_string(), data.to_vec()))
+                .collect(),
+        );
     }
 
     /// Put multi-format data on the system clipboard.
-    pub fn put_formats(&mut self, _formats: &[ClipboardFormat]) {
-        tracing::warn!("clipboard copy not implemented");
+    pub fn put_formats(&mut self, formats: &[ClipboardFormat]) {
+        self.inner.put(self.selection, Clipboard::contents(formats));
+    }
+
+    /// The data to offer for each mimetype, given the formats to offer.
+    pub(super) fn contents(formats: &[ClipboardFormat]) -> Vec<(String, Vec<u8>)> {
+        let mut contents = Vec::with_capacity(formats.len());
+        for format in formats {
+            if format.identifier == ClipboardFormat::TEXT {
+                // Offer text under all the names applications look for.
+                contents.extend(
+                    [Clipboard::UTF8, Clipboard::UTF8_STRING, Clipboard::TEXT]
+                        .iter()
+                        .map(|mimetype| (mimetype.to_string(), format.data.clone())),
+                );
+            } else {
+                contents.push((format.identifier.to_string(), format.data.clone()));
+            }
+        }
+        contents
     }
 
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         vec![Clipboard::UTF8, Clipboard::TEXT, Clipboard::UTF8_STRING]
             .iter()
-            .find_map(
-                |mimetype| match std::str::from_utf8(&self.inner.receive(*mimetype)?) {
+            .find_map(|mimetype| {
+                match std::str::from_utf8(&self.inner.receive(self.selection, *mimetype)?) {
                     Ok(s) => Some(s.to_string()),
                     Err(cause) => {
                         tracing::error!("clipboard unable to retrieve utf8 content {:?}", cause);
                         None
                     }
-                },
-            )
+                }
+            })
     }
 
     /// Given a list of supported clipboard types, returns the supported type which has
     /// highest priority on the system clipboard, or `None` if no types are supported.
-    pub fn preferred_format(&self, _formats: &[FormatId]) -> Option<FormatId> {
-        tracing::warn!("clipboard preferred_format not implemented");
-        None
+    pub fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
+        let available = self.inner.mimetypes(self.selection);
+        formats
+            .iter()
+            .find(|format| {
+                available
+                    .iter()
+                    .any(|mimetype| mimetype.starts_with(**format))
+            })
+            .copied()
     }
 
     /// Return data in a given format, if available.
@@ -272,11 +940,30 @@ impl Clipboard {
     /// It is recommended that the `fmt` argument be a format returned by
     /// [`Clipboard::preferred_format`]
     pub fn get_format(&self, format: FormatId) -> Option<Vec<u8>> {
-        self.inner.receive(format)
+        self.inner.receive(self.selection, format)
     }
 
     pub fn available_type_names(&self) -> Vec<String> {
-        tracing::warn!("clipboard available_type_names not implemented");
-        Vec::new()
+        self.inner.mimetypes(self.selection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contents_offer_text_under_every_name() {
+        let formats = [
+            ClipboardFormat::new(ClipboardFormat::TEXT, "text"),
+            ClipboardFormat::new("image/png", [1, 2, 3]),
+        ];
+        let expected = vec![
+            (Clipboard::UTF8.to_string(), b"text".to_vec()),
+            (Clipboard::UTF8_STRING.to_string(), b"text".to_vec()),
+            (Clipboard::TEXT.to_string(), b"text".to_vec()),
+            ("image/png".to_string(), vec![1, 2, 3]),
+        ];
+        assert_eq!(Clipboard::contents(&formats), expected);
     }
 }
@@ -0,0 +1,429 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Context menus, which wayland leaves to clients, so we draw them ourselves in a popup.
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::keyboard::{KbKey, KeyEvent};
+use crate::kurbo::{Point, Size};
+use crate::mouse::MouseEvent;
+use crate::region::Region;
+use crate::window::WinHandler;
+
+use super::menu::{hotkey_label, Menu, MenuItem};
+use super::surfaces::buffers::{fill, Align, RawRect, RawSize, Text};
+use super::surfaces::surface::Draw;
+use super::window::WindowHandle;
+
+const WIDTH: f64 = 200.;
+const ITEM_HEIGHT: f64 = 24.;
+const SEPARATOR_HEIGHT: f64 = 9.;
+/// The space around the items, in display points.
+const PADDING: f64 = 4.;
+/// Where labels start, leaving room for the check mark.
+const LABEL_X: f64 = 24.;
+/// The room kept for hotkeys at the end of an item.
+const HOTKEY_WIDTH: f64 = 64.;
+const FONT_SIZE: f64 = 13.;
+
+// Colors, as ARGB.
+const BACKGROUND: u32 = 0xfff6_f5f4;
+const BORDER: u32 = 0xffc0_bfbc;
+const HOVERED: u32 = 0xff35_84e4;
+const DISABLED: u32 = 0xffde_ddda;
+const MARK: u32 = 0xff3d_3846;
+const TEXT: u32 = 0xff24_1f31;
+const HOVERED_TEXT: u32 = 0xffff_ffff;
+const DISABLED_TEXT: u32 = 0xff9a_9996;
+
+#[derive(Debug)]
+enum Kind {
+    /// An item that sends its command when chosen.
+    Action {
+        id: u32,
+        selected: Option<bool>,
+    },
+    /// A submenu, which isn't supported yet.
+    Dropdown,
+    Separator,
+}
+
+/// The layout of an item, in display points from the top of the menu.
+#[derive(Debug)]
+struct Row {
+    kind: Kind,
+    label: String,
+    /// How the item's hotkey is shown, if it has one.
+    hotkey: Option<String>,
+    enabled: bool,
+    y0: f64,
+    y1: f64,
+}
+
+impl Row {
+    fn selectable(&self) -> bool {
+        self.enabled && matches!(self.kind, Kind::Action { .. })
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    rows: Vec<Row>,
+    hovered: Option<usize>,
+}
+
+impl State {
+    fn new(menu: Menu) -> Self {
+        let mut y = PADDING;
+        let rows = menu
+            .items
+            .into_iter()
+            .map(|item| {
+                let (kind, label, hotkey, enabled, height) = match item {
+                    MenuItem::Action {
+                        id,
+                        text,
+                        key,
+                        selected,
+                        enabled,
+                    } => {
+                        let hotkey = key.as_ref().map(hotkey_label);
+                        let kind = Kind::Action { id, selected };
+                        (kind, text, hotkey, enabled, ITEM_HEIGHT)
+                    }
+                    MenuItem::Dropdown { text, enabled, .. } => {
+                        (Kind::Dropdown, text, None, enabled, ITEM_HEIGHT)
+                    }
+                    MenuItem::Separator => (
+                        Kind::Separator,
+                        String::new(),
+                        None,
+                        false,
+                        SEPARATOR_HEIGHT,
+                    ),
+                };
+                y += height;
+                Row {
+                    kind,
+                    label,
+                    hotkey,
+                    enabled,
+                    y0: y - height,
+                    y1: y,
+                }
+            })
+            .collect();
+        Self {
+            rows,
+            hovered: None,
+        }
+    }
+
+    fn size(&self) -> Size {
+        let height = self.rows.last().map_or(0., |row| row.y1);
+        Size::new(WIDTH, height + PADDING)
+    }
+
+    fn row_at(&self, pos: Point) -> Option<usize> {
+        if pos.x < 0. || pos.x > WIDTH {
+            return None;
+        }
+        self.rows
+            .iter()
+            .position(|row| row.y0 <= pos.y && pos.y < row.y1)
+            .filter(|idx| self.rows[*idx].selectable())
+    }
+
+    /// Move the hover to the next selectable row, wrapping around at the ends.
+    fn step(&mut self, forward: bool) {
+        let len = self.rows.len();
+        let start = match (self.hovered, forward) {
+            (Some(idx), _) => idx,
+            (None, true) => len.saturating_sub(1),
+            (None, false) => 0,
+        };
+        self.hovered = (1..=len)
+            .map(|offset| match forward {
+                true => (start + offset) % len,
+                false => (start + len - offset) % len,
+            })
+            .find(|idx| self.rows[*idx].selectable());
+    }
+
+    fn draw(&self, buf: &mut [u8], size: RawSize, scale: i32) {
+        let scale = scale as f64;
+        let rect = |x0: f64, y0: f64, x1: f64, y1: f64| RawRect {
+            x0: (x0 * scale) as i32,
+            y0: (y0 * scale) as i32,
+            x1: (x1 * scale) as i32,
+            y1: (y1 * scale) as i32,
+        };
+        fill(buf, size, size.to_rect(), BORDER);
+        fill(
+            buf,
+            size,
+            rect(1., 1., WIDTH - 1., self.size().height - 1.),
+            BACKGROUND,
+        );
+
+        for (idx, row) in self.rows.iter().enumerate() {
+            let (y0, y1) = (row.y0, row.y1);
+            match row.kind {
+                Kind::Separator => {
+                    let middle = (y0 + y1) / 2.;
+                    fill(
+                        buf,
+                        size,
+                        rect(PADDING, middle, WIDTH - PADDING, middle + 1.),
+                        BORDER,
+                    );
+                }
+                Kind::Action { selected, .. } => {
+                    if self.hovered == Some(idx) {
+                        fill(buf, size, rect(PADDING, y0, WIDTH - PADDING, y1), HOVERED);
+                    } else if !row.enabled {
+                        fill(buf, size, rect(PADDING, y0, WIDTH - PADDING, y1), DISABLED);
+                    }
+                    if selected == Some(true) {
+                        let middle = (y0 + y1) / 2.;
+                        let mark = rect(2. * PADDING, middle - 3., 2. * PADDING + 6., middle + 3.);
+                        fill(buf, size, mark, MARK);
+                    }
+                }
+                Kind::Dropdown => {
+                    fill(buf, size, rect(PADDING, y0, WIDTH - PADDING, y1), DISABLED);
+                }
+            }
+            if matches!(row.kind, Kind::Separator) {
+                continue;
+            }
+
+            let color = match (self.hovered == Some(idx), row.selectable()) {
+                (true, _) => HOVERED_TEXT,
+                (false, true) => TEXT,
+                (false, false) => DISABLED_TEXT,
+            };
+            let mut text = Text {
+                text: &row.label,
+                font_size: FONT_SIZE * scale,
+                bold: false,
+                color,
+                align: Align::Start,
+            };
+            let end = WIDTH - 2. * PADDING;
+            match &row.hotkey {
+                Some(hotkey) => {
+                    text.draw(buf, size, rect(LABEL_X, y0, end - HOTKEY_WIDTH, y1));
+                    text.text = hotkey;
+                    text.align = Align::End;
+                    text.draw(buf, size, rect(end - HOTKEY_WIDTH, y0, end, y1));
+                }
+                None => text.draw(buf, size, rect(LABEL_X, y0, end, y1)),
+            }
+        }
+    }
+
+    /// The menu and its items, for assistive technologies.
+    #[cfg(feature = "accesskit")]
+    fn accesskit_tree(&self) -> accesskit::TreeUpdate {
+        use accesskit::{CheckedState, NodeBuilder, NodeClassSet, NodeId, Role};
+        use std::num::NonZeroU128;
+
+        let root = NodeId(NonZeroU128::new(1).unwrap());
+        let item = |idx: usize| NodeId(NonZeroU128::new(idx as u128 + 2).unwrap());
+        let mut classes = NodeClassSet::lock_global();
+        let mut nodes = Vec::with_capacity(self.rows.len() + 1);
+        for (idx, row) in self.rows.iter().enumerate() {
+            let role = match row.kind {
+                Kind::Action {
+                    selected: Some(_), ..
+                } => Role::MenuItemCheckBox,
+                Kind::Action { .. } | Kind::Dropdown => Role::MenuItem,
+                Kind::Separator => Role::Splitter,
+            };
+            let mut node = NodeBuilder::new(role);
+            node.set_bounds(accesskit::Rect {
+                x0: 0.,
+                y0: row.y0,
+                x1: WIDTH,
+                y1: row.y1,
+            });
+            if !row.label.is_empty() {
+                node.set_name(row.label.clone());
+            }
+            if let Some(hotkey) = &row.hotkey {
+                node.set_description(hotkey.clone());
+            }
+            if let Kind::Action {
+                selected: Some(selected),
+                ..
+            } = row.kind
+            {
+                node.set_checked_state(match selected {
+                    true => CheckedState::True,
+                    false => CheckedState::False,
+                });
+            }
+            if !row.selectable() && !matches!(row.kind, Kind::Separator) {
+                node.set_disabled();
+            }
+            nodes.push((item(idx), node.build(&mut classes)));
+        }
+        let mut menu = NodeBuilder::new(Role::Menu);
+        menu.set_children((0..self.rows.len()).map(item).collect::<Vec<_>>());
+        nodes.push((root, menu.build(&mut classes)));
+        accesskit::TreeUpdate {
+            nodes,
+            tree: Some(accesskit::Tree::new(root)),
+            focus: Some(self.hovered.map_or(root, item)),
+        }
+    }
+}
+
+/// Handles the input of a context menu popup.
+pub(super) struct Handler {
+    state: Rc<RefCell<State>>,
+    /// The window the menu was opened for, which gets the chosen command.
+    parent: WindowHandle,
+    handle: Option<WindowHandle>,
+    /// Whether a button went down inside the menu, so the release of the click that opened the
+    /// menu doesn't choose an item.
+    pressed: bool,
+}
+
+impl Handler {
+    pub(super) fn new(menu: Menu, parent: WindowHandle) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State::new(menu))),
+            parent,
+            handle: None,
+            pressed: false,
+        }
+    }
+
+    /// The size of the popup, in display points.
+    pub(super) fn size(&self) -> Size {
+        self.state.borrow().size()
+    }
+
+    pub(super) fn draw(&self) -> Draw {
+        let state = self.state.clone();
+        Box::new(move |buf, size, scale| state.borrow().draw(buf, size, scale))
+    }
+
+    fn hover(&mut self, hovered: Option<usize>) {
+        if self.state.borrow().hovered == hovered {
+            return;
+        }
+        self.state.borrow_mut().hovered = hovered;
+        self.hover_changed();
+    }
+
+    /// Repaint the menu, and tell assistive technologies which item is hovered now.
+    fn hover_changed(&self) {
+        if let Some(handle) = &self.handle {
+            handle.invalidate();
+            #[cfg(feature = "accesskit")]
+            handle.update_accesskit_if_active(|| self.state.borrow().accesskit_tree());
+        }
+    }
+
+    /// Send the command of the hovered item to the parent, and close the menu.
+    fn choose(&mut self) {
+        let hovered = self.state.borrow().hovered;
+        let row = hovered.and_then(|idx| match self.state.borrow().rows[idx].kind {
+            Kind::Action { id, .. } => Some(id),
+            _ => None,
+        });
+        if let Some(id) = row {
+            if let Some(data) = self.parent.data() {
+                data.with_handler(|handler| handler.command(id));
+            }
+        }
+        self.close();
+    }
+
+    fn close(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.close();
+        }
+    }
+}
+
+impl WinHandler for Handler {
+    fn connect(&mut self, handle: &crate::WindowHandle) {
+        self.handle = Some(handle.0.clone());
+    }
+
+    fn prepare_paint(&mut self) {}
+
+    fn paint(&mut self, _invalid: &Region) {}
+
+    #[cfg(feature = "accesskit")]
+    fn accesskit_tree(&mut self) -> accesskit::TreeUpdate {
+        self.state.borrow().accesskit_tree()
+    }
+
+    fn key_down(&mut self, event: KeyEvent) -> bool {
+        match event.key {
+            KbKey::ArrowDown => self.state.borrow_mut().step(true),
+            KbKey::ArrowUp => self.state.borrow_mut().step(false),
+            KbKey::Enter => {
+                self.choose();
+                return true;
+            }
+            KbKey::Escape => {
+                self.close();
+                return true;
+            }
+            _ => return false,
+        }
+        self.hover_changed();
+        true
+    }
+
+    fn mouse_move(&mut self, event: &MouseEvent) {
+        let hovered = self.state.borrow().row_at(event.pos);
+        self.hover(hovered);
+    }
+
+    fn mouse_down(&mut self, event: &MouseEvent) {
+        self.pressed = true;
+        self.mouse_move(event);
+    }
+
+    fn mouse_up(&mut self, event: &MouseEvent) {
+        if !self.pressed {
+            return;
+        }
+        self.pressed = false;
+        if self.state.borrow().row_at(event.pos).is_some() {
+            self.choose();
+        }
+    }
+
+    fn mouse_leave(&mut self) {
+        self.hover(None);
+    }
+
+    fn request_close(&mut self) {
+        self.close();
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
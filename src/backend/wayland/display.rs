@@ -117,8 +117,15 @@ impl GlobalEventDispatch for std::sync::Arc<Environment> {
 }
 
 pub(super) fn new(dispatcher: Dispatcher) -> Result<Environment, error::Error> {
+    connect(dispatcher, wlc::Display::connect_to_env()?)
+}
+
+/// Set up the environment on an existing connection to a compositor.
+pub(super) fn connect(
+    dispatcher: Dispatcher,
+    d: wlc::Display,
+) -> Result<Environment, error::Error> {
     let dispatcher = std::sync::Arc::new(dispatcher);
-    let d = wlc::Display::connect_to_env()?;
 
     let mut queue = d.create_event_queue();
     let handle = d.attach(queue.token());
@@ -21,7 +21,10 @@ use wayland_client as wl;
 pub enum Error {
     /// Error connecting to wayland server.
     Connect(Arc<wl::ConnectError>),
-    /// A wayland global either doesn't exist, or doesn't support the version we need.
+    /// The compositor doesn't advertise a wayland global we can't do without, e.g. `xdg_wm_base`
+    /// (xdg-shell).
+    MissingGlobal(&'static str),
+    /// A wayland global doesn't support the version we need.
     Global {
         name: String,
         version: u32,
@@ -46,11 +49,14 @@ impl Error {
         Self::Fatal(Arc::new(e))
     }
 
-    pub fn global(name: impl Into<String>, version: u32, inner: wl::GlobalError) -> Self {
-        Error::Global {
-            name: name.into(),
-            version,
-            inner: Arc::new(inner),
+    pub fn global(name: &'static str, version: u32, inner: wl::GlobalError) -> Self {
+        match inner {
+            wl::GlobalError::Missing => Error::MissingGlobal(name),
+            inner => Error::Global {
+                name: name.into(),
+                version,
+                inner: Arc::new(inner),
+            },
         }
     }
 
@@ -63,6 +69,10 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
             Self::Connect(e) => write!(f, "could not connect to the wayland server: {e:?}"),
+            Self::MissingGlobal(name) => write!(
+                f,
+                "the compositor doesn't support {name}, which is required by glazier"
+            ),
             Self::Global { name, version, .. } => write!(
                 f,
                 "a required wayland global ({name}@{version}) was unavailable"
@@ -79,6 +89,7 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
             Self::Connect(e) => Some(&**e),
+            Self::MissingGlobal(_) => None,
             Self::Global { inner, .. } => Some(&**inner),
             Self::Fatal(e) => Some(&**e),
             Self::Err(e) => Some(&**e),
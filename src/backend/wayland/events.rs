@@ -10,7 +10,7 @@ use calloop::{
     Dispatcher, EventSource, Interest, Mode,
 };
 use std::{cell::RefCell, io, rc::Rc};
-use wayland_client::EventQueue;
+use wayland_client::{AnonymousObject, DispatchData, EventQueue, Main, RawEvent};
 
 use super::{application, window};
 
@@ -45,21 +45,38 @@ impl WaylandSource {
         ) -> io::Result<u32>,
     > {
         Dispatcher::new(self, |_winhandle, queue, appdata| {
-            queue
-                .borrow_mut()
-                .dispatch_pending(appdata, |event, object, _| {
-                    tracing::error!(
-                        "[glazier] Encountered an orphan event: {}@{} : {}",
-                        event.interface,
-                        object.as_ref().id(),
-                        event.name
-                    );
-                    tracing::error!("all events should be handled: please raise an issue");
-                })
+            dispatch_pending(&mut queue.borrow_mut(), appdata)
         })
     }
 }
 
+/// Dispatch the events which have already been read from the socket.
+pub(crate) fn dispatch_pending(
+    queue: &mut EventQueue,
+    appdata: &mut std::sync::Arc<application::Data>,
+) -> io::Result<u32> {
+    queue.dispatch_pending(appdata, orphan)
+}
+
+/// Block until the compositor has handled all of our requests, dispatching the events it sends
+/// meanwhile.
+pub(crate) fn roundtrip(
+    queue: &mut EventQueue,
+    appdata: &mut std::sync::Arc<application::Data>,
+) -> io::Result<u32> {
+    queue.sync_roundtrip(appdata, orphan)
+}
+
+fn orphan(event: RawEvent, object: Main<AnonymousObject>, _: DispatchData) {
+    tracing::error!(
+        "[glazier] Encountered an orphan event: {}@{} : {}",
+        event.interface,
+        object.as_ref().id(),
+        event.name
+    );
+    tracing::error!("all events should be handled: please raise an issue");
+}
+
 impl EventSource for WaylandSource {
     type Event = window::WindowHandle;
     type Metadata = Rc<RefCell<EventQueue>>;
@@ -0,0 +1,149 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Touchpad gestures.
+use std::cell::Cell;
+
+use wayland_client as wl;
+use wayland_client::protocol::wl_pointer::WlPointer;
+use wayland_protocols::unstable::pointer_gestures::v1::client::{
+    zwp_pointer_gesture_pinch_v1::{self, ZwpPointerGesturePinchV1},
+    zwp_pointer_gesture_swipe_v1::{self, ZwpPointerGestureSwipeV1},
+    zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+};
+
+use crate::kurbo::{Point, Vec2};
+use crate::{GestureEvent, GesturePhase};
+
+use super::application::Data;
+use super::surfaces::surface;
+
+/// The gestures of a pointer.
+#[derive(Debug, Clone)]
+pub(super) struct Gestures {
+    swipe: wl::Main<ZwpPointerGestureSwipeV1>,
+    pinch: wl::Main<ZwpPointerGesturePinchV1>,
+}
+
+impl Gestures {
+    pub(super) fn attach(
+        appdata: &std::sync::Arc<Data>,
+        id: u32,
+        manager: &wl::Main<ZwpPointerGesturesV1>,
+        pointer: &WlPointer,
+    ) -> Self {
+        let swipe = manager.get_swipe_gesture(pointer);
+        swipe.quick_assign({
+            let appdata = appdata.clone();
+            let fingers = Cell::new(0);
+            move |_, event, _| {
+                let (phase, delta) = match event {
+                    zwp_pointer_gesture_swipe_v1::Event::Begin { fingers: n, .. } => {
+                        fingers.set(n);
+                        (GesturePhase::Begin, Vec2::ZERO)
+                    }
+                    zwp_pointer_gesture_swipe_v1::Event::Update { dx, dy, .. } => {
+                        (GesturePhase::Update, Vec2::new(dx, dy))
+                    }
+                    zwp_pointer_gesture_swipe_v1::Event::End { cancelled, .. } => {
+                        (end(cancelled), Vec2::ZERO)
+                    }
+                    _ => return,
+                };
+                dispatch(&appdata, id, |pos| GestureEvent::Swipe {
+                    phase,
+                    fingers: fingers.get(),
+                    pos,
+                    delta,
+                });
+            }
+        });
+
+        let pinch = manager.get_pinch_gesture(pointer);
+        pinch.quick_assign({
+            let appdata = appdata.clone();
+            let fingers = Cell::new(0);
+            // Scales are relative to the start of the gesture, but zooming wants the change.
+            let scale = Cell::new(1.0);
+            move |_, event, _| {
+                let (phase, delta, rotation) = match event {
+                    zwp_pointer_gesture_pinch_v1::Event::Begin { fingers: n, .. } => {
+                        fingers.set(n);
+                        scale.set(1.0);
+                        (GesturePhase::Begin, Vec2::ZERO, 0.0)
+                    }
+                    zwp_pointer_gesture_pinch_v1::Event::Update {
+                        dx,
+                        dy,
+                        scale: current,
+                        rotation,
+                        ..
+                    } => {
+                        let previous = scale.replace(current);
+                        with_window(&appdata, id, |data, _| {
+                            data.with_handler(|handler| handler.zoom(current - previous));
+                        });
+                        (GesturePhase::Update, Vec2::new(dx, dy), rotation)
+                    }
+                    zwp_pointer_gesture_pinch_v1::Event::End { cancelled, .. } => {
+                        (end(cancelled), Vec2::ZERO, 0.0)
+                    }
+                    _ => return,
+                };
+                dispatch(&appdata, id, |pos| GestureEvent::Pinch {
+                    phase,
+                    fingers: fingers.get(),
+                    pos,
+                    delta,
+                    scale: scale.get(),
+                    rotation,
+                });
+            }
+        });
+
+        Self { swipe, pinch }
+    }
+
+    pub(super) fn destroy(&self) {
+        self.swipe.destroy();
+        self.pinch.destroy();
+    }
+}
+
+fn end(cancelled: i32) -> GesturePhase {
+    match cancelled {
+        0 => GesturePhase::End,
+        _ => GesturePhase::Cancel,
+    }
+}
+
+/// Run `f` for the window the pointer of seat `id` is over.
+fn with_window(appdata: &Data, id: u32, f: impl FnOnce(&surface::Data, Point)) {
+    let (window, pos) = match appdata.pointer.focus(id) {
+        Some(focus) => focus,
+        None => return,
+    };
+    let data = match appdata.handles.borrow().get(&window).and_then(|w| w.data()) {
+        Some(data) => data,
+        None => return,
+    };
+    f(&data, pos);
+}
+
+fn dispatch(appdata: &Data, id: u32, event: impl FnOnce(Point) -> GestureEvent) {
+    with_window(appdata, id, |data, pos| {
+        let event = event(pos);
+        data.with_handler(|handler| handler.gesture(&event));
+    });
+}
@@ -0,0 +1,604 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A headless compositor for tests, so windows can be built and fed input without a real one.
+//!
+//! It speaks just enough of the wire protocol over a socket pair for an `Application` to start,
+//! and for `WindowBuilder::build` to make a toplevel. Everything else the client asks for is
+//! ignored. Tests then send configures and input with a [`Compositor`], and call [`roundtrip`]
+//! to have them dispatched to the window's handler.
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use wayland_client as wl;
+
+use super::application::Application;
+use crate::kurbo::{Point, Size};
+
+/// The globals we advertise, with the version of each. Their names are their index plus one.
+const GLOBALS: [(&str, u32); 5] = [
+    ("wl_compositor", 4),
+    ("wl_shm", 1),
+    ("xdg_wm_base", 3),
+    ("wl_data_device_manager", 3),
+    ("wl_seat", 7),
+];
+
+/// `xdg_toplevel` state for an activated window.
+const ACTIVATED: u32 = 4;
+/// `wl_seat` capability for a pointer.
+const POINTER: u32 = 1;
+
+/// An argument of an event.
+enum Arg<'a> {
+    Uint(u32),
+    Int(i32),
+    Fixed(f64),
+    Str(&'a str),
+    Array(&'a [u8]),
+}
+
+/// The state of the compositor, shared by the thread reading requests and the tests sending
+/// events.
+struct Server {
+    stream: UnixStream,
+    /// The interface of each object the client created, for the requests we care about.
+    objects: HashMap<u32, &'static str>,
+    serial: u32,
+    /// The `wl_surface` of each `xdg_surface`.
+    roles: HashMap<u32, u32>,
+    /// The `xdg_toplevel` and `xdg_surface` of each toplevel, oldest first.
+    toplevels: Vec<(u32, u32)>,
+    pointer: Option<u32>,
+    /// The `wl_surface` the pointer is over.
+    entered: Option<u32>,
+    /// Every title the client set on a toplevel, in order.
+    titles: Vec<String>,
+    /// The events answering the requests being handled, which are sent together once they
+    /// have all been handled.
+    replies: Option<Vec<u8>>,
+}
+
+impl Server {
+    fn next_serial(&mut self) -> u32 {
+        self.serial += 1;
+        self.serial
+    }
+
+    fn send(&mut self, object: u32, opcode: u16, args: &[Arg]) {
+        let mut body = Vec::new();
+        for arg in args {
+            match arg {
+                Arg::Uint(value) => body.extend_from_slice(&value.to_ne_bytes()),
+                Arg::Int(value) => body.extend_from_slice(&value.to_ne_bytes()),
+                Arg::Fixed(value) => body.extend_from_slice(&((value * 256.) as i32).to_ne_bytes()),
+                Arg::Str(value) => {
+                    let mut bytes = value.as_bytes().to_vec();
+                    bytes.push(0);
+                    push_array(&mut body, &bytes);
+                }
+                Arg::Array(value) => push_array(&mut body, value),
+            }
+        }
+        let mut message = Vec::with_capacity(8 + body.len());
+        message.extend_from_slice(&object.to_ne_bytes());
+        let size = (8 + body.len()) as u32;
+        message.extend_from_slice(&((size << 16) | opcode as u32).to_ne_bytes());
+        message.extend_from_slice(&body);
+        match &mut self.replies {
+            Some(replies) => replies.extend_from_slice(&message),
+            None => self.stream.write_all(&message).unwrap(),
+        }
+    }
+
+    /// Handle a request from the client.
+    fn request(&mut self, object: u32, opcode: u16, mut args: &[u8]) {
+        let interface = match (object, self.objects.get(&object)) {
+            (1, _) => "wl_display",
+            (_, Some(interface)) => *interface,
+            (_, None) => return,
+        };
+        let new = match (interface, opcode) {
+            ("wl_display", 0) => {
+                let callback = take_uint(&mut args);
+                let serial = self.next_serial();
+                self.send(callback, 0, &[Arg::Uint(serial)]);
+                self.send(1, 1, &[Arg::Uint(callback)]);
+                return;
+            }
+            ("wl_display", 1) => {
+                let registry = take_uint(&mut args);
+                for (idx, (interface, version)) in GLOBALS.iter().enumerate() {
+                    let name = idx as u32 + 1;
+                    let args = [Arg::Uint(name), Arg::Str(interface), Arg::Uint(*version)];
+                    self.send(registry, 0, &args);
+                }
+                (registry, "wl_registry")
+            }
+            ("wl_registry", 0) => {
+                let name = take_uint(&mut args);
+                let _interface = take_array(&mut args);
+                let _version = take_uint(&mut args);
+                let id = take_uint(&mut args);
+                let interface = GLOBALS[name as usize - 1].0;
+                match interface {
+                    "wl_seat" => self.send(id, 0, &[Arg::Uint(POINTER)]),
+                    "wl_shm" => {
+                        // Argb8888 and Xrgb8888, which every compositor supports.
+                        self.send(id, 0, &[Arg::Uint(0)]);
+                        self.send(id, 0, &[Arg::Uint(1)]);
+                    }
+                    _ => {}
+                }
+                (id, interface)
+            }
+            ("wl_compositor", 0) => (take_uint(&mut args), "wl_surface"),
+            ("xdg_wm_base", 2) => {
+                let id = take_uint(&mut args);
+                let surface = take_uint(&mut args);
+                self.roles.insert(id, surface);
+                (id, "xdg_surface")
+            }
+            ("xdg_surface", 1) => {
+                let id = take_uint(&mut args);
+                self.toplevels.push((id, object));
+                (id, "xdg_toplevel")
+            }
+            ("xdg_toplevel", 2) => {
+                let title = take_array(&mut args);
+                let title = &title[..title.len().saturating_sub(1)];
+                self.titles
+                    .push(String::from_utf8_lossy(title).into_owned());
+                return;
+            }
+            ("wl_seat", 0) => {
+                let id = take_uint(&mut args);
+                self.pointer = Some(id);
+                (id, "wl_pointer")
+            }
+            _ => return,
+        };
+        self.objects.insert(new.0, new.1);
+    }
+
+    /// The newest toplevel, with the `wl_surface` it was made from.
+    fn toplevel(&self) -> (u32, u32, u32) {
+        let (toplevel, xdg_surface) = *self.toplevels.last().expect("no toplevel was created");
+        (toplevel, xdg_surface, self.roles[&xdg_surface])
+    }
+}
+
+fn push_array(body: &mut Vec<u8>, bytes: &[u8]) {
+    body.extend_from_slice(&(bytes.len() as u32).to_ne_bytes());
+    body.extend_from_slice(bytes);
+    body.resize((body.len() + 3) & !3, 0);
+}
+
+fn take_uint(args: &mut &[u8]) -> u32 {
+    let (value, rest) = args.split_at(4);
+    *args = rest;
+    u32::from_ne_bytes(value.try_into().unwrap())
+}
+
+fn take_array<'a>(args: &mut &'a [u8]) -> &'a [u8] {
+    let len = take_uint(args) as usize;
+    let (value, rest) = args.split_at((len + 3) & !3);
+    *args = rest;
+    &value[..len]
+}
+
+/// Read the requests of the client until it disconnects, or the test is over.
+fn serve(mut stream: UnixStream, server: Arc<Mutex<Server>>, stop: Arc<AtomicBool>) {
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_millis(10)))
+        .unwrap();
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+    while !stop.load(Ordering::Relaxed) {
+        // The file descriptors the client sends along are closed, as we don't read them.
+        match stream.read(&mut chunk) {
+            Ok(0) => return,
+            Ok(len) => buf.extend_from_slice(&chunk[..len]),
+            Err(cause)
+                if matches!(
+                    cause.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(_) => return,
+        }
+        // Answer everything read at once. Otherwise the client may read the globals without the
+        // reply to the `sync` after them, and bind the seat, whose events would then arrive
+        // before it's listened to.
+        let mut server = server.lock().unwrap();
+        server.replies = Some(Vec::new());
+        while buf.len() >= 8 {
+            let object = u32::from_ne_bytes(buf[0..4].try_into().unwrap());
+            let header = u32::from_ne_bytes(buf[4..8].try_into().unwrap());
+            let size = (header >> 16) as usize;
+            if buf.len() < size {
+                break;
+            }
+            let message: Vec<u8> = buf.drain(..size).collect();
+            let opcode = (header & 0xffff) as u16;
+            server.request(object, opcode, &message[8..]);
+        }
+        let replies = server.replies.take().unwrap();
+        server.stream.write_all(&replies).unwrap();
+    }
+}
+
+/// Our end of the connection, for tests to send events with.
+pub(super) struct Compositor {
+    server: Arc<Mutex<Server>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Compositor {
+    /// Configure the newest toplevel with `size`, as activated or not.
+    pub(super) fn configure(&self, size: Size, activated: bool) {
+        let mut server = self.server.lock().unwrap();
+        let (toplevel, xdg_surface, _) = server.toplevel();
+        let states: Vec<u8> = match activated {
+            true => ACTIVATED.to_ne_bytes().to_vec(),
+            false => Vec::new(),
+        };
+        let args = [
+            Arg::Int(size.width as i32),
+            Arg::Int(size.height as i32),
+            Arg::Array(&states),
+        ];
+        server.send(toplevel, 0, &args);
+        let serial = server.next_serial();
+        server.send(xdg_surface, 0, &[Arg::Uint(serial)]);
+    }
+
+    /// Every title the client set on a toplevel, in order.
+    pub(super) fn titles(&self) -> Vec<String> {
+        self.server.lock().unwrap().titles.clone()
+    }
+
+    /// Ask the newest toplevel to close, as a titlebar's close button would.
+    pub(super) fn close(&self) {
+        let mut server = self.server.lock().unwrap();
+        let (toplevel, ..) = server.toplevel();
+        server.send(toplevel, 1, &[]);
+    }
+
+    /// Move the pointer into the newest toplevel, at `pos`.
+    pub(super) fn pointer_enter(&self, pos: Point) {
+        let mut server = self.server.lock().unwrap();
+        let (.., surface) = server.toplevel();
+        let pointer = server.pointer.expect("the seat has no pointer");
+        let serial = server.next_serial();
+        let args = [
+            Arg::Uint(serial),
+            Arg::Uint(surface),
+            Arg::Fixed(pos.x),
+            Arg::Fixed(pos.y),
+        ];
+        server.send(pointer, 0, &args);
+        server.entered = Some(surface);
+    }
+
+    /// Move the pointer out of the toplevel it's over.
+    pub(super) fn pointer_leave(&self) {
+        let mut server = self.server.lock().unwrap();
+        let pointer = server.pointer.expect("the seat has no pointer");
+        let surface = server
+            .entered
+            .take()
+            .expect("the pointer isn't over a toplevel");
+        let serial = server.next_serial();
+        server.send(pointer, 1, &[Arg::Uint(serial), Arg::Uint(surface)]);
+    }
+
+    /// Move the pointer to `pos` in the toplevel it's over.
+    pub(super) fn pointer_motion(&self, pos: Point) {
+        let mut server = self.server.lock().unwrap();
+        let pointer = server.pointer.expect("the seat has no pointer");
+        let args = [Arg::Uint(0), Arg::Fixed(pos.x), Arg::Fixed(pos.y)];
+        server.send(pointer, 2, &args);
+    }
+
+    /// Press or release a button, with its linux event code.
+    pub(super) fn pointer_button(&self, button: u32, pressed: bool) {
+        let mut server = self.server.lock().unwrap();
+        let pointer = server.pointer.expect("the seat has no pointer");
+        let serial = server.next_serial();
+        let args = [
+            Arg::Uint(serial),
+            Arg::Uint(0),
+            Arg::Uint(button),
+            Arg::Uint(pressed as u32),
+        ];
+        server.send(pointer, 3, &args);
+    }
+
+    /// End the pointer events which belong together.
+    pub(super) fn pointer_frame(&self) {
+        let mut server = self.server.lock().unwrap();
+        let pointer = server.pointer.expect("the seat has no pointer");
+        server.send(pointer, 5, &[]);
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Start a headless compositor, and an application connected to it.
+pub(super) fn connect() -> (Application, Compositor) {
+    let (ours, theirs) = UnixStream::pair().unwrap();
+    let server = Arc::new(Mutex::new(Server {
+        stream: ours.try_clone().unwrap(),
+        objects: HashMap::new(),
+        serial: 0,
+        roles: HashMap::new(),
+        toplevels: Vec::new(),
+        pointer: None,
+        entered: None,
+        titles: Vec::new(),
+        replies: None,
+    }));
+    let stop = Arc::new(AtomicBool::new(false));
+    std::thread::spawn({
+        let server = server.clone();
+        let stop = stop.clone();
+        move || serve(ours, server, stop)
+    });
+    let display = unsafe { wl::Display::from_fd(theirs.into_raw_fd()) }.unwrap();
+    let app = Application::connect(display).unwrap();
+    (app, Compositor { server, stop })
+}
+
+/// Wait for the compositor to handle our requests, and dispatch the events it sent meanwhile.
+pub(super) fn roundtrip(app: &Application) {
+    app.data.roundtrip().unwrap();
+}
+
+mod tests {
+    use std::any::Any;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::mouse::{MouseButton, MouseEvent};
+    use crate::region::Region;
+    use crate::window::WinHandler;
+
+    use super::super::window::WindowBuilder;
+
+    /// Linux event code of the left mouse button.
+    const BTN_LEFT: u32 = 0x110;
+
+    #[derive(Debug, PartialEq)]
+    enum Heard {
+        Size(Size),
+        Move(Point),
+        Down(MouseButton, Point),
+        Up(MouseButton, Point),
+        Leave,
+        LostFocus,
+        Destroy,
+    }
+
+    struct Recorder {
+        heard: Rc<RefCell<Vec<Heard>>>,
+        handle: Option<crate::WindowHandle>,
+    }
+
+    impl WinHandler for Recorder {
+        fn connect(&mut self, handle: &crate::WindowHandle) {
+            self.handle = Some(handle.clone());
+        }
+
+        fn size(&mut self, size: Size) {
+            self.heard.borrow_mut().push(Heard::Size(size));
+        }
+
+        fn prepare_paint(&mut self) {}
+
+        fn paint(&mut self, _: &Region) {}
+
+        #[cfg(feature = "accesskit")]
+        fn accesskit_tree(&mut self) -> accesskit::TreeUpdate {
+            unimplemented!()
+        }
+
+        fn mouse_move(&mut self, event: &MouseEvent) {
+            self.heard.borrow_mut().push(Heard::Move(event.pos));
+        }
+
+        fn mouse_down(&mut self, event: &MouseEvent) {
+            self.heard
+                .borrow_mut()
+                .push(Heard::Down(event.button, event.pos));
+        }
+
+        fn mouse_up(&mut self, event: &MouseEvent) {
+            self.heard
+                .borrow_mut()
+                .push(Heard::Up(event.button, event.pos));
+        }
+
+        fn mouse_leave(&mut self) {
+            self.heard.borrow_mut().push(Heard::Leave);
+        }
+
+        fn lost_focus(&mut self) {
+            self.heard.borrow_mut().push(Heard::LostFocus);
+        }
+
+        fn request_close(&mut self) {
+            self.handle.as_ref().unwrap().close();
+        }
+
+        fn destroy(&mut self) {
+            self.heard.borrow_mut().push(Heard::Destroy);
+        }
+
+        fn as_any(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn window(app: &Application) -> Rc<RefCell<Vec<Heard>>> {
+        window_and_handle(app).0
+    }
+
+    fn window_and_handle(app: &Application) -> (Rc<RefCell<Vec<Heard>>>, crate::WindowHandle) {
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        let handle = WindowBuilder::new(app.clone())
+            .handler(Box::new(Recorder {
+                heard: heard.clone(),
+                handle: None,
+            }))
+            .size(Size::new(300., 200.))
+            .build()
+            .unwrap();
+        (heard, handle.into())
+    }
+
+    #[test]
+    fn configure_sizes_the_window() {
+        let (app, compositor) = connect();
+        let heard = window(&app);
+        roundtrip(&app);
+        heard.borrow_mut().clear();
+
+        compositor.configure(Size::new(640., 480.), true);
+        roundtrip(&app);
+        assert_eq!(*heard.borrow(), [Heard::Size(Size::new(640., 480.))]);
+
+        // Outside of an interactive resize, later configures don't wait for a frame either.
+        heard.borrow_mut().clear();
+        compositor.configure(Size::new(800., 600.), true);
+        roundtrip(&app);
+        assert_eq!(*heard.borrow(), [Heard::Size(Size::new(800., 600.))]);
+    }
+
+    #[test]
+    fn pointer_input_reaches_the_handler() {
+        let (app, compositor) = connect();
+        let heard = window(&app);
+        roundtrip(&app);
+        compositor.configure(Size::new(300., 200.), true);
+        roundtrip(&app);
+        heard.borrow_mut().clear();
+
+        compositor.pointer_enter(Point::new(10., 20.));
+        compositor.pointer_motion(Point::new(15., 25.));
+        compositor.pointer_button(BTN_LEFT, true);
+        compositor.pointer_frame();
+        roundtrip(&app);
+        assert_eq!(
+            *heard.borrow(),
+            [
+                Heard::Move(Point::new(15., 25.)),
+                Heard::Down(MouseButton::Left, Point::new(15., 25.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn pointer_input_stays_with_its_window() {
+        let (app, compositor) = connect();
+        let first = window(&app);
+        roundtrip(&app);
+        compositor.configure(Size::new(300., 200.), true);
+        compositor.pointer_enter(Point::new(10., 20.));
+        compositor.pointer_button(BTN_LEFT, true);
+        compositor.pointer_frame();
+        let second = window(&app);
+        roundtrip(&app);
+        compositor.configure(Size::new(300., 200.), true);
+        roundtrip(&app);
+        first.borrow_mut().clear();
+        second.borrow_mut().clear();
+
+        // Releasing the button and moving to the other window in one frame.
+        compositor.pointer_button(BTN_LEFT, false);
+        compositor.pointer_leave();
+        compositor.pointer_enter(Point::new(30., 40.));
+        compositor.pointer_frame();
+        roundtrip(&app);
+        assert_eq!(
+            *first.borrow(),
+            [
+                Heard::Up(MouseButton::Left, Point::new(10., 20.)),
+                Heard::Leave,
+            ]
+        );
+        assert_eq!(*second.borrow(), [Heard::Move(Point::new(30., 40.))]);
+    }
+
+    #[test]
+    fn close_from_request_close() {
+        let (app, compositor) = connect();
+        let heard = window(&app);
+        roundtrip(&app);
+        compositor.configure(Size::new(300., 200.), true);
+        roundtrip(&app);
+        heard.borrow_mut().clear();
+
+        compositor.close();
+        roundtrip(&app);
+        assert_eq!(*heard.borrow(), [Heard::LostFocus, Heard::Destroy]);
+    }
+
+    #[test]
+    fn request_close_goes_through_the_handler() {
+        let (app, compositor) = connect();
+        let (heard, handle) = window_and_handle(&app);
+        roundtrip(&app);
+        compositor.configure(Size::new(300., 200.), true);
+        roundtrip(&app);
+        heard.borrow_mut().clear();
+
+        handle.request_close();
+        assert!(heard.borrow().is_empty());
+        roundtrip(&app);
+        assert_eq!(*heard.borrow(), [Heard::LostFocus, Heard::Destroy]);
+    }
+
+    #[test]
+    fn first_configure_sends_the_title_again() {
+        let (app, compositor) = connect();
+        WindowBuilder::new(app.clone())
+            .handler(Box::new(Recorder {
+                heard: Rc::default(),
+                handle: None,
+            }))
+            .title("Notes")
+            .build()
+            .unwrap();
+        roundtrip(&app);
+        assert_eq!(compositor.titles(), ["Notes"]);
+
+        compositor.configure(Size::new(300., 200.), true);
+        roundtrip(&app);
+        // The title is sent while handling the configure, after the first roundtrip's sync.
+        roundtrip(&app);
+        assert_eq!(compositor.titles(), ["Notes", "Notes"]);
+    }
+}
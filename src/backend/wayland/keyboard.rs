@@ -12,6 +12,13 @@ use super::application::Data;
 use super::surfaces::buffers;
 use crate::backend::shared::xkb;
 
+/// Key events, with the window they're for.
+type KeyQueue = calloop::channel::Sender<(u64, KeyEvent)>;
+
+/// An event for the keyboard of a seat, `None` once the keyboard is gone, with the window an
+/// enter is for.
+type SeatEvent = (u32, Option<wl_keyboard::Event>, Option<u64>, KeyQueue);
+
 #[allow(unused)]
 #[derive(Clone)]
 struct CachedKeyPress {
@@ -21,7 +28,7 @@ struct CachedKeyPress {
     key: u32,
     repeat: bool,
     state: wayland_client::protocol::wl_keyboard::KeyState,
-    queue: calloop::channel::Sender<KeyEvent>,
+    queue: KeyQueue,
 }
 
 impl CachedKeyPress {
@@ -48,36 +55,35 @@ impl Default for Repeat {
 }
 
 struct Keyboard {
-    /// Whether we've currently got keyboard focus.
-    focused: bool,
-    repeat: Repeat,
+    /// The window with keyboard focus, which gets the key events.
+    window: Option<u64>,
+    /// The keys which are held down, as xkb keycodes.
+    pressed: Vec<u32>,
+    /// How to repeat held keys, or `None` if the compositor disabled repeating.
+    repeat: Option<Repeat>,
     last_key_press: Option<CachedKeyPress>,
+    /// The pending repeat of `last_key_press`, if any.
+    repeat_timeout: Option<calloop::timer::Timeout>,
     xkb_context: xkb::Context,
     xkb_keymap: std::cell::RefCell<Option<xkb::Keymap>>,
     xkb_state: std::cell::RefCell<Option<xkb::State>>,
-    xkb_mods: std::cell::Cell<Modifiers>,
 }
 
-impl Default for Keyboard {
-    fn default() -> Self {
+impl Keyboard {
+    fn new() -> Self {
         Self {
-            focused: false,
-            repeat: Repeat::default(),
+            window: None,
+            pressed: Vec::new(),
+            repeat: Some(Repeat::default()),
             last_key_press: None,
+            repeat_timeout: None,
             xkb_context: xkb::Context::new(),
             xkb_keymap: std::cell::RefCell::new(None),
             xkb_state: std::cell::RefCell::new(None),
-            xkb_mods: std::cell::Cell::new(Modifiers::empty()),
         }
     }
-}
 
-impl Keyboard {
-    fn focused(&mut self, updated: bool) {
-        self.focused = updated;
-    }
-
-    fn repeat(&mut self, u: Repeat) {
+    fn repeat(&mut self, u: Option<Repeat>) {
         self.repeat = u;
     }
 
@@ -104,23 +110,45 @@ impl Keyboard {
         let keystate = match keystroke.state {
             wl_keyboard::KeyState::Released => {
                 self.replace_last_key_press(self.release_last_key_press(keystroke));
+                self.pressed.retain(|key| *key != keystroke.key);
                 KeyState::Up
             }
             wl_keyboard::KeyState::Pressed => {
-                self.replace_last_key_press(Some(keystroke.repeat()));
+                if !self.pressed.contains(&keystroke.key) {
+                    self.pressed.push(keystroke.key);
+                }
+                // Modifiers and the like don't repeat, but they do end the repeat of any other key.
+                let repeats = matches!(
+                    &*self.xkb_keymap.borrow(),
+                    Some(keymap) if keymap.repeats(keystroke.key)
+                );
+                self.replace_last_key_press(repeats.then(|| keystroke.repeat()));
                 KeyState::Down
             }
             _ => panic!("unrecognised key event"),
         };
 
-        let mut event = self.xkb_state.borrow_mut().as_mut().unwrap().key_event(
-            keystroke.key,
-            keystate,
-            keystroke.repeat,
-        );
-        event.mods = self.xkb_mods.get();
+        self.send(&keystroke.queue, keystroke.key, keystate, keystroke.repeat);
+    }
 
-        if let Err(cause) = keystroke.queue.send(event) {
+    /// Send the key event for `key` to the focused window.
+    fn send(&self, queue: &KeyQueue, key: u32, keystate: KeyState, repeat: bool) {
+        let window = match self.window {
+            Some(window) => window,
+            None => {
+                tracing::debug!("dropping key event without a focused window {:?}", key);
+                return;
+            }
+        };
+        let event = match self.xkb_state.borrow_mut().as_mut() {
+            Some(state) => state.key_event(key, keystate, repeat),
+            None => {
+                tracing::warn!("dropping key event, the compositor didn't send a keymap");
+                return;
+            }
+        };
+
+        if let Err(cause) = queue.send((window, event)) {
             tracing::error!("failed to send druid key event: {:?}", cause);
         }
     }
@@ -129,41 +157,57 @@ impl Keyboard {
         &mut self,
         seat: u32,
         event: wl_keyboard::Event,
-        keyqueue: calloop::channel::Sender<KeyEvent>,
+        window: Option<u64>,
+        keyqueue: KeyQueue,
     ) {
         tracing::trace!("consume {:?} -> {:?}", seat, event);
         match event {
             wl_keyboard::Event::Keymap { format, fd, size } => {
                 if !matches!(format, wl_keyboard::KeymapFormat::XkbV1) {
-                    panic!("only xkb keymap supported for now");
+                    // Without a keymap we have no way of interpreting keys, so they get dropped.
+                    tracing::error!("unsupported keymap format {:?}", format);
+                    let _ = nix::unistd::close(fd);
+                    self.xkb_keymap.replace(None);
+                    self.xkb_state.replace(None);
+                    return;
                 }
 
-                // TODO to test memory ownership we copy the memory. That way we can deallocate it
-                // and see if we get a segfault.
-                let keymap_data = unsafe {
-                    buffers::Mmap::from_raw_private(
-                        fd,
-                        size.try_into().unwrap(),
-                        0,
-                        size.try_into().unwrap(),
-                    )
-                    .unwrap()
-                    .as_ref()
-                    .to_vec()
-                };
-
-                // keymap data is '\0' terminated.
-                let keymap = self.xkb_context.keymap_from_slice(&keymap_data);
+                let keymap = self.xkb_context.keymap_from_slice(&read_keymap(fd, size));
                 let keymapstate = self.xkb_context.state_from_keymap(&keymap);
 
                 self.xkb_keymap.replace(Some(keymap));
                 self.xkb_state.replace(keymapstate);
             }
-            wl_keyboard::Event::Enter { .. } => {
-                self.focused(true);
+            wl_keyboard::Event::Enter { keys, .. } => {
+                self.window = window;
+                // Keys which were held before we got the focus are released in this window, so
+                // the handler hears about that, though it never saw them go down. `keys` is an
+                // array of native endian u32s.
+                self.pressed = keys
+                    .chunks_exact(4)
+                    .map(|raw| u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]) + 8)
+                    .collect();
             }
             wl_keyboard::Event::Leave { .. } => {
-                self.focused(false);
+                // Keys held while we lose focus must not keep repeating.
+                self.replace_last_key_press(None);
+                // We don't hear about keys released elsewhere, so release them now, or the
+                // handler would think they're stuck down.
+                for key in std::mem::take(&mut self.pressed) {
+                    self.send(&keyqueue, key, KeyState::Up, false);
+                }
+                // Likewise for the modifiers, which the compositor sends again on the next enter.
+                if let Some(state) = self.xkb_state.borrow_mut().as_mut() {
+                    state.update_xkb_state(xkb::ActiveModifiers {
+                        base_mods: 0,
+                        latched_mods: 0,
+                        locked_mods: 0,
+                        base_layout: 0,
+                        latched_layout: 0,
+                        locked_layout: 0,
+                    });
+                }
+                self.window = None;
             }
             wl_keyboard::Event::Key {
                 serial,
@@ -183,7 +227,7 @@ impl Keyboard {
                     seat,
                     serial,
                     timestamp: time,
-                    key: key + 8, // TODO: understand the magic 8.
+                    key: key + 8, // xkb keycodes are evdev scancodes + 8.
                     state,
                     queue: keyqueue,
                 })
@@ -196,7 +240,10 @@ impl Keyboard {
                 ..
             } => {
                 let mut state = self.xkb_state.borrow_mut();
-                let state = state.as_mut().unwrap();
+                let state = match state.as_mut() {
+                    Some(state) => state,
+                    None => return,
+                };
                 state.update_xkb_state(xkb::ActiveModifiers {
                     base_mods: mods_depressed,
                     latched_mods: mods_latched,
@@ -209,10 +256,11 @@ impl Keyboard {
             }
             wl_keyboard::Event::RepeatInfo { rate, delay } => {
                 tracing::trace!("keyboard repeat info received {:?} {:?}", rate, delay);
-                self.repeat(Repeat {
+                // A rate of zero disables repeating.
+                self.repeat((rate > 0).then(|| Repeat {
                     rate: std::time::Duration::from_millis((1000 / rate) as u64),
                     delay: std::time::Duration::from_millis(delay as u64),
-                });
+                }));
             }
             evt => {
                 tracing::warn!("unimplemented keyboard event: {:?}", evt);
@@ -222,29 +270,32 @@ impl Keyboard {
 }
 
 pub(super) struct State {
-    apptx: calloop::channel::Sender<KeyEvent>,
-    apprx: std::cell::RefCell<Option<calloop::channel::Channel<KeyEvent>>>,
-    tx: calloop::channel::Sender<(u32, wl_keyboard::Event, calloop::channel::Sender<KeyEvent>)>,
+    /// Serial of the most recent keyboard enter or key press, which the clipboard needs.
+    serial: std::rc::Rc<std::cell::Cell<u32>>,
+    apptx: KeyQueue,
+    apprx: std::cell::RefCell<Option<calloop::channel::Channel<(u64, KeyEvent)>>>,
+    /// Events for the keyboard of each seat.
+    tx: calloop::channel::Sender<SeatEvent>,
 }
 
 impl Default for State {
     fn default() -> Self {
-        let (apptx, apprx) = calloop::channel::channel::<KeyEvent>();
-        let (tx, rx) = calloop::channel::channel::<(
-            u32,
-            wl_keyboard::Event,
-            calloop::channel::Sender<KeyEvent>,
-        )>();
+        let (apptx, apprx) = calloop::channel::channel::<(u64, KeyEvent)>();
+        let (tx, rx) = calloop::channel::channel::<SeatEvent>();
         let state = Self {
+            serial: Default::default(),
             apptx,
             apprx: std::cell::RefCell::new(Some(apprx)),
             tx,
         };
 
         std::thread::spawn(move || {
-            let mut eventloop: calloop::EventLoop<(calloop::LoopSignal, Keyboard)> =
-                calloop::EventLoop::try_new()
-                    .expect("failed to initialize the keyboard event loop!");
+            // Each seat has its own keyboard, so that they repeat independently.
+            let mut eventloop: calloop::EventLoop<(
+                calloop::LoopSignal,
+                std::collections::HashMap<u32, Keyboard>,
+            )> = calloop::EventLoop::try_new()
+                .expect("failed to initialize the keyboard event loop!");
             let signal = eventloop.get_signal();
             let handle = eventloop.handle();
             let repeat = calloop::timer::Timer::<CachedKeyPress>::new().unwrap();
@@ -260,14 +311,29 @@ impl Default for State {
                             }
                             calloop::channel::Event::Msg(keyevent) => keyevent,
                         };
-                        state.1.consume(event.0, event.1, event.2);
-                        match &state.1.last_key_press {
-                            None => repeater.cancel_all_timeouts(),
-                            Some(cached) => {
-                                repeater.cancel_all_timeouts();
-                                repeater.add_timeout(state.1.repeat.delay, cached.clone());
+                        let wlevent = match event.1 {
+                            Some(wlevent) => wlevent,
+                            None => {
+                                // The keyboard is gone, so stop repeating its keys.
+                                if let Some(keyboard) = state.1.remove(&event.0) {
+                                    if let Some(timeout) = keyboard.repeat_timeout {
+                                        repeater.cancel_timeout(&timeout);
+                                    }
+                                }
+                                return;
                             }
                         };
+                        let keyboard = state.1.entry(event.0).or_insert_with(Keyboard::new);
+                        keyboard.consume(event.0, wlevent, event.2, event.3);
+                        if let Some(timeout) = keyboard.repeat_timeout.take() {
+                            repeater.cancel_timeout(&timeout);
+                        }
+                        if let (Some(cached), Some(repeat)) =
+                            (&keyboard.last_key_press, &keyboard.repeat)
+                        {
+                            keyboard.repeat_timeout =
+                                Some(repeater.add_timeout(repeat.delay, cached.clone()));
+                        }
                     }
                 })
                 .unwrap();
@@ -275,8 +341,15 @@ impl Default for State {
             // generate repeat keypresses.
             handle
                 .insert_source(repeat, |event, timer, state| {
-                    timer.add_timeout(state.1.repeat.rate, event.clone());
-                    state.1.keystroke(&event);
+                    let keyboard = match state.1.get_mut(&event.seat) {
+                        Some(keyboard) => keyboard,
+                        None => return,
+                    };
+                    if let Some(repeat) = &keyboard.repeat {
+                        keyboard.repeat_timeout =
+                            Some(timer.add_timeout(repeat.rate, event.clone()));
+                    }
+                    keyboard.keystroke(&event);
                 })
                 .unwrap();
 
@@ -284,7 +357,7 @@ impl Default for State {
             eventloop
                 .run(
                     std::time::Duration::from_secs(60),
-                    &mut (signal, Keyboard::default()),
+                    &mut (signal, std::collections::HashMap::new()),
                     |_ignored| {
                         tracing::trace!("keyboard event loop idle");
                     },
@@ -297,30 +370,157 @@ impl Default for State {
     }
 }
 
+/// Read the keymap the compositor sent, which is '\0' terminated.
+fn read_keymap(fd: std::os::unix::io::RawFd, size: u32) -> Vec<u8> {
+    // TODO to test memory ownership we copy the memory. That way we can deallocate it
+    // and see if we get a segfault.
+    unsafe {
+        buffers::Mmap::from_raw_private(fd, size.try_into().unwrap(), 0, size.try_into().unwrap())
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+}
+
+/// The xkb state of each seat's keyboard, by the registry id of the seat.
+///
+/// Keys are handled on their own thread, but this is kept on the main thread, so pointer events
+/// always see the modifiers from the keyboard events which came before them.
+struct SeatModifiers {
+    xkb_context: xkb::Context,
+    states: std::cell::RefCell<std::collections::HashMap<u32, xkb::State>>,
+}
+
+impl SeatModifiers {
+    fn consume(&self, seat: u32, event: &wl_keyboard::Event) {
+        let mut states = self.states.borrow_mut();
+        match event {
+            wl_keyboard::Event::Keymap { format, fd, size } => {
+                let keymap = match format {
+                    wl_keyboard::KeymapFormat::XkbV1 => {
+                        self.xkb_context.keymap_from_slice(&read_keymap(*fd, *size))
+                    }
+                    _ => {
+                        states.remove(&seat);
+                        return;
+                    }
+                };
+                match self.xkb_context.state_from_keymap(&keymap) {
+                    Some(state) => states.insert(seat, state),
+                    None => states.remove(&seat),
+                };
+            }
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                if let Some(state) = states.get_mut(&seat) {
+                    state.update_xkb_state(xkb::ActiveModifiers {
+                        base_mods: *mods_depressed,
+                        latched_mods: *mods_latched,
+                        locked_mods: *mods_locked,
+                        base_layout: *group,
+                        latched_layout: 0,
+                        locked_layout: 0,
+                    });
+                }
+            }
+            // The compositor sends the modifiers again on the next enter.
+            wl_keyboard::Event::Leave { .. } => {
+                if let Some(state) = states.get_mut(&seat) {
+                    state.update_xkb_state(xkb::ActiveModifiers {
+                        base_mods: 0,
+                        latched_mods: 0,
+                        locked_mods: 0,
+                        base_layout: 0,
+                        latched_layout: 0,
+                        locked_layout: 0,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 pub struct Manager {
     inner: std::sync::Arc<State>,
+    mods: std::rc::Rc<SeatModifiers>,
 }
 
 impl Default for Manager {
     fn default() -> Self {
         Self {
             inner: std::sync::Arc::new(State::default()),
+            mods: std::rc::Rc::new(SeatModifiers {
+                xkb_context: xkb::Context::new(),
+                states: Default::default(),
+            }),
         }
     }
 }
 
 impl Manager {
+    /// The serial of the most recent keyboard input, shared so it stays up to date.
+    pub(super) fn serial(&self) -> std::rc::Rc<std::cell::Cell<u32>> {
+        self.inner.serial.clone()
+    }
+
+    /// The modifiers which are currently active on the seat with the given registry id.
+    pub(super) fn modifiers(&self, seat: u32) -> Modifiers {
+        let states = self.mods.states.borrow();
+        let state = states.get(&seat);
+        state
+            .map(xkb::State::modifiers)
+            .unwrap_or_else(Modifiers::empty)
+    }
+
     pub(super) fn attach(
         &self,
+        appdata: &std::sync::Arc<Data>,
         id: u32,
         seat: wlc::Main<wl_seat::WlSeat>,
     ) -> wlc::Main<wl_keyboard::WlKeyboard> {
         let keyboard = seat.get_keyboard();
         keyboard.quick_assign({
+            let appdata = appdata.clone();
             let tx = self.inner.tx.clone();
             let queue = self.inner.apptx.clone();
+            let latest_serial = self.inner.serial.clone();
+            let mods = self.mods.clone();
             move |_, event, _| {
-                if let Err(cause) = tx.send((id, event, queue.clone())) {
+                mods.consume(id, &event);
+                // The focus is per window, so it's handled here, where we know the surface.
+                let mut entered = None;
+                match &event {
+                    wl_keyboard::Event::Enter {
+                        serial, surface, ..
+                    } => {
+                        latest_serial.set(*serial);
+                        let window = appdata.find_window(surface);
+                        entered = window.as_ref().map(|w| w.id());
+                        appdata.keyboard_focus.set(entered);
+                        if let Some(data) = window.and_then(|w| w.data()) {
+                            data.set_keyboard_focus(true);
+                        }
+                    }
+                    wl_keyboard::Event::Key { serial, .. } => latest_serial.set(*serial),
+                    wl_keyboard::Event::Leave { surface, .. } => {
+                        let window = appdata.find_window(surface);
+                        // The enter for the next window may already have been handled.
+                        if window.as_ref().map(|w| w.id()) == appdata.keyboard_focus.get() {
+                            appdata.keyboard_focus.set(None);
+                        }
+                        if let Some(data) = window.and_then(|w| w.data()) {
+                            data.set_keyboard_focus(false);
+                        }
+                    }
+                    _ => {}
+                }
+                if let Err(cause) = tx.send((id, Some(event), entered, queue.clone())) {
                     tracing::error!("failed to transmit keyboard event {:?}", cause);
                 };
             }
@@ -329,13 +529,25 @@ impl Manager {
         keyboard
     }
 
+    /// Forget the keyboard of the seat with the given `id`, which has gone away.
+    pub(super) fn detach(&self, id: u32) {
+        self.mods.states.borrow_mut().remove(&id);
+        if let Err(cause) = self
+            .inner
+            .tx
+            .send((id, None, None, self.inner.apptx.clone()))
+        {
+            tracing::error!("failed to transmit keyboard removal {:?}", cause);
+        }
+    }
+
     // TODO turn struct into a calloop event source.
     pub(super) fn events(&self, handle: &calloop::LoopHandle<std::sync::Arc<Data>>) {
         let rx = self.inner.apprx.borrow_mut().take().unwrap();
         handle
             .insert_source(rx, {
                 move |evt, _ignored, appdata| {
-                    let evt = match evt {
+                    let (window, evt) = match evt {
                         calloop::channel::Event::Msg(e) => e,
                         calloop::channel::Event::Closed => {
                             tracing::info!("keyboard events receiver closed");
@@ -343,37 +555,57 @@ impl Manager {
                         }
                     };
 
-                    if let Some(winhandle) = appdata.acquire_current_window() {
-                        if let Some(windata) = winhandle.data() {
-                            windata.with_handler({
-                                let windata = windata.clone();
-                                let evt = evt;
-                                move |handler| match evt.state {
-                                    KeyState::Up => {
-                                        handler.key_up(evt.clone());
-                                        tracing::trace!(
-                                            "key press event up {:?} {:?}",
-                                            evt,
-                                            windata.active_text_input.get()
-                                        );
-                                    }
-                                    KeyState::Down => {
-                                        let handled = text::simulate_input(
-                                            handler,
-                                            windata.active_text_input.get(),
-                                            evt.clone(),
-                                        );
-                                        tracing::trace!(
-                                            "key press event down {:?} {:?} {:?}",
-                                            handled,
-                                            evt,
-                                            windata.active_text_input.get()
-                                        );
-                                    }
+                    // The events go to the window which had the focus when they happened, so
+                    // the keys released as the focus leaves reach the window it left.
+                    let winhandle = match appdata.handles.borrow().get(&window).cloned() {
+                        Some(winhandle) => winhandle,
+                        None => {
+                            tracing::debug!("dropping key event for closed window {:?}", evt);
+                            return;
+                        }
+                    };
+                    let windata = match winhandle.data() {
+                        Some(windata) => windata,
+                        None => return,
+                    };
+                    // Looked up first, the menu can't be borrowed while the handler runs.
+                    let command = match evt.state {
+                        KeyState::Down => winhandle.menu_command(&evt),
+                        _ => None,
+                    };
+                    windata.with_handler({
+                        let windata = windata.clone();
+                        let evt = evt;
+                        move |handler| match evt.state {
+                            KeyState::Up => {
+                                handler.key_up(evt.clone());
+                                tracing::trace!(
+                                    "key press event up {:?} {:?}",
+                                    evt,
+                                    windata.active_text_input.get()
+                                );
+                            }
+                            KeyState::Down => {
+                                let mut handled = text::simulate_input(
+                                    handler,
+                                    windata.active_text_input.get(),
+                                    evt.clone(),
+                                );
+                                // Like accelerators, hotkeys the handler ignored go to
+                                // the menu.
+                                if let (false, Some(id)) = (handled, command) {
+                                    handler.command(id);
+                                    handled = true;
                                 }
-                            });
+                                tracing::trace!(
+                                    "key press event down {:?} {:?} {:?}",
+                                    handled,
+                                    evt,
+                                    windata.active_text_input.get()
+                                );
+                            }
                         }
-                    }
+                    });
                 }
             })
             .unwrap();
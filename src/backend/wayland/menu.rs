@@ -16,39 +16,131 @@
 use super::window::WindowHandle;
 use crate::common_util::strip_access_key;
 use crate::hotkey::{HotKey, RawMods};
-use crate::keyboard::{KbKey, Modifiers};
+use crate::keyboard::{KbKey, KeyEvent, Modifiers};
 
 #[derive(Default, Debug)]
-pub struct Menu;
+pub struct Menu {
+    pub(super) items: Vec<MenuItem>,
+}
 
 #[derive(Debug)]
-struct MenuItem;
+pub(super) enum MenuItem {
+    Action {
+        id: u32,
+        text: String,
+        key: Option<HotKey>,
+        selected: Option<bool>,
+        enabled: bool,
+    },
+    Dropdown {
+        menu: Menu,
+        text: String,
+        enabled: bool,
+    },
+    Separator,
+}
 
 impl Menu {
     pub fn new() -> Menu {
-        Menu
+        Menu::default()
     }
 
     pub fn new_for_popup() -> Menu {
-        Menu
+        Menu::default()
     }
 
-    pub fn add_dropdown(&mut self, menu: Menu, text: &str, _enabled: bool) {
-        tracing::warn!("unimplemented");
+    pub fn add_dropdown(&mut self, menu: Menu, text: &str, enabled: bool) {
+        self.items.push(MenuItem::Dropdown {
+            menu,
+            text: strip_access_key(text),
+            enabled,
+        });
     }
 
     pub fn add_item(
         &mut self,
-        _id: u32,
-        _text: &str,
-        _key: Option<&HotKey>,
-        _selected: Option<bool>,
-        _enabled: bool,
+        id: u32,
+        text: &str,
+        key: Option<&HotKey>,
+        selected: Option<bool>,
+        enabled: bool,
     ) {
-        tracing::warn!("unimplemented");
+        self.items.push(MenuItem::Action {
+            id,
+            text: strip_access_key(text),
+            key: key.cloned(),
+            selected,
+            enabled,
+        });
     }
 
     pub fn add_separator(&mut self) {
-        tracing::warn!("unimplemented");
+        self.items.push(MenuItem::Separator);
+    }
+
+    /// The command of the enabled item whose hotkey matches `event`, including in submenus.
+    pub(super) fn command_for(&self, event: &KeyEvent) -> Option<u32> {
+        self.items.iter().find_map(|item| match item {
+            MenuItem::Action {
+                id,
+                key: Some(key),
+                enabled: true,
+                ..
+            } if key.matches(event) => Some(*id),
+            MenuItem::Dropdown {
+                menu,
+                enabled: true,
+                ..
+            } => menu.command_for(event),
+            _ => None,
+        })
+    }
+}
+
+/// How a menu shows `key`, e.g. "Ctrl+Shift+Z".
+pub(super) fn hotkey_label(key: &HotKey) -> String {
+    let mut label = String::new();
+    let mods: Modifiers = key.mods.into();
+    if mods.ctrl() {
+        label.push_str("Ctrl+");
+    }
+    if mods.shift() {
+        label.push_str("Shift+");
+    }
+    if mods.alt() {
+        label.push_str("Alt+");
+    }
+    if mods.meta() {
+        label.push_str("Super+");
+    }
+    match &key.key {
+        KbKey::Character(c) => match c.as_str() {
+            " " => label.push_str("Space"),
+            _ => label.extend(c.chars().flat_map(|c| c.to_uppercase())),
+        },
+        KbKey::Escape => label.push_str("Esc"),
+        KbKey::ArrowLeft => label.push_str("Left"),
+        KbKey::ArrowRight => label.push_str("Right"),
+        KbKey::ArrowUp => label.push_str("Up"),
+        KbKey::ArrowDown => label.push_str("Down"),
+        key => label.push_str(&key.to_string()),
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::SysMods;
+
+    #[test]
+    fn hotkey_labels() {
+        let redo = HotKey::new(SysMods::CmdShift, "z");
+        assert_eq!(hotkey_label(&redo), "Ctrl+Shift+Z");
+        assert_eq!(hotkey_label(&HotKey::new(None, KbKey::ArrowUp)), "Up");
+        assert_eq!(
+            hotkey_label(&HotKey::new(RawMods::Alt, KbKey::F4)),
+            "Alt+F4"
+        );
     }
 }
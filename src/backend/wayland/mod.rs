@@ -16,15 +16,24 @@
 
 pub mod application;
 pub mod clipboard;
+mod context_menu;
 mod display;
 pub mod error;
 mod events;
+mod gestures;
+#[cfg(test)]
+mod headless;
 pub mod keyboard;
 pub mod menu;
 mod outputs;
+mod pointer_lock;
 pub mod pointers;
 pub mod screen;
+mod seat;
 pub mod surfaces;
+mod tablet;
+mod text_input;
+mod touch;
 pub mod window;
 
 /// Little enum to make it clearer what some return values mean.
@@ -139,6 +139,7 @@ pub struct Meta {
     pub gid: u32,
     pub name: String,
     pub description: String,
+    /// Size of the output, in display points.
     pub logical: Dimensions,
     pub refresh: i32,
     pub physical: Dimensions,
@@ -187,4 +188,12 @@ impl Meta {
     pub fn id(&self) -> u32 {
         self.gid
     }
+
+    /// The area of the output in the compositor's logical coordinates, which are display points.
+    pub fn logical_rect(&self) -> kurbo::Rect {
+        kurbo::Rect::from_origin_size(
+            (self.position.x as f64, self.position.y as f64),
+            (self.logical.width as f64, self.logical.height as f64),
+        )
+    }
 }
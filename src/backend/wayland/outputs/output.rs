@@ -12,6 +12,10 @@ pub fn detect(
 ) -> Result<calloop::channel::Channel<outputs::Event>, error::Error> {
     let (outputstx, outputsrx) = calloop::channel::channel::<outputs::Event>();
     let xdg_output_manager_id: std::cell::RefCell<Option<u32>> = std::cell::RefCell::new(None);
+    // The latest state of each output, by the name of its global, to tell everyone which output
+    // went away when its global is removed.
+    let located: std::rc::Rc<std::cell::RefCell<std::collections::HashMap<u32, outputs::Meta>>> =
+        Default::default();
     display::GlobalEventDispatch::subscribe(env, {
         move |event: &'_ wlc::GlobalEvent,
               registry: &'_ wlc::Attached<wl_registry::WlRegistry>,
@@ -46,6 +50,7 @@ pub fn detect(
                     let mut xdgmeta = XdgMeta::new();
                     output.quick_assign({
                         let outputstx = outputstx.clone();
+                        let located = located.clone();
                         move |output, event, _ctx| {
                             let mut m = match meta.consume(&output, &event) {
                                 Some(m) => m,
@@ -67,6 +72,7 @@ pub fn detect(
 
                             xdgmeta.modify(&mut m);
                             m.output = Some(output.detach());
+                            located.borrow_mut().insert(id, m.clone());
 
                             if let Err(cause) = outputstx.send(outputs::Event::Located(m)) {
                                 tracing::warn!("unable to transmit output {:?}", cause);
@@ -74,11 +80,24 @@ pub fn detect(
                         }
                     });
                 }
-                wlc::GlobalEvent::Removed { interface, .. } => {
+                wlc::GlobalEvent::Removed { id, interface } => {
                     if interface.as_str() != "wl_output" {
                         return;
                     }
                     tracing::debug!("output removed event {:?} {:?}", registry, interface);
+                    // Outputs which never finished describing themselves were never located.
+                    let meta = match located.borrow_mut().remove(id) {
+                        Some(meta) => meta,
+                        None => return,
+                    };
+                    if let Some(output) = &meta.output {
+                        if output.as_ref().version() >= 3 {
+                            output.release();
+                        }
+                    }
+                    if let Err(cause) = outputstx.send(outputs::Event::Removed(meta)) {
+                        tracing::warn!("unable to transmit output removal {:?}", cause);
+                    }
                 }
             };
         }
@@ -153,6 +172,8 @@ impl XdgMeta {
 #[derive(Default)]
 struct Meta {
     meta: outputs::Meta,
+    /// Size of the current mode, in pixels.
+    mode: outputs::Dimensions,
 }
 
 impl Meta {
@@ -188,13 +209,24 @@ impl Meta {
                 refresh,
             } => {
                 if flags.contains(wl_output::Mode::Current) {
-                    self.meta.logical = outputs::Dimensions::from((*width, *height));
+                    self.mode = outputs::Dimensions::from((*width, *height));
                     self.meta.refresh = *refresh;
                 }
 
                 None
             }
             wl_output::Event::Done => {
+                // Without xdg_output, the logical size is only known from the scale. Outputs
+                // which never told us their scale aren't scaled.
+                let scale = if self.meta.scale > 0.0 {
+                    self.meta.scale
+                } else {
+                    1.0
+                };
+                self.meta.logical = outputs::Dimensions::from((
+                    (self.mode.width as f64 / scale).round() as i32,
+                    (self.mode.height as f64 / scale).round() as i32,
+                ));
                 self.meta.gid = wlc::Proxy::from(output.detach()).id();
                 self.meta.enabled = true;
                 Some(self.meta.clone())
@@ -0,0 +1,130 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pointer locking, and the relative motion which is reported while the pointer is locked.
+use std::cell::Cell;
+
+use wayland_client as wl;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_protocols::unstable::pointer_constraints::v1::client::{
+    zwp_locked_pointer_v1::{self, ZwpLockedPointerV1},
+    zwp_pointer_constraints_v1::Lifetime,
+};
+use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_v1::{
+    self, ZwpRelativePointerV1,
+};
+
+use crate::kurbo::Vec2;
+
+use super::application::Data;
+
+/// A pointer lock requested by a window.
+pub(super) struct Lock {
+    /// The window which asked for the lock.
+    pub(super) window: u64,
+    /// The seat whose pointer is locked.
+    pub(super) seat: u32,
+    /// Whether the compositor has actually locked the pointer yet.
+    pub(super) active: Cell<bool>,
+    locked: wl::Main<ZwpLockedPointerV1>,
+    relative: Option<wl::Main<ZwpRelativePointerV1>>,
+}
+
+impl Lock {
+    pub(super) fn destroy(&self) {
+        self.locked.destroy();
+        if let Some(relative) = &self.relative {
+            relative.destroy();
+        }
+    }
+}
+
+/// Ask the compositor to lock the pointer to `surface`, which belongs to `window`.
+///
+/// The lock only lasts until the pointer leaves the surface, or the window unlocks it.
+pub(super) fn lock(appdata: &std::sync::Arc<Data>, window: u64, surface: &WlSurface) {
+    let constraints = match &appdata.zwp_pointer_constraints_v1 {
+        Some(constraints) => constraints,
+        None => {
+            tracing::warn!("pointer locking is not supported by the compositor");
+            return;
+        }
+    };
+    let (seat, wl_pointer) = match appdata.pointer.lock_target(window) {
+        Some(target) => target,
+        None => {
+            tracing::warn!("unable to lock the pointer without a pointer");
+            return;
+        }
+    };
+    // Only one lock is kept, so a new lock replaces any earlier one.
+    appdata.pointer.unlock();
+
+    let locked = constraints.lock_pointer(surface, &wl_pointer, None, Lifetime::Oneshot);
+    locked.quick_assign({
+        let appdata = appdata.clone();
+        move |_, event, _| match event {
+            zwp_locked_pointer_v1::Event::Locked => appdata.pointer.lock_activated(),
+            zwp_locked_pointer_v1::Event::Unlocked => appdata.pointer.unlock(),
+            _ => tracing::warn!("unhandled locked pointer event {:?}", event),
+        }
+    });
+
+    let relative = match &appdata.zwp_relative_pointer_manager_v1 {
+        Some(manager) => {
+            let relative = manager.get_relative_pointer(&wl_pointer);
+            relative.quick_assign({
+                let appdata = appdata.clone();
+                move |_, event, _| {
+                    if let zwp_relative_pointer_v1::Event::RelativeMotion {
+                        dx,
+                        dy,
+                        dx_unaccel,
+                        dy_unaccel,
+                        ..
+                    } = event
+                    {
+                        let delta = Vec2::new(dx, dy);
+                        dispatch(&appdata, delta, Vec2::new(dx_unaccel, dy_unaccel));
+                    }
+                }
+            });
+            Some(relative)
+        }
+        None => {
+            tracing::warn!("relative pointer motion is not supported by the compositor");
+            None
+        }
+    };
+
+    appdata.pointer.set_lock(Lock {
+        window,
+        seat,
+        active: Cell::new(false),
+        locked,
+        relative,
+    });
+}
+
+fn dispatch(appdata: &Data, delta: Vec2, raw: Vec2) {
+    let window = match appdata.pointer.locked() {
+        Some(window) => window,
+        None => return,
+    };
+    let data = match appdata.handles.borrow().get(&window).and_then(|w| w.data()) {
+        Some(data) => data,
+        None => return,
+    };
+    data.with_handler(|handler| handler.pointer_move_relative(delta, raw));
+}
@@ -1,5 +1,6 @@
-use std::collections::VecDeque;
 use wayland_client::protocol::wl_pointer;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::protocol::wl_shm::WlShm;
 use wayland_client::protocol::wl_surface::{self, WlSurface};
 use wayland_client::{self as wl};
 use wayland_cursor::CursorImageBuffer;
@@ -8,13 +9,45 @@ use wayland_cursor::CursorTheme;
 use crate::keyboard::Modifiers;
 use crate::kurbo::{Point, Vec2};
 use crate::mouse;
+use crate::pointer::{MouseInfo, PointerType, WheelSource};
 
 use super::application::Data;
+use super::pointer_lock::Lock;
+use super::surfaces::decorations::Frame;
 
 // Button constants (linux specific)
 const BTN_LEFT: u32 = 0x110;
 const BTN_RIGHT: u32 = 0x111;
 const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+
+/// The wheel delta of one click of a scroll wheel, like on the other backends (and Windows).
+const WHEEL_STEP: f64 = 120.;
+/// The smooth scroll distance compositors send for one click of a scroll wheel.
+const AXIS_STEP: f64 = 10.;
+
+/// How close together the presses of a multi-click must be.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct MultiClick {
+    /// The longest time between presses.
+    pub(super) interval: std::time::Duration,
+    /// How far (in display points) the pointer may move between presses.
+    pub(super) distance: f64,
+}
+
+impl Default for MultiClick {
+    fn default() -> Self {
+        Self {
+            // this threshold was arbitrarily chosen based on experimention.
+            // there is likely a better default based on research to use.
+            // during experimentation this allowed one to get to around 4 clicks.
+            // but likely heavily dependent on the machine.
+            interval: std::time::Duration::from_millis(500),
+            distance: 4.0,
+        }
+    }
+}
 
 // used to keep track of click event counts.
 #[derive(Debug, Clone)]
@@ -22,6 +55,8 @@ struct ClickDebouncer {
     timestamp: std::time::Instant,
     count: u8,
     previous: mouse::MouseButton,
+    /// Where the previous press happened.
+    pos: Point,
 }
 
 impl Default for ClickDebouncer {
@@ -30,30 +65,26 @@ impl Default for ClickDebouncer {
             timestamp: std::time::Instant::now(),
             count: 1,
             previous: mouse::MouseButton::None,
+            pos: Point::ZERO,
         }
     }
 }
 
 impl ClickDebouncer {
-    // this threshold was arbitrarily chosen based on experimention.
-    // there is likely a better default based on research to use.
-    // during experimentation this allowed one to get to around 4 clicks.
-    // but likely heavily dependent on the machine.
-    const THRESHOLD: std::time::Duration = std::time::Duration::from_millis(500);
-
-    fn reset(ts: std::time::Instant, btn: mouse::MouseButton) -> Self {
+    fn reset(ts: std::time::Instant, btn: mouse::MouseButton, pos: Point) -> Self {
         Self {
             timestamp: ts,
             count: 1,
             previous: btn,
+            pos,
         }
     }
 
-    fn debounce(&mut self, current: MouseEvtKind) -> MouseEvtKind {
+    fn debounce(&mut self, current: MouseEvtKind, limits: MultiClick) -> MouseEvtKind {
         let ts = std::time::Instant::now();
 
         // reset counting and button.
-        if self.timestamp + ClickDebouncer::THRESHOLD < ts {
+        if self.timestamp + limits.interval < ts {
             *self = ClickDebouncer::default();
         }
 
@@ -62,17 +93,21 @@ impl ClickDebouncer {
                 evt.count = self.count;
                 MouseEvtKind::Up(evt)
             }
-            MouseEvtKind::Down(mut evt) if self.previous == evt.button => {
-                self.count += 1;
+            MouseEvtKind::Down(mut evt)
+                if self.previous == evt.button && self.pos.distance(evt.pos) <= limits.distance =>
+            {
+                // Each press extends the window for the next one.
+                self.timestamp = ts;
+                self.count = self.count.saturating_add(1);
                 evt.count = self.count;
                 MouseEvtKind::Down(evt)
             }
-            MouseEvtKind::Down(evt) if self.previous != evt.button => {
-                *self = ClickDebouncer::reset(ts, evt.button);
+            MouseEvtKind::Down(evt) => {
+                *self = ClickDebouncer::reset(ts, evt.button, evt.pos);
                 MouseEvtKind::Down(evt)
             }
             MouseEvtKind::Leave => {
-                *self = ClickDebouncer::reset(ts, mouse::MouseButton::None);
+                *self = ClickDebouncer::reset(ts, mouse::MouseButton::None, Point::ZERO);
                 current
             }
             _ => current,
@@ -81,39 +116,76 @@ impl ClickDebouncer {
 }
 
 /// Collect up mouse events then emit them together on a pointer frame.
+///
+/// Each seat has its own pointer, and so its own position, buttons and focus. The cursor and the
+/// pointer lock are shared by all of them.
 pub(crate) struct Pointer {
+    /// The pointer of each seat, by the seat's registry id.
+    seats: std::cell::RefCell<std::collections::BTreeMap<u32, std::rc::Rc<SeatPointer>>>,
+    /// The seat id, seat and serial of the most recent button press, needed to grab the pointer
+    /// for popups or to move the window.
+    button_press: std::cell::RefCell<Option<(u32, WlSeat, u32)>>,
+    /// How close together presses must be to count as a multi-click.
+    multi_click: std::cell::Cell<MultiClick>,
+    /// Loads the cursor themes.
+    shm: wl::Main<WlShm>,
+    /// The cursor theme at each scale we've shown the cursor at, as loading one is slow.
+    themes: std::cell::RefCell<std::collections::BTreeMap<i32, CursorTheme>>,
+    /// Cache the current cursor, so we can see if it changed
+    current_cursor: std::cell::RefCell<mouse::Cursor>,
+    /// The pointer lock a window asked for, if any.
+    lock: std::cell::RefCell<Option<Lock>>,
+}
+
+/// The pointer of one seat.
+struct SeatPointer {
+    wl_pointer: wl_pointer::WlPointer,
     /// The image surface which contains the cursor image.
-    pub(crate) cursor_surface: wl::Main<WlSurface>,
+    cursor_surface: wl::Main<WlSurface>,
     /// Events that have occurred since the last frame.
-    pub(crate) queued_events: std::cell::RefCell<VecDeque<PointerEvent>>,
+    pending: std::cell::RefCell<Pending>,
+    /// The window the pointer is currently over.
+    focus: std::cell::Cell<Option<u64>>,
+    /// Serial of the most recent enter event, needed to change the cursor.
+    enter_serial: std::cell::Cell<u32>,
+    /// What is scrolling in the current frame, if the compositor told us.
+    axis_source: std::cell::Cell<Option<wl_pointer::AxisSource>>,
+    /// The scroll wheel clicks on each axis since the last frame.
+    discrete: std::cell::Cell<Vec2>,
     /// Currently pressed buttons
     buttons: std::cell::RefCell<mouse::MouseButtons>,
     /// Current position
     pos: std::cell::Cell<Point>,
-    wl_pointer: std::cell::RefCell<Option<wl_pointer::WlPointer>>,
+    /// The decorations the pointer is over, which handle the pointer themselves.
+    frame: std::cell::RefCell<Option<Frame>>,
     // used to keep track of the current clicking
     clickevent: std::cell::RefCell<ClickDebouncer>,
-    /// cursor theme data.
-    theme: std::cell::RefCell<CursorTheme>,
-    /// Cache the current cursor, so we can see if it changed
-    current_cursor: std::cell::RefCell<mouse::Cursor>,
+    /// The scale of the window the pointer is over.
+    scale: std::cell::Cell<i32>,
+    /// Whether the window the pointer is over hides the cursor.
+    hidden: std::cell::Cell<bool>,
+}
+
+impl Drop for SeatPointer {
+    fn drop(&mut self) {
+        self.cursor_surface.destroy();
+    }
 }
 
 /// Raw wayland pointer events.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub(crate) enum PointerEvent {
     /// Mouse moved/entered
-    Motion {
-        pointer: wl_pointer::WlPointer,
-        point: Point,
-    },
+    Motion { point: Point },
     /// Mouse button pressed/released
     Button {
         button: u32,
         state: wl_pointer::ButtonState,
     },
-    /// Axis movement
-    Axis { axis: wl_pointer::Axis, value: f64 },
+    /// Axis movement, in display points until the frame turns wheel clicks into `WHEEL_STEP`s
+    Axis { delta: Vec2 },
+    /// A scroll sequence ended
+    AxisStop,
     /// Mouse left
     Leave,
 }
@@ -125,48 +197,221 @@ pub(crate) enum MouseEvtKind {
     Up(mouse::MouseEvent),
     Down(mouse::MouseEvent),
     Leave,
-    Wheel(mouse::MouseEvent),
+    Wheel(crate::PointerEvent),
 }
 
 #[allow(unused)]
 impl Pointer {
     /// Create a new pointer
-    pub fn new(theme: CursorTheme, cursor: wl::Main<WlSurface>) -> Self {
+    pub fn new(shm: wl::Main<WlShm>) -> Self {
+        Pointer {
+            seats: std::cell::RefCell::new(std::collections::BTreeMap::new()),
+            button_press: std::cell::RefCell::new(None),
+            multi_click: std::cell::Cell::new(MultiClick::default()),
+            shm,
+            themes: std::cell::RefCell::new(std::collections::BTreeMap::new()),
+            current_cursor: std::cell::RefCell::new(mouse::Cursor::Arrow),
+            lock: std::cell::RefCell::new(None),
+        }
+    }
+
+    /// Start tracking the pointer of seat `id`, which shows its cursor on `cursor`.
+    pub(super) fn attach(
+        &self,
+        id: u32,
+        wl_pointer: wl_pointer::WlPointer,
+        cursor: wl::Main<WlSurface>,
+    ) {
+        tracing::trace!("attaching pointer reference {:?}", wl_pointer);
         // ignore all events
         cursor.quick_assign(|a1, event, a2| {
             tracing::trace!("pointer surface event {:?} {:?} {:?}", a1, event, a2);
         });
 
-        Pointer {
-            theme: std::cell::RefCell::new(theme),
+        let pointer = SeatPointer {
+            wl_pointer,
+            cursor_surface: cursor,
+            pending: std::cell::RefCell::new(Pending::default()),
+            focus: std::cell::Cell::new(None),
+            enter_serial: std::cell::Cell::new(0),
+            axis_source: std::cell::Cell::new(None),
+            discrete: std::cell::Cell::new(Vec2::ZERO),
             buttons: std::cell::RefCell::new(mouse::MouseButtons::new()),
             pos: std::cell::Cell::new(Point::ZERO), // will get set before we emit any events
-            queued_events: std::cell::RefCell::new(VecDeque::with_capacity(3)), // should be enough most of the time
-            cursor_surface: cursor,
-            wl_pointer: std::cell::RefCell::new(None),
-            current_cursor: std::cell::RefCell::new(mouse::Cursor::Arrow),
+            frame: std::cell::RefCell::new(None),
             clickevent: std::cell::RefCell::new(ClickDebouncer::default()),
+            scale: std::cell::Cell::new(1),
+            hidden: std::cell::Cell::new(false),
+        };
+        self.seats
+            .borrow_mut()
+            .insert(id, std::rc::Rc::new(pointer));
+    }
+
+    /// Forget the pointer of seat `id`, once the seat no longer has one.
+    ///
+    /// Nothing else tells the window the pointer was over that it's gone, so it hears that the
+    /// pointer left.
+    pub(super) fn detach(&self, appdata: &Data, id: u32) {
+        let pointer = match self.seats.borrow_mut().remove(&id) {
+            Some(pointer) => pointer,
+            None => return,
+        };
+        // The lock refers to the pointer, so it can't outlive it.
+        let locked = matches!(&*self.lock.borrow(), Some(lock) if lock.seat == id);
+        if locked {
+            self.unlock();
+        }
+        let pressed = matches!(&*self.button_press.borrow(), Some((seat, ..)) if *seat == id);
+        if pressed {
+            self.button_press.replace(None);
+        }
+        if let Some(frame) = pointer.frame.take() {
+            frame.pointer_leave();
+        }
+        let window = pointer.focus.get();
+        let data = window.and_then(|window| appdata.handles.borrow().get(&window)?.data());
+        if let Some(data) = data {
+            data.with_handler(|handler| handler.mouse_leave());
         }
     }
 
-    pub fn attach(&self, current: wl_pointer::WlPointer) {
-        tracing::trace!("attaching pointer reference {:?}", current);
-        self.wl_pointer.replace(Some(current));
+    fn seat(&self, id: u32) -> Option<std::rc::Rc<SeatPointer>> {
+        self.seats.borrow().get(&id).cloned()
     }
 
-    #[inline]
-    pub fn push(&self, event: PointerEvent) {
-        self.queued_events.borrow_mut().push_back(event);
+    /// The window the pointer of seat `id` is over, and where it is in that window.
+    pub(super) fn focus(&self, id: u32) -> Option<(u64, Point)> {
+        let pointer = self.seat(id)?;
+        let window = pointer.focus.get()?;
+        Some((window, pointer.pos.get()))
     }
 
-    #[inline]
-    pub fn pop(&self) -> Option<PointerEvent> {
-        self.queued_events.borrow_mut().pop_front()
+    pub(super) fn set_multi_click(&self, limits: MultiClick) {
+        self.multi_click.set(limits);
     }
 
-    #[inline]
-    pub fn cursor(&self) -> &WlSurface {
-        &self.cursor_surface
+    /// The seat and serial of the most recent button press, if there was one.
+    ///
+    /// This is the press of any seat, and the seat which made it is the one to grab or drag with.
+    pub(super) fn button_press(&self) -> Option<(WlSeat, u32)> {
+        let press = self.button_press.borrow();
+        press
+            .as_ref()
+            .map(|(_, seat, serial)| (seat.clone(), *serial))
+    }
+
+    /// The window the pointer which made the most recent button press is over.
+    pub(super) fn press_focus(&self) -> Option<u64> {
+        let seat = self.button_press.borrow().as_ref()?.0;
+        self.seat(seat)?.focus.get()
+    }
+
+    /// The seat and pointer to lock to `window`.
+    ///
+    /// That's the pointer over the window, or any pointer if none is, as the compositor waits for
+    /// the pointer to be over the window before locking it anyway.
+    pub(super) fn lock_target(&self, window: u64) -> Option<(u32, wl_pointer::WlPointer)> {
+        let seats = self.seats.borrow();
+        let (id, pointer) = seats
+            .iter()
+            .find(|(_, pointer)| pointer.focus.get() == Some(window))
+            .or_else(|| seats.iter().next())?;
+        Some((*id, pointer.wl_pointer.clone()))
+    }
+
+    /// The window the pointer is locked to, once the compositor has locked it.
+    pub(super) fn locked(&self) -> Option<u64> {
+        self.lock
+            .borrow()
+            .as_ref()
+            .filter(|lock| lock.active.get())
+            .map(|lock| lock.window)
+    }
+
+    /// Whether the compositor has locked the pointer of seat `id`.
+    fn is_locked(&self, id: u32) -> bool {
+        matches!(&*self.lock.borrow(), Some(lock) if lock.seat == id && lock.active.get())
+    }
+
+    pub(super) fn set_lock(&self, lock: Lock) {
+        self.lock.replace(Some(lock));
+    }
+
+    /// The compositor locked the pointer, so hide it until it's unlocked.
+    pub(super) fn lock_activated(&self) {
+        let seat = match &*self.lock.borrow() {
+            Some(lock) => {
+                lock.active.set(true);
+                lock.seat
+            }
+            None => return,
+        };
+        if let Some(pointer) = self.seat(seat) {
+            pointer
+                .wl_pointer
+                .set_cursor(pointer.enter_serial.get(), None, 0, 0);
+        }
+    }
+
+    /// Release the pointer lock if `window` holds it, whether or not it's active yet.
+    pub(super) fn unlock_window(&self, window: u64) {
+        let owned = matches!(&*self.lock.borrow(), Some(lock) if lock.window == window);
+        if owned {
+            self.unlock();
+        }
+    }
+
+    /// Release the pointer lock, if there is one, and show the cursor again.
+    pub(super) fn unlock(&self) {
+        let lock = match self.lock.take() {
+            Some(lock) => lock,
+            None => return,
+        };
+        lock.destroy();
+        let pointer = match self.seat(lock.seat) {
+            Some(pointer) => pointer,
+            None => return,
+        };
+        // Once the pointer has left, the next enter sets the cursor anyway.
+        if lock.active.get() && pointer.focus.get().is_some() && !pointer.hidden.get() {
+            let cursor = self.current_cursor.borrow().clone();
+            self.apply_cursor(&pointer, cursor);
+        }
+    }
+
+    /// Hide or show the cursor for `window`, which only changes what's displayed for the pointers
+    /// over it. The window tells us again on the next enter.
+    pub(super) fn set_hidden(&self, window: u64, hidden: bool) {
+        for (id, pointer) in self.seats.borrow().iter() {
+            if pointer.focus.get() != Some(window) || pointer.hidden.replace(hidden) == hidden {
+                continue;
+            }
+            // The cursor is already hidden while the pointer is locked.
+            if self.is_locked(*id) {
+                continue;
+            }
+            self.show(pointer);
+        }
+    }
+
+    /// Set the cursor for the window the pointer is over, which might be no cursor at all.
+    fn show(&self, pointer: &SeatPointer) {
+        if pointer.hidden.get() {
+            pointer
+                .wl_pointer
+                .set_cursor(pointer.enter_serial.get(), None, 0, 0);
+        } else {
+            let cursor = self.current_cursor.borrow().clone();
+            self.apply_cursor(pointer, cursor);
+        }
+    }
+
+    /// Match the cursor to the scale of the window the pointer is over.
+    fn set_scale(&self, pointer: &SeatPointer, scale: i32) {
+        if pointer.scale.replace(scale) != scale {
+            self.show(pointer);
+        }
     }
 
     pub fn replace(&self, cursor: &mouse::Cursor) {
@@ -179,114 +424,261 @@ impl Pointer {
             return;
         }
 
-        let b = self.wl_pointer.borrow_mut();
-        let wl_pointer = match &*b {
-            None => return,
-            Some(p) => p,
-        };
-
         tracing::trace!("replacing cursor {:?} -> {:?}", current, cursor);
-        let buffer = match self.get_cursor_buffer(&cursor) {
+        self.current_cursor.replace(cursor.clone());
+        // The cursor stays hidden while the pointer is locked or the window hides it, and shows
+        // up once that's over. Pointers which aren't over a window get it when they enter one.
+        for (id, pointer) in self.seats.borrow().iter() {
+            if pointer.focus.get().is_none() || pointer.hidden.get() || self.is_locked(*id) {
+                continue;
+            }
+            self.apply_cursor(pointer, cursor.clone());
+        }
+    }
+
+    /// Show `cursor` for `pointer`, regardless of what is currently displayed.
+    fn apply_cursor(&self, pointer: &SeatPointer, cursor: mouse::Cursor) {
+        let scale = pointer.scale.get();
+        let buffer = match self.get_cursor_buffer(&cursor, scale) {
             None => return,
             Some(b) => b,
         };
 
+        // The hotspot is in surface coordinates, which are in display points.
         let (hot_x, hot_y) = buffer.hotspot();
         self.current_cursor.replace(cursor);
-        wl_pointer.set_cursor(0, Some(&self.cursor_surface), hot_x as i32, hot_y as i32);
-        self.cursor_surface.attach(Some(&*buffer), 0, 0);
+        let surface = &pointer.cursor_surface;
+        pointer.wl_pointer.set_cursor(
+            pointer.enter_serial.get(),
+            Some(surface),
+            hot_x as i32 / scale,
+            hot_y as i32 / scale,
+        );
+        if surface.as_ref().version() >= wl_surface::REQ_SET_BUFFER_SCALE_SINCE {
+            surface.set_buffer_scale(scale);
+        }
+        surface.attach(Some(&*buffer), 0, 0);
 
-        if self.cursor_surface.as_ref().version() >= wl_surface::REQ_DAMAGE_BUFFER_SINCE {
-            self.cursor_surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
+        if surface.as_ref().version() >= wl_surface::REQ_DAMAGE_BUFFER_SINCE {
+            surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
         } else {
-            self.cursor_surface.damage(0, 0, i32::MAX, i32::MAX);
+            surface.damage(0, 0, i32::MAX, i32::MAX);
         }
 
-        self.cursor_surface.commit();
+        surface.commit();
     }
 
-    fn get_cursor_buffer(&self, cursor: &mouse::Cursor) -> Option<CursorImageBuffer> {
+    fn get_cursor_buffer(&self, cursor: &mouse::Cursor, scale: i32) -> Option<CursorImageBuffer> {
         #[allow(deprecated)]
         match cursor {
-            mouse::Cursor::Arrow => self.unpack_image_buffer("left_ptr"),
-            mouse::Cursor::IBeam => self.unpack_image_buffer("xterm"),
-            mouse::Cursor::Crosshair => self.unpack_image_buffer("cross"),
-            mouse::Cursor::OpenHand => self.unpack_image_buffer("openhand"),
-            mouse::Cursor::NotAllowed => self.unpack_image_buffer("X_cursor"),
-            mouse::Cursor::ResizeLeftRight => self.unpack_image_buffer("row-resize"),
-            mouse::Cursor::ResizeUpDown => self.unpack_image_buffer("col-resize"),
-            mouse::Cursor::Pointer => self.unpack_image_buffer("pointer"),
+            mouse::Cursor::Arrow => self.unpack_image_buffer("left_ptr", scale),
+            mouse::Cursor::IBeam => self.unpack_image_buffer("xterm", scale),
+            mouse::Cursor::Crosshair => self.unpack_image_buffer("cross", scale),
+            mouse::Cursor::OpenHand => self.unpack_image_buffer("openhand", scale),
+            mouse::Cursor::NotAllowed => self.unpack_image_buffer("X_cursor", scale),
+            mouse::Cursor::ResizeLeftRight => self.unpack_image_buffer("col-resize", scale),
+            mouse::Cursor::ResizeUpDown => self.unpack_image_buffer("row-resize", scale),
+            mouse::Cursor::Pointer => self.unpack_image_buffer("pointer", scale),
             mouse::Cursor::Custom(_) => {
                 tracing::warn!("custom cursors not implemented");
-                self.unpack_image_buffer("left_ptr")
+                self.unpack_image_buffer("left_ptr", scale)
             }
         }
     }
 
     // Just use the first image, people using animated cursors have already made bad life
     // choices and shouldn't expect it to work.
-    fn unpack_image_buffer(&self, name: &str) -> Option<CursorImageBuffer> {
-        self.theme
+    fn unpack_image_buffer(&self, name: &str, scale: i32) -> Option<CursorImageBuffer> {
+        self.themes
             .borrow_mut()
+            .entry(scale)
+            .or_insert_with(|| load_theme(&self.shm, scale))
             .get_cursor(name)
             .map(|c| c[c.frame_and_duration(0).frame_index].clone())
     }
 
     pub(super) fn consume(
         appdata: std::sync::Arc<Data>,
-        source: wl_pointer::WlPointer,
+        id: u32,
+        seat: &WlSeat,
         event: wl_pointer::Event,
     ) {
+        let pointer = match appdata.pointer.seat(id) {
+            Some(pointer) => pointer,
+            // The seat's pointer was released while the event was in flight.
+            None => return,
+        };
         match event {
             wl_pointer::Event::Enter {
+                serial,
                 surface,
                 surface_x,
                 surface_y,
-                ..
             } => {
-                appdata.pointer.push(PointerEvent::Motion {
+                pointer.enter_serial.set(serial);
+                let window = match appdata.find_window(&surface) {
+                    Some(w) => w,
+                    None => {
+                        if let Some(frame) = appdata.find_frame(&surface) {
+                            // Keep the window's cursor for when the pointer gets back to it.
+                            let cursor = appdata.pointer.current_cursor.borrow().clone();
+                            appdata.pointer.apply_cursor(&pointer, mouse::Cursor::Arrow);
+                            appdata.pointer.current_cursor.replace(cursor);
+                            frame.pointer_motion(Point::new(surface_x, surface_y));
+                            pointer.frame.replace(Some(frame));
+                            return;
+                        }
+                        // e.g. a surface which was destroyed while the event was in flight.
+                        tracing::debug!("pointer entered an unknown surface {:?}", surface);
+                        return;
+                    }
+                };
+                pointer.focus.set(Some(window.id()));
+                pointer.hidden.set(!window.cursor_visible());
+                if let Some(data) = window.data() {
+                    pointer.scale.set(data.scale.get());
+                }
+                // The cursor is undefined until we set it for this enter.
+                appdata.pointer.show(&pointer);
+                pointer.push(PointerEvent::Motion {
                     point: Point::new(surface_x, surface_y),
-                    pointer: source,
                 });
             }
-            wl_pointer::Event::Leave { surface, .. } => {
-                appdata.pointer.push(PointerEvent::Leave);
+            wl_pointer::Event::Leave { .. } => {
+                if let Some(frame) = pointer.frame.take() {
+                    frame.pointer_leave();
+                    return;
+                }
+                pointer.push(PointerEvent::Leave);
+                pointer.focus.set(None);
             }
             wl_pointer::Event::Motion {
                 surface_x,
                 surface_y,
                 ..
             } => {
-                appdata.pointer.push(PointerEvent::Motion {
-                    point: Point::new(surface_x, surface_y),
-                    pointer: source,
-                });
+                let point = Point::new(surface_x, surface_y);
+                if let Some(frame) = &*pointer.frame.borrow() {
+                    frame.pointer_motion(point);
+                    return;
+                }
+                // Surface local coordinates are already in display points.
+                pointer.push(PointerEvent::Motion { point });
             }
-            wl_pointer::Event::Button { button, state, .. } => {
-                appdata.pointer.push(PointerEvent::Button { button, state });
+            wl_pointer::Event::Button {
+                serial,
+                button,
+                state,
+                ..
+            } => {
+                if state == wl_pointer::ButtonState::Pressed {
+                    appdata
+                        .pointer
+                        .button_press
+                        .replace(Some((id, seat.clone(), serial)));
+                }
+                let frame = pointer.frame.borrow().clone();
+                if let Some(frame) = frame {
+                    let pressed = state == wl_pointer::ButtonState::Pressed;
+                    frame.pointer_button(seat, serial, linux_to_mouse_button(button), pressed);
+                    return;
+                }
+                pointer.push(PointerEvent::Button { button, state });
             }
             wl_pointer::Event::Axis { axis, value, .. } => {
-                appdata.pointer.push(PointerEvent::Axis { axis, value });
+                // Like motion, scroll values are in surface local coordinates, so they are
+                // already in display points.
+                let delta = match axis {
+                    wl_pointer::Axis::VerticalScroll => Vec2::new(0., value),
+                    wl_pointer::Axis::HorizontalScroll => Vec2::new(value, 0.),
+                    _ => {
+                        log::error!("axis direction not vertical or horizontal");
+                        return;
+                    }
+                };
+                pointer.push(PointerEvent::Axis { delta });
             }
-            wl_pointer::Event::Frame => {
-                let winhandle = match appdata.acquire_current_window().and_then(|w| w.data()) {
-                    Some(w) => w,
-                    None => {
-                        tracing::warn!("dropping mouse events, no window available");
-                        appdata.pointer.queued_events.borrow_mut().clear();
+            wl_pointer::Event::AxisSource { axis_source } => {
+                pointer.axis_source.set(Some(axis_source));
+            }
+            wl_pointer::Event::AxisStop { .. } => {
+                // The fingers were lifted (wheels never stop like this), so widgets can start any
+                // kinetic scrolling from the event which says so.
+                pointer.push(PointerEvent::AxisStop);
+            }
+            wl_pointer::Event::AxisDiscrete { axis, discrete } => {
+                // These always come with an `Axis` event in the same frame, which is changed to
+                // scroll by the clicks when the frame ends.
+                let steps = match axis {
+                    wl_pointer::Axis::VerticalScroll => Vec2::new(0., discrete as f64),
+                    wl_pointer::Axis::HorizontalScroll => Vec2::new(discrete as f64, 0.),
+                    _ => {
+                        log::error!("axis direction not vertical or horizontal");
                         return;
                     }
                 };
-                let mut winhandle = winhandle.handler.borrow_mut();
+                pointer.discrete.set(pointer.discrete.get() + steps);
+            }
+            wl_pointer::Event::Frame => {
+                // The compositor says what is scrolling in every frame that scrolls.
+                let source = pointer.axis_source.take();
+                let wheel = source == Some(wl_pointer::AxisSource::Wheel);
+                let source = source.and_then(wheel_source);
+                let pending = pointer.pending.take();
+                let steps = pointer.discrete.take();
+                // The window may have moved to an output with a different scale.
+                let window = pointer.focus.get();
+                let data = window.and_then(|id| appdata.handles.borrow().get(&id)?.data());
+                if let Some(data) = data {
+                    appdata.pointer.set_scale(&pointer, data.scale.get());
+                }
+                for (window, mut event) in pending.into_events() {
+                    if let PointerEvent::Axis { delta } = &mut event {
+                        *delta = wheel_delta(*delta, steps, wheel);
+                    }
+                    let mods = appdata.keyboard.modifiers(id);
+                    let event = match pointer.mouse_event(
+                        event,
+                        mods,
+                        appdata.pointer.multi_click.get(),
+                        source,
+                    ) {
+                        Some(event) => event,
+                        None => continue,
+                    };
+                    let winhandle = match appdata.handles.borrow().get(&window).cloned() {
+                        Some(w) => w,
+                        None => {
+                            tracing::debug!("dropping mouse event for closed window {:?}", window);
+                            continue;
+                        }
+                    };
+                    let data = match winhandle.data() {
+                        Some(data) => data,
+                        None => continue,
+                    };
+                    // Locked pointers only report relative motion.
+                    if matches!(event, MouseEvtKind::Move(_)) && appdata.pointer.is_locked(id) {
+                        continue;
+                    }
+
+                    let left_press =
+                        matches!(&event, MouseEvtKind::Down(evt) if evt.button.is_left());
 
-                // (re-entrancy) call user code
-                while let Some(event) = appdata.pointer.dequeue() {
-                    match event {
+                    // (re-entrancy) call user code
+                    data.with_handler(|winhandle| match event {
                         MouseEvtKind::Move(evt) => winhandle.mouse_move(&evt),
                         MouseEvtKind::Up(evt) => winhandle.mouse_up(&evt),
                         MouseEvtKind::Down(evt) => winhandle.mouse_down(&evt),
-                        MouseEvtKind::Wheel(evt) => winhandle.mouse_wheel(&evt),
+                        MouseEvtKind::Wheel(evt) => winhandle.wheel(&evt),
                         MouseEvtKind::Leave => winhandle.mouse_leave(),
+                    });
+
+                    // A left press on a titlebar the handler draws itself moves the window.
+                    if left_press {
+                        if let Some((seat, serial)) = appdata.pointer.button_press() {
+                            winhandle.begin_move_drag(&seat, serial);
+                        }
                     }
                 }
             }
@@ -295,111 +687,322 @@ impl Pointer {
             }
         }
     }
+}
 
-    fn dequeue(&self) -> Option<MouseEvtKind> {
-        use wl_pointer::{Axis, ButtonState};
-        // sometimes we need to ignore an event and move on
-        loop {
-            let event = self.queued_events.borrow_mut().pop_front()?;
-            tracing::trace!("mouse event {:?}", event);
-            match event {
-                PointerEvent::Motion { pointer, point } => {
-                    self.pos.replace(point);
-                    return Some(MouseEvtKind::Move(mouse::MouseEvent {
-                        pos: point,
-                        buttons: *self.buttons.borrow(),
-                        mods: Modifiers::empty(),
-                        count: 0,
-                        focus: false,
-                        button: mouse::MouseButton::None,
-                        wheel_delta: Vec2::ZERO,
-                    }));
-                }
-                PointerEvent::Button { button, state } => {
-                    let button = match linux_to_mouse_button(button) {
-                        // Skip unsupported buttons.
-                        None => {
-                            tracing::debug!("unsupported button click {:?}", button);
-                            continue;
-                        }
-                        Some(b) => b,
-                    };
-                    let evt = match state {
-                        ButtonState::Pressed => {
-                            self.buttons.borrow_mut().insert(button);
-                            self.clickevent.borrow_mut().debounce(MouseEvtKind::Down(
-                                mouse::MouseEvent {
-                                    pos: self.pos.get(),
-                                    buttons: *self.buttons.borrow(),
-                                    mods: Modifiers::empty(),
-                                    count: 1,
-                                    focus: false,
-                                    button,
-                                    wheel_delta: Vec2::ZERO,
-                                },
-                            ))
-                        }
-                        ButtonState::Released => {
-                            self.buttons.borrow_mut().remove(button);
-                            self.clickevent.borrow_mut().debounce(MouseEvtKind::Up(
-                                mouse::MouseEvent {
-                                    pos: self.pos.get(),
-                                    buttons: *self.buttons.borrow(),
-                                    mods: Modifiers::empty(),
-                                    count: 0,
-                                    focus: false,
-                                    button,
-                                    wheel_delta: Vec2::ZERO,
-                                },
-                            ))
-                        }
-                        _ => {
-                            log::error!("mouse button changed, but not pressed or released");
-                            continue;
-                        }
-                    };
-                    return Some(evt);
-                }
-                PointerEvent::Axis { axis, value } => {
-                    let wheel_delta = match axis {
-                        Axis::VerticalScroll => Vec2::new(0., value),
-                        Axis::HorizontalScroll => Vec2::new(value, 0.),
-                        _ => {
-                            log::error!("axis direction not vertical or horizontal");
-                            continue;
-                        }
-                    };
-                    return Some(MouseEvtKind::Wheel(mouse::MouseEvent {
-                        pos: self.pos.get(),
-                        buttons: *self.buttons.borrow(),
-                        mods: Modifiers::empty(),
-                        count: 0,
-                        focus: false,
-                        button: mouse::MouseButton::None,
-                        wheel_delta,
-                    }));
-                }
-                PointerEvent::Leave => {
-                    // The parent will remove us.
-                    return Some(MouseEvtKind::Leave);
+impl SeatPointer {
+    /// Add an event for the window the pointer is currently over to the current frame.
+    #[inline]
+    fn push(&self, event: PointerEvent) {
+        let window = match self.focus.get() {
+            Some(window) => window,
+            None => {
+                tracing::debug!("dropping pointer event outside of any window {:?}", event);
+                return;
+            }
+        };
+        self.pending.borrow_mut().push(window, event);
+    }
+
+    fn mouse_event(
+        &self,
+        event: PointerEvent,
+        mods: Modifiers,
+        limits: MultiClick,
+        source: Option<WheelSource>,
+    ) -> Option<MouseEvtKind> {
+        use wl_pointer::ButtonState;
+        tracing::trace!("mouse event {:?}", event);
+        let evt = match event {
+            PointerEvent::Motion { point } => {
+                self.pos.replace(point);
+                MouseEvtKind::Move(mouse::MouseEvent {
+                    pos: point,
+                    buttons: *self.buttons.borrow(),
+                    mods,
+                    count: 0,
+                    focus: false,
+                    button: mouse::MouseButton::None,
+                    wheel_delta: Vec2::ZERO,
+                })
+            }
+            PointerEvent::Button { button, state } => {
+                let button = linux_to_mouse_button(button);
+                match state {
+                    ButtonState::Pressed => {
+                        self.buttons.borrow_mut().insert(button);
+                        self.clickevent.borrow_mut().debounce(
+                            MouseEvtKind::Down(mouse::MouseEvent {
+                                pos: self.pos.get(),
+                                buttons: *self.buttons.borrow(),
+                                mods,
+                                count: 1,
+                                focus: false,
+                                button,
+                                wheel_delta: Vec2::ZERO,
+                            }),
+                            limits,
+                        )
+                    }
+                    ButtonState::Released => {
+                        self.buttons.borrow_mut().remove(button);
+                        self.clickevent.borrow_mut().debounce(
+                            MouseEvtKind::Up(mouse::MouseEvent {
+                                pos: self.pos.get(),
+                                buttons: *self.buttons.borrow(),
+                                mods,
+                                count: 0,
+                                focus: false,
+                                button,
+                                wheel_delta: Vec2::ZERO,
+                            }),
+                            limits,
+                        )
+                    }
+                    _ => {
+                        log::error!("mouse button changed, but not pressed or released");
+                        return None;
+                    }
                 }
             }
+            PointerEvent::Axis { delta } => self.wheel_event(mods, delta, source, false),
+            PointerEvent::AxisStop => self.wheel_event(mods, Vec2::ZERO, source, true),
+            PointerEvent::Leave => {
+                // We don't hear about buttons released outside of our windows, so forget
+                // them, or they would stay held.
+                self.buttons.replace(mouse::MouseButtons::new());
+                MouseEvtKind::Leave
+            }
+        };
+        Some(evt)
+    }
+
+    /// A wheel event, which says what is scrolling and whether the scroll just ended.
+    fn wheel_event(
+        &self,
+        mods: Modifiers,
+        delta: Vec2,
+        source: Option<WheelSource>,
+        end: bool,
+    ) -> MouseEvtKind {
+        let mut event = crate::PointerEvent::from(mouse::MouseEvent {
+            pos: self.pos.get(),
+            buttons: *self.buttons.borrow(),
+            mods,
+            count: 0,
+            focus: false,
+            button: mouse::MouseButton::None,
+            wheel_delta: delta,
+        });
+        event.pointer_type = PointerType::Mouse(MouseInfo {
+            wheel_delta: delta,
+            wheel_source: source,
+            wheel_end: end,
+        });
+        MouseEvtKind::Wheel(event)
+    }
+}
+
+/// The pointer events of one frame, which are delivered in a fixed order when the frame ends.
+///
+/// Motion collapses into the final position, and scrolling into a single delta. If the pointer
+/// moves between windows during the frame, each window gets its own events before the next one.
+#[derive(Debug, Default)]
+struct Pending {
+    /// The events of the windows the pointer already left during the frame.
+    earlier: Vec<(u64, PointerEvent)>,
+    /// The window the pointer is over, or has just left.
+    window: Option<u64>,
+    /// Where the pointer entered or last moved to, as entering is reported as a move.
+    motion: Option<Point>,
+    /// Buttons pressed and released, in the order they were.
+    buttons: Vec<(u32, wl_pointer::ButtonState)>,
+    /// The total scroll distance.
+    axis: Option<Vec2>,
+    /// Whether a scroll sequence ended.
+    axis_stop: bool,
+    /// Whether the pointer left `window`.
+    left: bool,
+}
+
+impl Pending {
+    fn push(&mut self, window: u64, event: PointerEvent) {
+        if self.window != Some(window) {
+            // Finish with the window the pointer was over, so none of its events are lost.
+            self.flush();
+            self.window = Some(window);
+        }
+        match event {
+            PointerEvent::Leave => {
+                self.left = true;
+                return;
+            }
+            // The pointer left and came straight back.
+            _ => self.left = false,
+        }
+        match event {
+            PointerEvent::Motion { point } => self.motion = Some(point),
+            PointerEvent::Button { button, state } => self.buttons.push((button, state)),
+            PointerEvent::Axis { delta } => *self.axis.get_or_insert(Vec2::ZERO) += delta,
+            PointerEvent::AxisStop => self.axis_stop = true,
+            PointerEvent::Leave => unreachable!(),
+        }
+    }
+
+    /// Move the events of the current window after those of the earlier ones, as entering or
+    /// moving, then buttons, scrolling and leaving.
+    fn flush(&mut self) {
+        let window = match self.window.take() {
+            Some(window) => window,
+            None => return,
+        };
+        let mut events = std::mem::take(&mut self.earlier);
+        if let Some(point) = self.motion.take() {
+            events.push((window, PointerEvent::Motion { point }));
+        }
+        for (button, state) in self.buttons.drain(..) {
+            events.push((window, PointerEvent::Button { button, state }));
+        }
+        if let Some(delta) = self.axis.take() {
+            events.push((window, PointerEvent::Axis { delta }));
+        }
+        if std::mem::take(&mut self.axis_stop) {
+            events.push((window, PointerEvent::AxisStop));
+        }
+        if std::mem::take(&mut self.left) {
+            events.push((window, PointerEvent::Leave));
         }
+        self.earlier = events;
+    }
+
+    /// The events of the frame, window by window.
+    fn into_events(mut self) -> Vec<(u64, PointerEvent)> {
+        self.flush();
+        self.earlier
     }
 }
 
-impl Drop for Pointer {
-    fn drop(&mut self) {
-        self.cursor_surface.destroy();
+/// Make scroll wheels scroll by `WHEEL_STEP` for each click, as they do on the other backends.
+///
+/// The clicks the compositor counted in `steps` are used where there are any, otherwise if the
+/// scrolling comes from a `wheel` the clicks are worked out from the smooth distance. Anything
+/// else, like a touchpad, keeps scrolling by the smooth distance.
+fn wheel_delta(delta: Vec2, steps: Vec2, wheel: bool) -> Vec2 {
+    let convert = |delta: f64, steps: f64| {
+        if steps != 0. {
+            steps * WHEEL_STEP
+        } else if wheel {
+            delta * WHEEL_STEP / AXIS_STEP
+        } else {
+            delta
+        }
+    };
+    Vec2::new(convert(delta.x, steps.x), convert(delta.y, steps.y))
+}
+
+fn wheel_source(source: wl_pointer::AxisSource) -> Option<WheelSource> {
+    match source {
+        wl_pointer::AxisSource::Wheel => Some(WheelSource::Wheel),
+        wl_pointer::AxisSource::WheelTilt => Some(WheelSource::WheelTilt),
+        wl_pointer::AxisSource::Finger => Some(WheelSource::Finger),
+        wl_pointer::AxisSource::Continuous => Some(WheelSource::Continuous),
+        _ => None,
     }
 }
 
 #[inline]
-fn linux_to_mouse_button(button: u32) -> Option<mouse::MouseButton> {
+fn linux_to_mouse_button(button: u32) -> mouse::MouseButton {
     match button {
-        BTN_LEFT => Some(mouse::MouseButton::Left),
-        BTN_RIGHT => Some(mouse::MouseButton::Right),
-        BTN_MIDDLE => Some(mouse::MouseButton::Middle),
-        _ => None,
+        BTN_LEFT => mouse::MouseButton::Left,
+        BTN_RIGHT => mouse::MouseButton::Right,
+        BTN_MIDDLE => mouse::MouseButton::Middle,
+        BTN_SIDE => mouse::MouseButton::X1,
+        BTN_EXTRA => mouse::MouseButton::X2,
+        _ => {
+            tracing::debug!("unsupported button click {:?}", button);
+            mouse::MouseButton::None
+        }
+    }
+}
+
+/// Load the cursor theme for `scale`.
+///
+/// The theme and size come from `XCURSOR_THEME` and `XCURSOR_SIZE`, falling back to the usual
+/// xcursor defaults.
+fn load_theme(shm: &wl::Main<WlShm>, scale: i32) -> CursorTheme {
+    tracing::debug!("loading the cursor theme at scale {}", scale);
+    let name = std::env::var("XCURSOR_THEME").unwrap_or_else(|_| "default".into());
+    let size = std::env::var("XCURSOR_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(24);
+    CursorTheme::load_from_name(&name, size * scale as u32, shm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_coalescing() {
+        let motion = |x, y| PointerEvent::Motion {
+            point: Point::new(x, y),
+        };
+        let button = |state| PointerEvent::Button {
+            button: BTN_LEFT,
+            state,
+        };
+        let axis = |x, y| PointerEvent::Axis {
+            delta: Vec2::new(x, y),
+        };
+
+        // Motion, a click and some scrolling, all in one frame.
+        let mut pending = Pending::default();
+        pending.push(1, motion(1., 1.));
+        pending.push(1, axis(0., 5.));
+        pending.push(1, button(wl_pointer::ButtonState::Pressed));
+        pending.push(1, motion(2., 2.));
+        pending.push(1, axis(2., 5.));
+        pending.push(1, button(wl_pointer::ButtonState::Released));
+        let expected = vec![
+            (1, motion(2., 2.)),
+            (1, button(wl_pointer::ButtonState::Pressed)),
+            (1, button(wl_pointer::ButtonState::Released)),
+            (1, axis(2., 10.)),
+        ];
+        assert_eq!(pending.into_events(), expected);
+
+        // Moving into another window finishes with the previous one first, without losing any of
+        // its events.
+        let mut pending = Pending::default();
+        pending.push(1, button(wl_pointer::ButtonState::Released));
+        pending.push(1, PointerEvent::Leave);
+        pending.push(2, motion(3., 3.));
+        let expected = vec![
+            (1, button(wl_pointer::ButtonState::Released)),
+            (1, PointerEvent::Leave),
+            (2, motion(3., 3.)),
+        ];
+        assert_eq!(pending.into_events(), expected);
+
+        // Coming straight back isn't leaving.
+        let mut pending = Pending::default();
+        pending.push(1, PointerEvent::Leave);
+        pending.push(1, motion(3., 3.));
+        assert_eq!(pending.into_events(), vec![(1, motion(3., 3.))]);
+    }
+
+    #[test]
+    fn wheel_steps() {
+        // Counted clicks win over the smooth distance.
+        let delta = wheel_delta(Vec2::new(3., 30.), Vec2::new(0., 2.), true);
+        assert_eq!(delta, Vec2::new(3. * 12., 240.));
+
+        // Without counted clicks, a wheel's distance is turned into clicks.
+        let delta = wheel_delta(Vec2::new(0., -10.), Vec2::ZERO, true);
+        assert_eq!(delta, Vec2::new(0., -120.));
+
+        // Other sources keep their smooth distance.
+        let delta = wheel_delta(Vec2::new(1.5, -4.), Vec2::ZERO, false);
+        assert_eq!(delta, Vec2::new(1.5, -4.));
     }
 }
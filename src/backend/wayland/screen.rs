@@ -13,29 +13,28 @@
 // limitations under the License.
 
 //! wayland Monitors and Screen information.
+use crate::kurbo::Point;
 use crate::screen::Monitor;
 
-use super::error;
 use super::outputs;
 
-fn _get_monitors() -> Result<Vec<Monitor>, error::Error> {
-    let metas = outputs::current()?;
-    let monitors: Vec<Monitor> = metas
-        .iter()
-        .map(|m| {
-            let rect = kurbo::Rect::from_origin_size(
-                (m.position.x as f64, m.position.y as f64),
-                (m.logical.width as f64, m.logical.height as f64),
-            );
-            Monitor::new(false, rect, rect)
-        })
-        .collect();
-    Ok(monitors)
+/// Wayland has no primary output, so we treat the one at the origin as primary.
+fn monitor(meta: &outputs::Meta) -> Monitor {
+    let rect = meta.logical_rect();
+    Monitor::new(rect.origin() == Point::ZERO, rect, rect)
 }
 
 pub(crate) fn get_monitors() -> Vec<Monitor> {
-    match _get_monitors() {
-        Ok(m) => m,
+    // A running application keeps track of outputs as they come and go.
+    if let Some(app) = crate::Application::try_global() {
+        let outputs = app.backend_app.data.outputs.borrow();
+        if !outputs.is_empty() {
+            return outputs.values().map(monitor).collect();
+        }
+    }
+
+    match outputs::current() {
+        Ok(metas) => metas.iter().map(monitor).collect(),
         Err(cause) => {
             tracing::error!(
                 "unable to detect monitors, failed to connect to wayland server {:?}",
@@ -0,0 +1,172 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Seats, the groups of input devices (keyboard, pointer, ...) used by one person.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wayland_client as wl;
+use wayland_client::protocol::wl_data_device::WlDataDevice;
+use wayland_client::protocol::wl_keyboard::WlKeyboard;
+use wayland_client::protocol::wl_pointer::WlPointer;
+use wayland_client::protocol::wl_seat::{self, WlSeat};
+use wayland_client::protocol::wl_touch::WlTouch;
+use wayland_protocols::unstable::primary_selection::v1::client::zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1;
+use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_seat_v2::ZwpTabletSeatV2;
+
+use super::application::Data;
+use super::gestures::Gestures;
+use super::pointers;
+use super::tablet;
+use super::text_input::TextInput;
+use super::touch;
+
+#[derive(Debug, Clone)]
+pub struct Seat {
+    pub(super) wl_seat: wl::Main<WlSeat>,
+    name: String,
+    capabilities: wl_seat::Capability,
+    keyboard: Option<wl::Main<WlKeyboard>>,
+    pointer: Option<wl::Main<WlPointer>>,
+    gestures: Option<Gestures>,
+    touch: Option<wl::Main<WlTouch>>,
+    pub(super) data_device: Option<wl::Main<WlDataDevice>>,
+    pub(super) primary_selection_device: Option<wl::Main<ZwpPrimarySelectionDeviceV1>>,
+    pub(super) text_input: Option<TextInput>,
+    pub(super) tablet_seat: Option<wl::Main<ZwpTabletSeatV2>>,
+}
+
+impl Seat {
+    pub(super) fn new(wl_seat: wl::Main<WlSeat>) -> Self {
+        Self {
+            wl_seat,
+            name: "".into(),
+            capabilities: wl_seat::Capability::empty(),
+            keyboard: None,
+            pointer: None,
+            gestures: None,
+            touch: None,
+            data_device: None,
+            primary_selection_device: None,
+            text_input: None,
+            tablet_seat: None,
+        }
+    }
+
+    /// Start handling the events of the seat with the given registry `id`.
+    pub(super) fn listen(appdata: &std::sync::Arc<Data>, id: u32, seat: &Rc<RefCell<Seat>>) {
+        let wl_seat = seat.borrow().wl_seat.clone();
+        wl_seat.quick_assign(with_cloned!(seat, appdata; move |d1, event, d3| {
+            tracing::debug!("seat events {:?} {:?} {:?}", d1, event, d3);
+            let mut seat = seat.borrow_mut();
+            appdata.clipboard.attach(&appdata, &mut seat);
+            if let Some(manager) = &appdata.zwp_text_input_manager_v3 {
+                TextInput::attach(&appdata, manager, &mut seat);
+            }
+            if let Some(manager) = &appdata.zwp_tablet_manager_v2 {
+                tablet::attach(&appdata, manager, id, &mut seat);
+            }
+            match event {
+                wl_seat::Event::Capabilities { capabilities } => {
+                    seat.capabilities = capabilities;
+                    if !capabilities.contains(wl_seat::Capability::Keyboard) {
+                        seat.release_keyboard(&appdata, id);
+                    } else if seat.keyboard.is_none() {
+                        seat.keyboard = Some(appdata.keyboard.attach(&appdata, id, seat.wl_seat.clone()));
+                    }
+
+                    if !capabilities.contains(wl_seat::Capability::Pointer) {
+                        seat.release_pointer(&appdata, id);
+                    } else if seat.pointer.is_none() {
+                        let pointer = seat.wl_seat.get_pointer();
+                        let cursor = appdata.wl_compositor.create_surface();
+                        appdata.pointer.attach(id, pointer.detach(), cursor);
+                        pointer.quick_assign({
+                            let app = appdata.clone();
+                            let wl_seat = seat.wl_seat.detach();
+                            move |_, event, _| {
+                                pointers::Pointer::consume(app.clone(), id, &wl_seat, event);
+                            }
+                        });
+                        if let Some(manager) = &appdata.zwp_pointer_gestures_v1 {
+                            seat.gestures = Some(Gestures::attach(&appdata, id, manager, &pointer));
+                        }
+                        seat.pointer = Some(pointer);
+                    }
+
+                    if !capabilities.contains(wl_seat::Capability::Touch) {
+                        seat.release_touch();
+                    } else if seat.touch.is_none() {
+                        let touch = seat.wl_seat.get_touch();
+                        touch::attach(&appdata, id, &touch);
+                        seat.touch = Some(touch);
+                    }
+                }
+                wl_seat::Event::Name { name } => {
+                    seat.name = name;
+                }
+                _ => tracing::info!("seat quick assign unknown event {:?}", event), // ignore future events
+            }
+        }));
+    }
+
+    /// Let go of everything we created for the seat, once the compositor has removed it.
+    pub(super) fn release(&mut self, appdata: &Data, id: u32) {
+        tracing::debug!("releasing seat {:?} {:?}", id, self.name);
+        self.release_keyboard(appdata, id);
+        self.release_pointer(appdata, id);
+        self.release_touch();
+        appdata.clipboard.detach(self);
+        if let Some(text_input) = self.text_input.take() {
+            text_input.destroy();
+        }
+        if let Some(tablet_seat) = self.tablet_seat.take() {
+            tablet_seat.destroy();
+        }
+        if self.wl_seat.as_ref().version() >= 5 {
+            self.wl_seat.release();
+        }
+    }
+
+    // Before version 3 there's no way to release the devices, and they just stop sending events.
+
+    fn release_keyboard(&mut self, appdata: &Data, id: u32) {
+        if let Some(keyboard) = self.keyboard.take() {
+            appdata.keyboard.detach(id);
+            if keyboard.as_ref().version() >= 3 {
+                keyboard.release();
+            }
+        }
+    }
+
+    fn release_pointer(&mut self, appdata: &Data, id: u32) {
+        if let Some(gestures) = self.gestures.take() {
+            gestures.destroy();
+        }
+        if let Some(pointer) = self.pointer.take() {
+            appdata.pointer.detach(appdata, id);
+            if pointer.as_ref().version() >= 3 {
+                pointer.release();
+            }
+        }
+    }
+
+    fn release_touch(&mut self) {
+        if let Some(touch) = self.touch.take() {
+            if touch.as_ref().version() >= 3 {
+                touch.release();
+            }
+        }
+    }
+}
@@ -108,6 +108,12 @@ impl<const N: usize> Buffers<N> {
             return;
         }
 
+        // the damage is kept, and we paint once the first configure has been acked.
+        if !window.configured.get() {
+            tracing::trace!("request_paint before the surface was configured");
+            return;
+        }
+
         if self.pending_buffer_borrowed.get() {
             panic!("called request_paint during painting");
         }
@@ -601,6 +607,101 @@ impl fmt::Debug for RawSize {
     }
 }
 
+/// Fill `rect` of a frame of `size` with an ARGB `color`, for the few things we draw ourselves.
+pub fn fill(buf: &mut [u8], size: RawSize, rect: RawRect, color: u32) {
+    let (x0, x1) = (rect.x0.clamp(0, size.width), rect.x1.clamp(0, size.width));
+    let (y0, y1) = (rect.y0.clamp(0, size.height), rect.y1.clamp(0, size.height));
+    let color = color.to_le_bytes();
+    for y in y0..y1 {
+        let start = ((y * size.width + x0) * PIXEL_WIDTH) as usize;
+        let end = ((y * size.width + x1) * PIXEL_WIDTH) as usize;
+        for pixel in buf[start..end].chunks_exact_mut(PIXEL_WIDTH as usize) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+}
+
+/// Where text goes across the rect it's drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+/// A line of text, for the few things we draw ourselves.
+///
+/// It's drawn with cairo's simple text API, which is enough for labels in the system's sans-serif
+/// font, though it doesn't shape complex scripts.
+#[derive(Debug)]
+pub struct Text<'a> {
+    pub text: &'a str,
+    /// The font size in physical pixels.
+    pub font_size: f64,
+    pub bold: bool,
+    /// The color as ARGB.
+    pub color: u32,
+    pub align: Align,
+}
+
+impl Text<'_> {
+    /// Draw the text into `rect` of a frame of `size`, centered vertically, and cut off at the
+    /// edges of `rect`.
+    pub fn draw(&self, buf: &mut [u8], size: RawSize, rect: RawRect) {
+        assert!(buf.len() >= size.frame_size() as usize);
+        // Text can't contain a nul, as cairo would stop there.
+        let text = match std::ffi::CString::new(self.text) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+        let family = std::ffi::CString::new("sans-serif").unwrap();
+        let [blue, green, red, alpha] = self.color.to_le_bytes().map(|c| c as f64 / 255.);
+        let (x0, y0) = (rect.x0 as f64, rect.y0 as f64);
+        let (x1, y1) = (rect.x1 as f64, rect.y1 as f64);
+        let weight = match self.bold {
+            true => cairo_sys::FONT_WEIGHT_BOLD,
+            false => cairo_sys::FONT_WEIGHT_NORMAL,
+        };
+        // Safety: the frame is big enough for the surface, which only lives until we return.
+        unsafe {
+            let surface = cairo_sys::cairo_image_surface_create_for_data(
+                buf.as_mut_ptr(),
+                cairo_sys::FORMAT_A_RGB32,
+                size.width,
+                size.height,
+                size.width * PIXEL_WIDTH,
+            );
+            let cr = cairo_sys::cairo_create(surface);
+            cairo_sys::cairo_rectangle(cr, x0, y0, x1 - x0, y1 - y0);
+            cairo_sys::cairo_clip(cr);
+            cairo_sys::cairo_select_font_face(
+                cr,
+                family.as_ptr(),
+                cairo_sys::FONT_SLANT_NORMAL,
+                weight,
+            );
+            cairo_sys::cairo_set_font_size(cr, self.font_size);
+            let mut font = std::mem::zeroed::<cairo_sys::FontExtents>();
+            cairo_sys::cairo_font_extents(cr, &mut font);
+            let mut extents = std::mem::zeroed::<cairo_sys::TextExtents>();
+            cairo_sys::cairo_text_extents(cr, text.as_ptr(), &mut extents);
+            let x = match self.align {
+                Align::Start => x0,
+                Align::Center => (x0 + x1 - extents.x_advance) / 2.,
+                Align::End => x1 - extents.x_advance,
+            };
+            // Center the font rather than the text, so all labels share a baseline.
+            let baseline = (y0 + y1 + font.ascent - font.descent) / 2.;
+            cairo_sys::cairo_move_to(cr, x.round(), baseline.round());
+            cairo_sys::cairo_set_source_rgba(cr, red, green, blue, alpha);
+            cairo_sys::cairo_show_text(cr, text.as_ptr());
+            cairo_sys::cairo_destroy(cr);
+            cairo_sys::cairo_surface_flush(surface);
+            cairo_sys::cairo_surface_destroy(surface);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RawRect {
     pub x0: i32,
@@ -645,3 +746,42 @@ impl From<RawRect> for Rect {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_stays_in_its_rect() {
+        let size = RawSize {
+            width: 64,
+            height: 32,
+        };
+        let mut buf = vec![0; size.frame_size() as usize];
+        let rect = RawRect {
+            x0: 4,
+            y0: 8,
+            x1: 24,
+            y1: 24,
+        };
+        let text = Text {
+            text: "Wide text",
+            font_size: 14.,
+            bold: false,
+            color: 0xff00_0000,
+            align: Align::Start,
+        };
+        text.draw(&mut buf, size, rect);
+
+        let drawn = |x: i32, y: i32| {
+            let idx = ((y * size.width + x) * PIXEL_WIDTH) as usize;
+            buf[idx + 3] != 0
+        };
+        let inside = |x: i32, y: i32| (4..24).contains(&x) && (8..24).contains(&y);
+        let points = (0..size.height).flat_map(|y| (0..size.width).map(move |x| (x, y)));
+        assert!(points.clone().any(|(x, y)| drawn(x, y)));
+        assert!(points
+            .filter(|(x, y)| drawn(*x, *y))
+            .all(|(x, y)| inside(x, y)));
+    }
+}
@@ -0,0 +1,419 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Window decorations we draw ourselves, for compositors which leave them to clients.
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wayland_client as wlc;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::protocol::wl_subsurface::WlSubsurface;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_protocols::xdg_shell::client::xdg_toplevel::{self, ResizeEdge};
+
+use crate::kurbo::{Insets, Point, Size};
+use crate::mouse::MouseButton;
+use crate::region::Region;
+use crate::window;
+
+use super::buffers::{fill, Align, RawRect, RawSize, Text};
+use super::surface;
+use super::{Compositor, CompositorHandle, Handle};
+
+/// The height of the titlebar, in display points.
+const TITLEBAR_HEIGHT: f64 = 32.;
+/// The width of the (invisible) resize borders around the window, in display points.
+const BORDER: f64 = 8.;
+const TITLE_FONT_SIZE: f64 = 14.;
+
+// Colors, as ARGB.
+const TITLEBAR: u32 = 0xffeb_ebeb;
+const HOVERED: u32 = 0xffd6_d6d6;
+const CLOSE_HOVERED: u32 = 0xffe0_1b24;
+const GLYPH: u32 = 0xff3d_3846;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Button {
+    Minimize,
+    Maximize,
+    Close,
+}
+
+/// What is under the pointer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Area {
+    Titlebar,
+    Button(Button),
+    Edge(ResizeEdge),
+}
+
+/// The layout of the decorations, in display points from the top left of the frame surface.
+#[derive(Debug, Default)]
+struct State {
+    /// The size of the window content, without the decorations.
+    content: Size,
+    title: String,
+    resizable: bool,
+    pos: Point,
+    hovered: Option<Button>,
+    /// The button which went down, which only acts if the pointer is released over it.
+    pressed: Option<Button>,
+}
+
+impl State {
+    fn size(&self) -> Size {
+        Size::new(
+            self.content.width + 2. * BORDER,
+            self.content.height + TITLEBAR_HEIGHT + 2. * BORDER,
+        )
+    }
+
+    /// The horizontal extent of a button, which are lined up at the right of the titlebar.
+    fn button_x(&self, button: Button) -> (f64, f64) {
+        let right = BORDER + self.content.width;
+        let idx = match button {
+            Button::Close => 1.,
+            Button::Maximize => 2.,
+            Button::Minimize => 3.,
+        };
+        (
+            right - idx * TITLEBAR_HEIGHT,
+            right - (idx - 1.) * TITLEBAR_HEIGHT,
+        )
+    }
+
+    fn area(&self, pos: Point) -> Option<Area> {
+        let right = BORDER + self.content.width;
+        let bottom = BORDER + TITLEBAR_HEIGHT + self.content.height;
+        let (left, right, top, bottom) = (
+            pos.x < BORDER,
+            pos.x >= right,
+            pos.y < BORDER,
+            pos.y >= bottom,
+        );
+        let edge = match (left, right, top, bottom) {
+            (true, _, true, _) => Some(ResizeEdge::TopLeft),
+            (_, true, true, _) => Some(ResizeEdge::TopRight),
+            (true, _, _, true) => Some(ResizeEdge::BottomLeft),
+            (_, true, _, true) => Some(ResizeEdge::BottomRight),
+            (true, ..) => Some(ResizeEdge::Left),
+            (_, true, ..) => Some(ResizeEdge::Right),
+            (_, _, true, _) => Some(ResizeEdge::Top),
+            (.., true) => Some(ResizeEdge::Bottom),
+            _ => None,
+        };
+        if let Some(edge) = edge {
+            return match self.resizable {
+                true => Some(Area::Edge(edge)),
+                false => None,
+            };
+        }
+        if pos.y >= BORDER + TITLEBAR_HEIGHT {
+            // The content covers the rest of the frame.
+            return None;
+        }
+        let button = [Button::Minimize, Button::Maximize, Button::Close]
+            .into_iter()
+            .find(|button| {
+                let (x0, x1) = self.button_x(*button);
+                x0 <= pos.x && pos.x < x1
+            });
+        Some(button.map_or(Area::Titlebar, Area::Button))
+    }
+
+    fn draw(&self, buf: &mut [u8], size: RawSize, scale: i32) {
+        let scale = scale as f64;
+        let rect = |x0: f64, y0: f64, x1: f64, y1: f64| RawRect {
+            x0: (x0 * scale) as i32,
+            y0: (y0 * scale) as i32,
+            x1: (x1 * scale) as i32,
+            y1: (y1 * scale) as i32,
+        };
+        // The borders are only there to be grabbed, so they stay transparent.
+        fill(buf, size, size.to_rect(), 0);
+        let (y0, y1) = (BORDER, BORDER + TITLEBAR_HEIGHT);
+        fill(
+            buf,
+            size,
+            rect(BORDER, y0, BORDER + self.content.width, y1),
+            TITLEBAR,
+        );
+
+        for button in [Button::Minimize, Button::Maximize, Button::Close] {
+            let (x0, x1) = self.button_x(button);
+            if self.hovered == Some(button) {
+                let color = match button {
+                    Button::Close => CLOSE_HOVERED,
+                    _ => HOVERED,
+                };
+                fill(buf, size, rect(x0, y0, x1, y1), color);
+            }
+            let (cx, cy) = ((x0 + x1) / 2., (y0 + y1) / 2.);
+            match button {
+                Button::Minimize => {
+                    fill(buf, size, rect(cx - 5., cy + 4., cx + 5., cy + 5.), GLYPH);
+                }
+                Button::Maximize => {
+                    fill(buf, size, rect(cx - 5., cy - 5., cx + 5., cy - 4.), GLYPH);
+                    fill(buf, size, rect(cx - 5., cy + 4., cx + 5., cy + 5.), GLYPH);
+                    fill(buf, size, rect(cx - 5., cy - 5., cx - 4., cy + 5.), GLYPH);
+                    fill(buf, size, rect(cx + 4., cy - 5., cx + 5., cy + 5.), GLYPH);
+                }
+                Button::Close => {
+                    // Two diagonals, made of small squares.
+                    for step in 0..10 {
+                        let d = step as f64 - 4.5;
+                        for (x, y) in [(cx + d, cy + d), (cx + d, cy - d)] {
+                            fill(buf, size, rect(x - 0.5, y - 0.5, x + 1., y + 1.), GLYPH);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Keep clear of the buttons on both sides, so the title stays centered.
+        let (x1, _) = self.button_x(Button::Minimize);
+        let x0 = 2. * BORDER + self.content.width - x1;
+        let title = Text {
+            text: &self.title,
+            font_size: TITLE_FONT_SIZE * scale,
+            bold: true,
+            color: GLYPH,
+            align: Align::Center,
+        };
+        title.draw(buf, size, rect(x0, y0, x1, y1));
+    }
+}
+
+/// The frame is drawn with a draw hook, so its handler has nothing to do.
+struct Chrome;
+
+impl window::WinHandler for Chrome {
+    fn connect(&mut self, _: &crate::WindowHandle) {}
+
+    fn prepare_paint(&mut self) {}
+
+    fn paint(&mut self, _: &Region) {}
+
+    /// The decorations belong to the window, so they aren't published on their own.
+    #[cfg(feature = "accesskit")]
+    fn accesskit_tree(&mut self) -> accesskit::TreeUpdate {
+        let root = accesskit::NodeId(std::num::NonZeroU128::new(1).unwrap());
+        let node = accesskit::NodeBuilder::new(accesskit::Role::TitleBar)
+            .build(&mut accesskit::NodeClassSet::lock_global());
+        accesskit::TreeUpdate {
+            nodes: vec![(root, node)],
+            tree: Some(accesskit::Tree::new(root)),
+            focus: None,
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+struct Inner {
+    surface: surface::Surface,
+    subsurface: wlc::Main<WlSubsurface>,
+    /// The window the decorations belong to.
+    window: surface::Surface,
+    xdg_toplevel: wlc::Main<xdg_toplevel::XdgToplevel>,
+    window_state: Rc<Cell<window::WindowState>>,
+    state: Rc<RefCell<State>>,
+}
+
+/// A titlebar and resize borders, drawn in a subsurface around the window content.
+#[derive(Clone)]
+pub struct Frame {
+    inner: Rc<Inner>,
+}
+
+impl Frame {
+    pub(super) fn new(
+        compositor: &CompositorHandle,
+        window: &surface::Surface,
+        xdg_toplevel: &wlc::Main<xdg_toplevel::XdgToplevel>,
+        window_state: &Rc<Cell<window::WindowState>>,
+        resizable: bool,
+        title: &str,
+    ) -> Option<Self> {
+        let subcompositor = match compositor.wl_subcompositor() {
+            Some(subcompositor) => subcompositor,
+            None => {
+                // This was already logged at startup.
+                tracing::debug!("unable to draw window decorations without subsurfaces");
+                return None;
+            }
+        };
+        let surface = surface::Surface::new(compositor.clone(), Box::new(Chrome), Size::ZERO);
+        let subsurface = subcompositor.get_subsurface(
+            &surface.inner.wl_surface.borrow(),
+            &window.inner.wl_surface.borrow(),
+        );
+        // The frame surrounds the content, with the titlebar above it.
+        subsurface.set_position(-BORDER as i32, -(BORDER + TITLEBAR_HEIGHT) as i32);
+        subsurface.place_below(&window.inner.wl_surface.borrow());
+        // Hovering redraws the frame on its own, without waiting for the window to commit.
+        subsurface.set_desync();
+        // Subsurfaces don't get a configure of their own.
+        surface.set_configured();
+
+        let state = Rc::new(RefCell::new(State {
+            resizable,
+            title: title.to_string(),
+            ..State::default()
+        }));
+        surface.inner.draw.replace(Some(Box::new({
+            let state = state.clone();
+            move |buf, size, scale| state.borrow().draw(buf, size, scale)
+        })));
+
+        Some(Self {
+            inner: Rc::new(Inner {
+                surface,
+                subsurface,
+                window: window.clone(),
+                xdg_toplevel: xdg_toplevel.clone(),
+                window_state: window_state.clone(),
+                state,
+            }),
+        })
+    }
+
+    /// Whether to offer resizing from the borders.
+    pub(super) fn set_resizable(&self, resizable: bool) {
+        self.inner.state.borrow_mut().resizable = resizable;
+    }
+
+    pub(super) fn set_title(&self, title: &str) {
+        self.inner.state.borrow_mut().title = title.to_string();
+        self.redraw();
+    }
+
+    /// How much of the window geometry the decorations take up, around the window's size.
+    pub(super) fn insets(&self) -> Insets {
+        Insets::new(0., TITLEBAR_HEIGHT, 0., 0.)
+    }
+
+    /// Fit the frame around content of the given size.
+    pub(super) fn resize(&self, content: Size) {
+        let size = {
+            let mut state = self.inner.state.borrow_mut();
+            state.content = content;
+            state.size()
+        };
+        self.inner.surface.resize(size);
+        self.redraw();
+    }
+
+    pub(super) fn destroy(&self) {
+        self.inner.subsurface.destroy();
+        self.inner.surface.release();
+    }
+
+    /// Is `surface` the wayland surface backing this frame?
+    pub(crate) fn owns_surface(&self, surface: &WlSurface) -> bool {
+        self.inner.surface.inner.owns_surface(surface)
+    }
+
+    pub(crate) fn pointer_motion(&self, pos: Point) {
+        let changed = {
+            let mut state = self.inner.state.borrow_mut();
+            state.pos = pos;
+            let hovered = match state.area(pos) {
+                Some(Area::Button(button)) => Some(button),
+                _ => None,
+            };
+            std::mem::replace(&mut state.hovered, hovered) != hovered
+        };
+        if changed {
+            self.redraw();
+        }
+    }
+
+    pub(crate) fn pointer_leave(&self) {
+        let changed = {
+            let mut state = self.inner.state.borrow_mut();
+            state.pressed = None;
+            state.hovered.take().is_some()
+        };
+        if changed {
+            self.redraw();
+        }
+    }
+
+    pub(crate) fn pointer_button(
+        &self,
+        seat: &WlSeat,
+        serial: u32,
+        button: MouseButton,
+        pressed: bool,
+    ) {
+        let toplevel = &self.inner.xdg_toplevel;
+        let (pos, area, clicked) = {
+            let mut state = self.inner.state.borrow_mut();
+            let area = state.area(state.pos);
+            let clicked = match (button, pressed, area) {
+                (MouseButton::Left, true, Some(Area::Button(button))) => {
+                    state.pressed = Some(button);
+                    None
+                }
+                (MouseButton::Left, false, Some(Area::Button(button))) => {
+                    state.pressed.take().filter(|pressed| *pressed == button)
+                }
+                (MouseButton::Left, false, _) => state.pressed.take(),
+                _ => None,
+            };
+            (state.pos, area, clicked)
+        };
+
+        match (button, pressed, area) {
+            (MouseButton::Left, true, Some(Area::Titlebar)) => toplevel._move(seat, serial),
+            (MouseButton::Left, true, Some(Area::Edge(edge))) => {
+                toplevel.resize(seat, serial, edge)
+            }
+            // The menu is positioned in window geometry coordinates, which start at the titlebar.
+            (MouseButton::Right, true, Some(Area::Titlebar)) => toplevel.show_window_menu(
+                seat,
+                serial,
+                (pos.x - BORDER) as i32,
+                (pos.y - BORDER) as i32,
+            ),
+            _ => {}
+        }
+
+        match clicked {
+            Some(Button::Close) => {
+                self.inner
+                    .window
+                    .inner
+                    .with_handler(|handler| handler.request_close());
+            }
+            Some(Button::Maximize) => match self.inner.window_state.get() {
+                window::WindowState::Maximized => toplevel.unset_maximized(),
+                _ => toplevel.set_maximized(),
+            },
+            Some(Button::Minimize) => toplevel.set_minimized(),
+            None => {}
+        }
+    }
+
+    fn redraw(&self) {
+        let surface = &self.inner.surface;
+        surface.invalidate();
+        surface.inner.run_deferred_tasks();
+    }
+}
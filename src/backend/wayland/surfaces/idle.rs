@@ -19,9 +19,12 @@ impl std::fmt::Debug for Kind {
     }
 }
 
+/// The work waiting for the next turn of the loop, or `None` once the window has closed.
+pub(super) type Queue = std::sync::Arc<std::sync::Mutex<Option<Vec<Kind>>>>;
+
 #[derive(Clone)]
 pub struct Handle {
-    pub(super) queue: std::sync::Arc<std::sync::Mutex<Vec<Kind>>>,
+    pub(super) queue: Queue,
 }
 
 impl Handle {
@@ -36,19 +39,44 @@ impl Handle {
         F: FnOnce(&mut dyn window::WinHandler) + Send + 'static,
     {
         tracing::trace!("add_idle_callback initiated");
-        let mut queue = self.queue.lock().unwrap();
-        queue.push(Kind::Callback(Box::new(callback)));
+        match &mut *self.queue.lock().unwrap() {
+            Some(queue) => queue.push(Kind::Callback(Box::new(callback))),
+            None => tracing::debug!("dropping idle callback for a closed window"),
+        }
     }
 
+    /// Add an idle token, which is passed to `WinHandler::idle` when the message loop is
+    /// empty. A token which is already waiting isn't queued again, so the handler only sees it
+    /// once per turn of the loop.
     pub fn add_idle_token(&self, token: window::IdleToken) {
         tracing::trace!("add_idle_token initiated {:?}", token);
         let mut queue = self.queue.lock().unwrap();
-        queue.push(Kind::Token(token));
+        let queue = match &mut *queue {
+            Some(queue) => queue,
+            None => {
+                tracing::debug!("dropping idle token {:?} for a closed window", token);
+                return;
+            }
+        };
+        let queued = queue
+            .iter()
+            .any(|item| matches!(item, Kind::Token(queued) if *queued == token));
+        if !queued {
+            queue.push(Kind::Token(token));
+        }
     }
 }
 
+/// Drop everything that's waiting, and anything added later, as the window has closed.
+pub(super) fn close(state: &Handle) {
+    state.queue.lock().unwrap().take();
+}
+
 pub(crate) fn run(state: &Handle, winhandle: &mut dyn window::WinHandler) {
-    let queue: Vec<_> = std::mem::take(&mut state.queue.lock().unwrap());
+    let queue = match &mut *state.queue.lock().unwrap() {
+        Some(queue) => std::mem::take(queue),
+        None => return,
+    };
     for item in queue {
         match item {
             Kind::Callback(it) => it(winhandle),
@@ -56,3 +84,54 @@ pub(crate) fn run(state: &Handle, winhandle: &mut dyn window::WinHandler) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use super::*;
+    use crate::region::Region;
+
+    #[derive(Default)]
+    struct Recorder(Vec<window::IdleToken>);
+
+    impl window::WinHandler for Recorder {
+        fn connect(&mut self, _: &crate::WindowHandle) {}
+
+        fn prepare_paint(&mut self) {}
+
+        fn paint(&mut self, _: &Region) {}
+
+        #[cfg(feature = "accesskit")]
+        fn accesskit_tree(&mut self) -> accesskit::TreeUpdate {
+            unimplemented!()
+        }
+
+        fn idle(&mut self, token: window::IdleToken) {
+            self.0.push(token);
+        }
+
+        fn as_any(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn closed_window_drops_idle_work() {
+        let handle = Handle {
+            queue: Queue::new(std::sync::Mutex::new(Some(Vec::new()))),
+        };
+        let mut handler = Recorder::default();
+
+        handle.add_idle_token(window::IdleToken::new(1));
+        run(&handle, &mut handler);
+        assert_eq!(handler.0, [window::IdleToken::new(1)]);
+
+        handle.add_idle_token(window::IdleToken::new(2));
+        close(&handle);
+        handle.add_idle_token(window::IdleToken::new(3));
+        handle.add_idle_callback(|_| panic!("callback ran after the window closed"));
+        run(&handle, &mut handler);
+        assert_eq!(handler.0, [window::IdleToken::new(1)]);
+    }
+}
@@ -261,6 +261,7 @@ impl Surface {
                 let ls = handle.inner.ls_surface.borrow();
                 ls.ack_configure(serial);
                 ls.set_size(dim.width as u32, dim.height as u32);
+                handle.inner.wl_surface.borrow().set_configured();
                 handle.inner.wl_surface.borrow().update_dimensions(dim);
                 handle.inner.wl_surface.borrow().request_paint();
                 handle.inner.available.replace(true);
@@ -291,6 +292,7 @@ impl Outputs for Surface {
     }
 
     fn inserted(&self, o: &outputs::Meta) {
+        self.inner.wl_surface.borrow().inserted(o);
         let old = String::from(
             self.inner
                 .output
@@ -324,9 +326,9 @@ impl Outputs for Surface {
         );
 
         let sdata = self.inner.wl_surface.borrow().inner.clone();
-        self.inner
-            .wl_surface
-            .replace(surface::Surface::replace(&sdata));
+        sdata.with_handler(|handler| handler.surface_invalidated());
+        let (replacement, old) = surface::Surface::replace(&sdata);
+        self.inner.wl_surface.replace(replacement);
         let sdata = self.inner.wl_surface.borrow().inner.clone();
         let replacedlayershell = self.inner.ls_surface.replace(
             sdata
@@ -344,6 +346,8 @@ impl Outputs for Surface {
         Surface::initialize(self);
 
         replacedlayershell.destroy();
+        old.destroy();
+        sdata.with_handler(|handler| handler.surface_ready());
     }
 }
 
@@ -367,6 +371,14 @@ impl Handle for Surface {
         return self.inner.wl_surface.borrow().set_size(dim);
     }
 
+    fn get_position(&self) -> kurbo::Point {
+        return self.inner.wl_surface.borrow().get_position();
+    }
+
+    fn set_position(&self, position: kurbo::Point) {
+        return self.inner.wl_surface.borrow().set_position(position);
+    }
+
     fn request_anim_frame(&self) {
         if *self.inner.available.borrow() {
             self.inner.wl_surface.borrow().request_anim_frame()
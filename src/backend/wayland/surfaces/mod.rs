@@ -1,11 +1,20 @@
+use wayland_client::protocol::wl_output::WlOutput;
+use wayland_client::protocol::wl_region::WlRegion;
+use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_client::protocol::wl_shm::WlShm;
+use wayland_client::protocol::wl_subcompositor::WlSubcompositor;
 use wayland_client::{self as wlc, protocol::wl_surface::WlSurface};
+use wayland_protocols::presentation_time::client::wp_presentation::WpPresentation;
+use wayland_protocols::unstable::xdg_decoration::v1::client::zxdg_decoration_manager_v1::ZxdgDecorationManagerV1;
+use wayland_protocols::viewporter::client::wp_viewporter::WpViewporter;
 use wayland_protocols::wlr::unstable::layer_shell::v1::client::zwlr_layer_shell_v1::ZwlrLayerShellV1;
 use wayland_protocols::xdg_shell::client::xdg_popup;
 use wayland_protocols::xdg_shell::client::xdg_positioner;
 use wayland_protocols::xdg_shell::client::xdg_surface;
+use wayland_protocols::xdg_shell::client::xdg_toplevel;
 
 use crate::kurbo;
+use crate::window;
 use crate::Scale;
 use crate::TextFieldToken;
 
@@ -13,6 +22,7 @@ use super::error;
 use super::outputs;
 
 pub mod buffers;
+pub mod decorations;
 pub mod idle;
 pub mod layershell;
 pub mod popup;
@@ -24,15 +34,36 @@ pub static GLOBAL_ID: crate::Counter = crate::Counter::new();
 pub trait Compositor {
     fn output(&self, id: u32) -> Option<outputs::Meta>;
     fn create_surface(&self) -> wlc::Main<WlSurface>;
+    fn create_region(&self) -> wlc::Main<WlRegion>;
     fn shared_mem(&self) -> wlc::Main<WlShm>;
     fn get_xdg_surface(&self, surface: &wlc::Main<WlSurface>)
         -> wlc::Main<xdg_surface::XdgSurface>;
     fn get_xdg_positioner(&self) -> wlc::Main<xdg_positioner::XdgPositioner>;
     fn zwlr_layershell_v1(&self) -> Option<wlc::Main<ZwlrLayerShellV1>>;
+    fn zxdg_decoration_manager_v1(&self) -> Option<wlc::Main<ZxdgDecorationManagerV1>>;
+    fn wl_subcompositor(&self) -> Option<wlc::Main<WlSubcompositor>>;
+    fn wp_viewporter(&self) -> Option<wlc::Main<WpViewporter>>;
+    fn wp_presentation(&self) -> Option<wlc::Main<WpPresentation>>;
 }
 
 pub trait Decor {
     fn inner_set_title(&self, title: String);
+    fn set_app_id(&self, app_id: String);
+    fn window_state(&self) -> window::WindowState;
+    /// Let the user move the window by dragging the pointer, after the press with `serial`.
+    fn begin_move_drag(&self, seat: &WlSeat, serial: u32);
+    /// Let the user resize the window from `edge`, after the press with `serial`.
+    fn begin_resize_drag(&self, seat: &WlSeat, serial: u32, edge: window::ResizeEdge);
+    /// Make the window fullscreen, on `output` if given, or leave fullscreen.
+    fn set_fullscreen(&self, fullscreen: bool, output: Option<WlOutput>);
+    /// Whether the user can resize the window. A window which can't keeps its current size.
+    fn set_resizable(&self, resizable: bool);
+    fn set_min_size(&self, size: kurbo::Size);
+    fn set_max_size(&self, size: Option<kurbo::Size>);
+    /// The decorations we draw ourselves, if any.
+    fn frame(&self) -> Option<decorations::Frame>;
+    /// The toplevel, for surfaces which are one, so dialogs can be made children of it.
+    fn xdg_toplevel(&self) -> Option<wlc::Main<xdg_toplevel::XdgToplevel>>;
 }
 
 impl dyn Decor {
@@ -58,6 +89,9 @@ pub(super) trait Outputs {
 pub trait Handle {
     fn get_size(&self) -> kurbo::Size;
     fn set_size(&self, dim: kurbo::Size);
+    /// The position relative to the parent, for surfaces which have one.
+    fn get_position(&self) -> kurbo::Point;
+    fn set_position(&self, position: kurbo::Point);
     fn request_anim_frame(&self);
     fn invalidate(&self);
     fn invalidate_rect(&self, rect: kurbo::Rect);
@@ -88,6 +122,10 @@ impl CompositorHandle {
         self.inner.upgrade().map(|c| c.create_surface())
     }
 
+    fn create_region(&self) -> Option<wlc::Main<WlRegion>> {
+        self.inner.upgrade().map(|c| c.create_region())
+    }
+
     /// Recompute the scale to use (the maximum of all the provided outputs).
     fn recompute_scale(&self, outputs: &std::collections::HashSet<u32>) -> i32 {
         let compositor = match self.inner.upgrade() {
@@ -134,6 +172,13 @@ impl Compositor for CompositorHandle {
         }
     }
 
+    fn create_region(&self) -> wlc::Main<WlRegion> {
+        match self.inner.upgrade() {
+            None => panic!("unable to acquire underlying compositor to create a region"),
+            Some(c) => c.create_region(),
+        }
+    }
+
     fn shared_mem(&self) -> wlc::Main<WlShm> {
         match self.inner.upgrade() {
             None => panic!("unable to acquire underlying compositor to acquire shared memory"),
@@ -166,4 +211,50 @@ impl Compositor for CompositorHandle {
             Some(c) => c.zwlr_layershell_v1(),
         }
     }
+
+    fn zxdg_decoration_manager_v1(&self) -> Option<wlc::Main<ZxdgDecorationManagerV1>> {
+        match self.inner.upgrade() {
+            None => {
+                tracing::warn!(
+                    "unable to acquire underyling compositor to acquire the decoration manager"
+                );
+                None
+            }
+            Some(c) => c.zxdg_decoration_manager_v1(),
+        }
+    }
+
+    fn wl_subcompositor(&self) -> Option<wlc::Main<WlSubcompositor>> {
+        match self.inner.upgrade() {
+            None => {
+                tracing::warn!(
+                    "unable to acquire underyling compositor to acquire the subcompositor"
+                );
+                None
+            }
+            Some(c) => c.wl_subcompositor(),
+        }
+    }
+
+    fn wp_viewporter(&self) -> Option<wlc::Main<WpViewporter>> {
+        match self.inner.upgrade() {
+            None => {
+                tracing::warn!("unable to acquire underyling compositor to acquire the viewporter");
+                None
+            }
+            Some(c) => c.wp_viewporter(),
+        }
+    }
+
+    fn wp_presentation(&self) -> Option<wlc::Main<WpPresentation>> {
+        match self.inner.upgrade() {
+            None => {
+                tracing::warn!(
+                    "unable to acquire underyling compositor to acquire the presentation"
+                );
+                None
+            }
+            Some(c) => c.wp_presentation(),
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use wayland_client as wlc;
+use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_protocols::xdg_shell::client::xdg_popup;
 use wayland_protocols::xdg_shell::client::xdg_positioner;
 use wayland_protocols::xdg_shell::client::xdg_surface;
@@ -20,6 +21,13 @@ struct Inner {
     wl_xdg_surface: wlc::Main<xdg_surface::XdgSurface>,
     wl_xdg_popup: wlc::Main<xdg_popup::XdgPopup>,
     wl_xdg_pos: wlc::Main<xdg_positioner::XdgPositioner>,
+    compositor: CompositorHandle,
+    /// How the popup was placed, which is the starting point for moving it.
+    config: std::cell::RefCell<Config>,
+    /// Where the compositor put the popup, relative to the parent's window geometry.
+    position: std::rc::Rc<std::cell::Cell<kurbo::Point>>,
+    /// The token of the most recent reposition request.
+    reposition: std::cell::Cell<u32>,
 }
 
 impl From<Inner> for std::sync::Arc<surface::Data> {
@@ -36,6 +44,9 @@ pub struct Config {
     pub anchor: xdg_positioner::Anchor,
     pub gravity: xdg_positioner::Gravity,
     pub constraint_adjustment: xdg_positioner::ConstraintAdjustment,
+    /// The seat and the serial of the input event which opened the popup, to take an explicit
+    /// grab. Grabbed popups get the keyboard focus, and are dismissed by clicks elsewhere.
+    pub grab: Option<(WlSeat, u32)>,
 }
 
 impl Config {
@@ -80,6 +91,7 @@ impl Default for Config {
             anchor_rect: (kurbo::Point::ZERO, kurbo::Size::from((1., 1.))),
             gravity: xdg_positioner::Gravity::BottomLeft,
             constraint_adjustment: xdg_positioner::ConstraintAdjustment::all(),
+            grab: None,
         }
     }
 }
@@ -106,6 +118,7 @@ impl Surface {
             move |xdg_surface, event, _| match event {
                 xdg_surface::Event::Configure { serial } => {
                     xdg_surface.ack_configure(serial);
+                    wl_surface.set_configured();
                     let dim = wl_surface.inner.logical_size.get();
                     wl_surface.inner.handler.borrow_mut().size(dim);
                     wl_surface.request_paint();
@@ -114,7 +127,8 @@ impl Surface {
             }
         });
 
-        let wl_xdg_pos = config.apply(&compositor);
+        let grab = config.grab.clone();
+        let wl_xdg_pos = config.clone().apply(&compositor);
         wl_xdg_pos.quick_assign(|obj, event, _| {
             tracing::debug!("{:?} {:?}", obj, event);
         });
@@ -123,8 +137,10 @@ impl Surface {
             Ok(p) => p,
             Err(cause) => return Err(cause),
         };
+        let position = std::rc::Rc::new(std::cell::Cell::new(config.offset));
         wl_xdg_popup.quick_assign({
             let wl_surface = wl_surface.clone();
+            let position = position.clone();
             move |_xdg_popup, event, _| {
                 match event {
                     xdg_popup::Event::Configure {
@@ -140,8 +156,13 @@ impl Surface {
                             width,
                             height
                         );
+                        position.set(kurbo::Point::new(x as f64, y as f64));
                         wl_surface.update_dimensions((width as f64, height as f64));
                     }
+                    xdg_popup::Event::Repositioned { token } => {
+                        // The new position arrives with the configure which follows.
+                        tracing::debug!("popup repositioned {:?}", token);
+                    }
                     xdg_popup::Event::PopupDone => {
                         tracing::debug!("popup done {:?}", event);
                         match wl_surface.data() {
@@ -157,6 +178,10 @@ impl Surface {
                 };
             }
         });
+        // The grab has to be taken before the initial commit.
+        if let Some((seat, serial)) = grab {
+            wl_xdg_popup.grab(&seat, serial);
+        }
 
         let handle = Self {
             inner: std::sync::Arc::new(Inner {
@@ -164,6 +189,13 @@ impl Surface {
                 wl_xdg_surface,
                 wl_xdg_popup,
                 wl_xdg_pos,
+                compositor,
+                config: std::cell::RefCell::new(Config {
+                    grab: None,
+                    ..config
+                }),
+                position,
+                reposition: std::cell::Cell::new(0),
             }),
         };
 
@@ -193,6 +225,34 @@ impl Handle for Surface {
         self.inner.wl_surface.set_size(dim);
     }
 
+    fn get_position(&self) -> kurbo::Point {
+        self.inner.position.get()
+    }
+
+    /// Move the popup, which the compositor is free to adjust like it did when the popup was
+    /// first placed.
+    fn set_position(&self, position: kurbo::Point) {
+        let popup = &self.inner.wl_xdg_popup;
+        if popup.as_ref().version() < xdg_popup::REQ_REPOSITION_SINCE {
+            tracing::warn!("moving popups is not supported by the compositor");
+            return;
+        }
+        let config = self
+            .inner
+            .config
+            .borrow()
+            .clone()
+            .with_size(self.inner.wl_surface.inner.logical_size.get())
+            .with_offset(position);
+        self.inner.config.replace(config.clone());
+        let pos = config.apply(&self.inner.compositor);
+        let token = self.inner.reposition.get().wrapping_add(1);
+        self.inner.reposition.set(token);
+        popup.reposition(&pos, token);
+        // The popup keeps its own copy of the positioner.
+        pos.destroy();
+    }
+
     fn request_anim_frame(&self) {
         self.inner.wl_surface.request_anim_frame()
     }
@@ -226,6 +286,9 @@ impl Handle for Surface {
     }
 
     fn release(&self) {
+        // The roles have to go before the surface.
+        self.inner.wl_xdg_popup.destroy();
+        self.inner.wl_xdg_surface.destroy();
         self.inner.wl_surface.release()
     }
 
@@ -1,10 +1,17 @@
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::Duration;
 use wayland_client as wlc;
+use wayland_client::protocol::wl_callback;
+use wayland_client::protocol::wl_output;
+use wayland_client::protocol::wl_seat;
 use wayland_client::protocol::wl_surface;
+use wayland_protocols::presentation_time::client::wp_presentation_feedback;
+use wayland_protocols::viewporter::client::wp_viewport::WpViewport;
 use wayland_protocols::xdg_shell::client::xdg_popup;
 use wayland_protocols::xdg_shell::client::xdg_positioner;
 use wayland_protocols::xdg_shell::client::xdg_surface;
+use wayland_protocols::xdg_shell::client::xdg_toplevel;
 
 use crate::kurbo;
 use crate::window;
@@ -13,17 +20,28 @@ use crate::{region::Region, scale::Scale, TextFieldToken};
 use super::super::Changed;
 
 use super::super::outputs;
+use super::super::text_input::TextInput;
 use super::buffers;
+use super::decorations;
 use super::error;
 use super::idle;
 use super::Popup;
 use super::{Compositor, CompositorHandle, Decor, Handle, Outputs};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DeferredTask {
     Paint,
-    AnimationClear,
+    /// Commit pending surface state (e.g. a new title) which doesn't need a repaint.
+    Commit,
+    /// Tell the input method about changes to the focused text field.
+    TextInput,
+    /// Tell the handler it lost the focus and is destroyed, as the window was closed.
+    Destroy,
 }
 
+/// Draws a frame straight into the buffer, given its physical size and the scale.
+pub(crate) type Draw = Box<dyn FnMut(&mut [u8], buffers::RawSize, i32)>;
+
 #[derive(Clone)]
 pub struct Surface {
     pub(super) inner: std::sync::Arc<Data>,
@@ -47,23 +65,36 @@ impl Surface {
             Some(v) => v,
         };
 
+        let viewport = compositor
+            .wp_viewporter()
+            .map(|viewporter| viewporter.get_viewport(&wl_surface));
         let current = std::sync::Arc::new(Data {
             compositor: compositor.clone(),
             wl_surface: RefCell::new(wl_surface),
+            viewport: RefCell::new(viewport),
             outputs: RefCell::new(std::collections::HashSet::new()),
             buffers: buffers::Buffers::new(compositor.shared_mem(), initial_size.into()),
             logical_size: Cell::new(initial_size),
             scale: Cell::new(1),
             anim_frame_requested: Cell::new(false),
             handler: RefCell::new(handler),
-            idle_queue: std::sync::Arc::new(std::sync::Mutex::new(vec![])),
+            idle_queue: std::sync::Arc::new(std::sync::Mutex::new(Some(vec![]))),
             active_text_input: Cell::new(None),
+            text_inputs: RefCell::new(Vec::new()),
             damaged_region: RefCell::new(Region::EMPTY),
             deferred_tasks: RefCell::new(std::collections::VecDeque::new()),
+            draw: RefCell::new(None),
+            opaque: Cell::new(false),
+            input_region: RefCell::new(None),
+            configured: Cell::new(false),
+            activated: Cell::new(false),
+            keyboard_focus: Cell::new(false),
+            closing: Cell::new(false),
         });
 
         // register to receive wl_surface events.
         Surface::initsurface(&current);
+        current.update_viewport();
 
         Self { inner: current }
     }
@@ -88,17 +119,42 @@ impl Surface {
         self.inner.wl_surface.borrow().commit()
     }
 
-    pub(super) fn replace(current: &std::sync::Arc<Data>) -> Surface {
-        current
+    /// Record that the role's configure has been acked, which allows us to attach buffers.
+    pub(super) fn set_configured(&self) {
+        self.inner.configured.set(true);
+    }
+
+    /// Give the surface a new `wl_surface`, returning the old one.
+    ///
+    /// The old one must be destroyed once its role object has been.
+    pub(super) fn replace(
+        current: &std::sync::Arc<Data>,
+    ) -> (Surface, wlc::Main<wl_surface::WlSurface>) {
+        let old = current
             .wl_surface
             .replace(match current.compositor.create_surface() {
                 None => panic!("unable to create surface"),
                 Some(v) => v,
             });
+        let viewport = current
+            .compositor
+            .wp_viewporter()
+            .map(|viewporter| viewporter.get_viewport(&current.wl_surface.borrow()));
+        if let Some(old) = current.viewport.replace(viewport) {
+            old.destroy();
+        }
+        // The new surface needs its own configure before we can paint it.
+        current.configured.set(false);
         Surface::initsurface(current);
-        Self {
+        current.update_viewport();
+        current.update_buffer_scale();
+        // The regions belong to the old surface, so send them again.
+        current.update_opaque_region();
+        current.update_input_region();
+        let surface = Self {
             inner: current.clone(),
-        }
+        };
+        (surface, old)
     }
 
     fn initsurface(current: &std::sync::Arc<Data>) {
@@ -118,6 +174,7 @@ impl Surface {
         data: &wlc::DispatchData,
     ) {
         tracing::debug!("wl_surface event {:?} {:?} {:?}", surface, event, data);
+        let was_visible = current.is_visible();
         match event {
             wl_surface::Event::Enter { output } => {
                 let proxy = wlc::Proxy::from(output.clone());
@@ -130,29 +187,24 @@ impl Surface {
             _ => tracing::warn!("unhandled wayland surface event {:?}", event),
         }
 
-        if current.wl_surface.borrow().as_ref().version() >= wl_surface::REQ_SET_BUFFER_SCALE_SINCE
-        {
-            let new_scale = current.recompute_scale();
-            if current.set_scale(new_scale).is_changed() {
-                current.wl_surface.borrow().set_buffer_scale(new_scale);
-                // We also need to change the physical size to match the new scale
-                current
-                    .buffers
-                    .set_size(buffers::RawSize::from(current.logical_size.get()).scale(new_scale));
-                // always repaint, because the scale changed.
-                current.schedule_deferred_task(DeferredTask::Paint);
-            }
-        }
+        current.update_scale();
+        current.visibility_changed(was_visible);
     }
 }
 
 impl Outputs for Surface {
     fn removed(&self, o: &outputs::Meta) {
+        let was_visible = self.inner.is_visible();
         self.inner.outputs.borrow_mut().remove(&o.id());
+        self.inner.update_scale();
+        self.inner.visibility_changed(was_visible);
     }
 
-    fn inserted(&self, _: &outputs::Meta) {
-        // nothing to do here.
+    fn inserted(&self, o: &outputs::Meta) {
+        // Outputs are sent again when they change, so this might be a new scale for one of ours.
+        if self.inner.outputs.borrow().contains(&o.id()) {
+            self.inner.update_scale();
+        }
     }
 }
 
@@ -165,6 +217,18 @@ impl Handle for Surface {
         self.inner.resize(dim);
     }
 
+    // Wayland doesn't tell windows where they are, or let them pick.
+    fn get_position(&self) -> kurbo::Point {
+        kurbo::Point::ZERO
+    }
+
+    fn set_position(&self, position: kurbo::Point) {
+        tracing::warn!(
+            "windows can't set their own position on wayland {:?}",
+            position
+        );
+    }
+
     fn request_anim_frame(&self) {
         self.inner.request_anim_frame()
     }
@@ -221,6 +285,10 @@ impl From<&Surface> for std::sync::Arc<Data> {
 pub struct Data {
     pub(super) compositor: CompositorHandle,
     pub(super) wl_surface: RefCell<wlc::Main<wl_surface::WlSurface>>,
+    /// Maps the buffer, which is in physical pixels, to the logical size of the surface.
+    ///
+    /// Without one (if the compositor doesn't support viewports), we set the buffer scale instead.
+    viewport: RefCell<Option<wlc::Main<WpViewport>>>,
 
     /// The outputs that our surface is present on (we should get the first enter event early).
     pub(super) outputs: RefCell<std::collections::HashSet<u32>>,
@@ -237,6 +305,8 @@ pub struct Data {
     /// Contains the callbacks from user code.
     pub(crate) handler: RefCell<Box<dyn window::WinHandler>>,
     pub(crate) active_text_input: Cell<Option<TextFieldToken>>,
+    /// The text inputs of the seats whose keyboard focus is on this surface.
+    pub(crate) text_inputs: RefCell<Vec<TextInput>>,
 
     /// Whether we have requested an animation frame. This stops us requesting more than 1.
     anim_frame_requested: Cell<bool>,
@@ -250,7 +320,26 @@ pub struct Data {
     /// to avoid possible re-entrancy.
     deferred_tasks: RefCell<std::collections::VecDeque<DeferredTask>>,
 
-    idle_queue: std::sync::Arc<std::sync::Mutex<Vec<idle::Kind>>>,
+    idle_queue: idle::Queue,
+
+    /// For surfaces we render ourselves, such as context menus, rather than the handler.
+    pub(crate) draw: RefCell<Option<Draw>>,
+    /// Whether the window has no transparent parts, see `WindowBuilder::transparent`.
+    opaque: Cell<bool>,
+    /// The part of the surface that takes pointer and touch input, in display points. `None`
+    /// means all of it.
+    input_region: RefCell<Option<Region>>,
+    /// Whether we have acked the first configure of the surface's role.
+    ///
+    /// Attaching a buffer before then is a protocol error, so paints wait until it is set.
+    pub(super) configured: Cell<bool>,
+    /// Whether the compositor says the window is active. The handler is focused while the window
+    /// is active or has keyboard focus, as both tell us the same thing.
+    activated: Cell<bool>,
+    /// Whether a keyboard has entered the surface.
+    keyboard_focus: Cell<bool>,
+    /// Whether the window was closed, so only the handler's destruction is left to run.
+    closing: Cell<bool>,
 }
 
 impl Data {
@@ -261,6 +350,15 @@ impl Data {
         }
     }
 
+    pub(crate) fn wl_surface(&self) -> wl_surface::WlSurface {
+        self.wl_surface.borrow().detach()
+    }
+
+    /// Is `surface` the wayland surface backing this window?
+    pub(crate) fn owns_surface(&self, surface: &wl_surface::WlSurface) -> bool {
+        self.wl_surface.borrow().as_ref().equals(surface.as_ref())
+    }
+
     #[track_caller]
     pub(crate) fn with_handler<T, F: FnOnce(&mut dyn window::WinHandler) -> T>(
         &self,
@@ -314,11 +412,162 @@ impl Data {
         let previous_logical_size = self.logical_size.replace(dim);
         if previous_logical_size != dim {
             self.buffers.set_size(raw_logical_size.scale(scale));
+            self.update_viewport();
+            self.update_opaque_region();
         }
 
         dim
     }
 
+    pub(super) fn set_activated(&self, activated: bool) {
+        let was_focused = self.is_focused();
+        self.activated.set(activated);
+        self.focus_changed(was_focused);
+    }
+
+    pub(crate) fn set_keyboard_focus(&self, focused: bool) {
+        let was_focused = self.is_focused();
+        self.keyboard_focus.set(focused);
+        self.focus_changed(was_focused);
+    }
+
+    /// Take the focus away for good, as the window is closing.
+    pub(crate) fn unfocus(&self) {
+        let was_focused = self.is_focused();
+        self.activated.set(false);
+        self.keyboard_focus.set(false);
+        self.focus_changed(was_focused);
+    }
+
+    fn is_focused(&self) -> bool {
+        self.activated.get() || self.keyboard_focus.get()
+    }
+
+    /// Tell the handler if the focus changed, so it hears about each change once.
+    fn focus_changed(&self, was_focused: bool) {
+        match (was_focused, self.is_focused()) {
+            (false, true) => {
+                self.with_handler(|handler| handler.got_focus());
+            }
+            (true, false) => {
+                self.with_handler(|handler| handler.lost_focus());
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the surface is on any output.
+    pub(crate) fn is_visible(&self) -> bool {
+        !self.outputs.borrow().is_empty()
+    }
+
+    /// Tell the handler if the surface was shown or hidden, and repaint it if it was shown, as
+    /// the compositor stops sending frame callbacks to hidden surfaces.
+    fn visibility_changed(&self, was_visible: bool) {
+        let visible = self.is_visible();
+        if visible == was_visible {
+            return;
+        }
+        tracing::debug!("surface visibility changed to {}", visible);
+        self.with_handler(|handler| handler.visibility_changed(visible));
+        if visible {
+            self.invalidate();
+        }
+    }
+
+    pub(crate) fn set_opaque(&self, opaque: bool) {
+        if self.opaque.replace(opaque) != opaque {
+            self.update_opaque_region();
+        }
+    }
+
+    pub(crate) fn set_input_region(&self, region: Option<Region>) {
+        self.input_region.replace(region);
+        self.update_input_region();
+        // Unlike the opaque region, this doesn't change with a repaint, so commit it by itself.
+        self.schedule_deferred_task(DeferredTask::Commit);
+    }
+
+    /// Tell the compositor the logical size to show the buffer at. Like the rest of the surface
+    /// state, this is applied on the next commit.
+    fn update_viewport(&self) {
+        let viewport = self.viewport.borrow();
+        let viewport = match &*viewport {
+            Some(viewport) => viewport,
+            None => return,
+        };
+        let size = buffers::RawSize::from(self.logical_size.get());
+        if size.is_empty() {
+            // An empty destination is a protocol error, so go back to the size of the buffer.
+            viewport.set_destination(-1, -1);
+        } else {
+            viewport.set_destination(size.width, size.height);
+        }
+    }
+
+    /// Tell the compositor the scale of our buffers. Like the rest of the surface state, this is
+    /// applied on the next commit, which attaches a buffer of the matching size.
+    fn update_buffer_scale(&self) {
+        // The viewport already maps the buffer to the logical size, whatever the scale.
+        if self.viewport.borrow().is_some() {
+            return;
+        }
+        let wl_surface = self.wl_surface.borrow();
+        if wl_surface.as_ref().version() >= wl_surface::REQ_SET_BUFFER_SCALE_SINCE {
+            wl_surface.set_buffer_scale(self.scale.get());
+        }
+    }
+
+    /// Tell the compositor whether it can skip drawing what's underneath the surface. Like the
+    /// rest of the surface state, this is applied on the next commit.
+    fn update_opaque_region(&self) {
+        let wl_surface = self.wl_surface.borrow();
+        if !self.opaque.get() {
+            wl_surface.set_opaque_region(None);
+            return;
+        }
+        let region = match self.compositor.create_region() {
+            Some(region) => region,
+            None => return,
+        };
+        // The region is in surface coordinates, which are display points.
+        let size = self.logical_size.get();
+        region.add(0, 0, size.width as i32, size.height as i32);
+        wl_surface.set_opaque_region(Some(&region));
+        region.destroy();
+    }
+
+    /// Tell the compositor which part of the surface takes input, input elsewhere goes to whatever
+    /// is behind it. Like the rest of the surface state, this is applied on the next commit.
+    fn update_input_region(&self) {
+        let wl_surface = self.wl_surface.borrow();
+        let input_region = self.input_region.borrow();
+        let rects = match &*input_region {
+            Some(input_region) => input_region.rects(),
+            None => {
+                wl_surface.set_input_region(None);
+                return;
+            }
+        };
+        let region = match self.compositor.create_region() {
+            Some(region) => region,
+            None => return,
+        };
+        // The region is in surface coordinates, which are display points. Round outwards, so
+        // the edges of the rects still take input.
+        for rect in rects {
+            let rect = rect.expand();
+            region.add(
+                rect.x0 as i32,
+                rect.y0 as i32,
+                rect.width() as i32,
+                rect.height() as i32,
+            );
+        }
+        wl_surface.set_input_region(Some(&region));
+        region.destroy();
+    }
+
     /// Assert that the physical size = logical size * scale
     #[allow(unused)]
     fn assert_size(&self) {
@@ -339,6 +588,35 @@ impl Data {
         self.compositor.recompute_scale(&self.outputs.borrow())
     }
 
+    /// Match the scale to the outputs the surface is on.
+    ///
+    /// This is always a whole number, so on an output scaled by 1.25 or 1.5 the compositor
+    /// downscales our buffer. TODO: use `wp_fractional_scale_v1` once we're on a version of
+    /// wayland-protocols which has it (it isn't in 0.29), the viewport can already map a buffer of
+    /// any size.
+    fn update_scale(&self) {
+        let viewport = self.viewport.borrow().is_some();
+        if !viewport
+            && self.wl_surface.borrow().as_ref().version() < wl_surface::REQ_SET_BUFFER_SCALE_SINCE
+        {
+            return;
+        }
+        // A surface which isn't on any output (e.g. its only monitor was unplugged) keeps the
+        // scale it had, until it's shown somewhere else.
+        if self.outputs.borrow().is_empty() {
+            return;
+        }
+        let new_scale = self.recompute_scale();
+        if self.set_scale(new_scale).is_changed() {
+            self.update_buffer_scale();
+            // We also need to change the physical size to match the new scale
+            self.buffers
+                .set_size(buffers::RawSize::from(self.logical_size.get()).scale(new_scale));
+            // always repaint, because the scale changed.
+            self.schedule_deferred_task(DeferredTask::Paint);
+        }
+    }
+
     /// Sets the scale
     ///
     /// Up to the caller to make sure `physical_size`, `logical_size` and `scale` are consistent.
@@ -364,7 +642,7 @@ impl Data {
     /// - `buf` is what we draw the frame into
     /// - `size` is the physical size in pixels we are drawing.
     /// - `force` means draw the whole frame, even if it wasn't all invalidated.
-    pub(super) fn paint(&self, physical_size: buffers::RawSize, _buf: &mut [u8], force: bool) {
+    pub(super) fn paint(&self, physical_size: buffers::RawSize, buf: &mut [u8], force: bool) {
         tracing::trace!(
             "paint initiated {:?} - {:?} {:?}",
             self.get_size(),
@@ -379,8 +657,17 @@ impl Data {
             self.wl_surface.borrow().as_ref().version() >= wl_surface::REQ_DAMAGE_BUFFER_SINCE;
 
         if force || !damage_buffer_supported {
-            self.invalidate();
-            self.wl_surface.borrow().damage(0, 0, i32::MAX, i32::MAX);
+            // Mark everything as damaged so the handler repaints the whole frame. Going through
+            // `invalidate` here would schedule a second, redundant paint.
+            let window_rect = self.logical_size.get().to_rect();
+            self.damaged_region.borrow_mut().add_rect(window_rect);
+            if damage_buffer_supported {
+                self.wl_surface
+                    .borrow()
+                    .damage_buffer(0, 0, i32::MAX, i32::MAX);
+            } else {
+                self.wl_surface.borrow().damage(0, 0, i32::MAX, i32::MAX);
+            }
         } else {
             let damaged_region = self.damaged_region.borrow_mut();
             for rect in damaged_region.rects() {
@@ -401,11 +688,53 @@ impl Data {
         }
 
         // reset damage ready for next frame.
-        self.damaged_region.borrow_mut().clear();
+        let invalid = std::mem::replace(&mut *self.damaged_region.borrow_mut(), Region::EMPTY);
+        self.with_handler_and_dont_check_the_other_borrows(|winhandle| winhandle.paint(&invalid));
+        if let Some(draw) = &mut *self.draw.borrow_mut() {
+            draw(buf, physical_size, self.scale.get());
+        }
         self.buffers.attach(self);
+        self.request_presentation_feedback();
         self.wl_surface.borrow().commit();
     }
 
+    /// Ask the compositor to tell the handler when the frame about to be committed is shown.
+    fn request_presentation_feedback(&self) {
+        let presentation = match self.compositor.wp_presentation() {
+            Some(presentation) => presentation,
+            None => return,
+        };
+        // The compositor sends the feedback some time after the commit, once the frame has been
+        // shown or discarded. The idle handle passes it on to the handler.
+        let idle = self.get_idle_handle();
+        presentation
+            .feedback(&self.wl_surface.borrow())
+            .quick_assign(move |_, event, _| {
+                let info = match event {
+                    wp_presentation_feedback::Event::Presented {
+                        tv_sec_hi,
+                        tv_sec_lo,
+                        tv_nsec,
+                        refresh,
+                        ..
+                    } => window::PresentationInfo::Presented {
+                        time: Duration::new((tv_sec_hi as u64) << 32 | tv_sec_lo as u64, tv_nsec),
+                        refresh: (refresh != 0).then(|| Duration::from_nanos(refresh as u64)),
+                    },
+                    wp_presentation_feedback::Event::Discarded => {
+                        window::PresentationInfo::Discarded
+                    }
+                    // The outputs the frame was shown on, which we don't need.
+                    wp_presentation_feedback::Event::SyncOutput { .. } => return,
+                    _ => {
+                        tracing::warn!("unhandled presentation feedback event {:?}", event);
+                        return;
+                    }
+                };
+                idle.add_idle_callback(move |handler| handler.presented(info));
+            });
+    }
+
     /// Request invalidation of the entire window contents.
     fn invalidate(&self) {
         tracing::trace!("invalidate initiated");
@@ -421,7 +750,7 @@ impl Data {
     fn invalidate_rect(&self, rect: kurbo::Rect) {
         tracing::trace!("invalidate_rect initiated {:?}", rect);
         // Quick check to see if we can skip the rect entirely (if it is outside the visible
-        // screen).
+        // screen, or has no area).
         if rect.intersect(self.logical_size.get().to_rect()).is_empty() {
             return;
         }
@@ -438,7 +767,16 @@ impl Data {
 
     pub fn schedule_deferred_task(&self, task: DeferredTask) {
         tracing::trace!("scedule_deferred_task initiated");
-        self.deferred_tasks.borrow_mut().push_back(task);
+        if self.closing.get() {
+            return;
+        }
+        let mut tasks = self.deferred_tasks.borrow_mut();
+        // Many invalidations or state changes in one handler pass only need a single paint or
+        // commit.
+        if tasks.contains(&task) {
+            return;
+        }
+        tasks.push_back(task);
     }
 
     pub fn run_deferred_tasks(&self) {
@@ -457,12 +795,34 @@ impl Data {
             DeferredTask::Paint => {
                 self.buffers.request_paint(self);
             }
-            DeferredTask::AnimationClear => {
-                self.anim_frame_requested.set(false);
+            DeferredTask::Commit => {
+                self.wl_surface.borrow().commit();
+            }
+            DeferredTask::TextInput => {
+                let inputs = self.text_inputs.borrow().clone();
+                for input in inputs {
+                    input.update(self);
+                }
+            }
+            DeferredTask::Destroy => {
+                // The handler hears that it lost the focus before it's destroyed.
+                self.unfocus();
+                self.with_handler(|handler| handler.destroy());
             }
         }
     }
 
+    /// Destroy the handler once it is no longer borrowed, as the window may be closed from one
+    /// of its own callbacks. Nothing else we had deferred runs after that.
+    pub(crate) fn close(&self) {
+        self.deferred_tasks.borrow_mut().clear();
+        self.schedule_deferred_task(DeferredTask::Destroy);
+        self.closing.set(true);
+        if self.handler.try_borrow_mut().is_ok() {
+            self.run_deferred_tasks();
+        }
+    }
+
     pub(super) fn get_size(&self) -> kurbo::Size {
         // size in pixels, so we must apply scale.
         let logical_size = self.logical_size.get();
@@ -470,26 +830,51 @@ impl Data {
         kurbo::Size::new(logical_size.width * scale, logical_size.height * scale)
     }
 
-    pub(super) fn request_anim_frame(&self) {
+    pub(super) fn request_anim_frame(self: &std::sync::Arc<Self>) {
         if self.anim_frame_requested.replace(true) {
             return;
         }
 
-        let idle = self.get_idle_handle();
-        idle.add_idle_callback(move |winhandle| {
+        let current = self.clone();
+        self.wl_surface
+            .borrow()
+            .frame()
+            .quick_assign(move |_, event, _| match event {
+                wl_callback::Event::Done { callback_data } => {
+                    current.anim_frame_done(callback_data)
+                }
+                _ => tracing::warn!("unhandled wl_callback event {:?}", event),
+            });
+        // The frame callback only takes effect once the surface is committed.
+        self.schedule_deferred_task(DeferredTask::Commit);
+    }
+
+    /// The compositor is ready for the next animation frame, `time` is in milliseconds.
+    fn anim_frame_done(&self, time: u32) {
+        self.anim_frame_requested.set(false);
+        // Invalidations made in `prepare_paint` schedule a paint, which runs straight after.
+        self.with_handler(|winhandle| {
+            winhandle.frame_time(Duration::from_millis(time.into()));
             winhandle.prepare_paint();
         });
-        self.schedule_deferred_task(DeferredTask::AnimationClear);
     }
 
     pub(super) fn remove_text_field(&self, token: TextFieldToken) {
         if self.active_text_input.get() == Some(token) {
             self.active_text_input.set(None);
+            self.schedule_deferred_task(DeferredTask::TextInput);
         }
     }
 
     pub(super) fn set_focused_text_field(&self, active_field: Option<TextFieldToken>) {
         self.active_text_input.set(active_field);
+        self.schedule_deferred_task(DeferredTask::TextInput);
+    }
+
+    pub(crate) fn update_text_field(&self, token: TextFieldToken) {
+        if self.active_text_input.get() == Some(token) {
+            self.schedule_deferred_task(DeferredTask::TextInput);
+        }
     }
 
     pub(super) fn get_idle_handle(&self) -> idle::Handle {
@@ -510,6 +895,10 @@ impl Data {
     }
 
     pub(super) fn release(&self) {
+        idle::close(&self.get_idle_handle());
+        if let Some(viewport) = self.viewport.borrow_mut().take() {
+            viewport.destroy();
+        }
         self.wl_surface.borrow().destroy();
     }
 }
@@ -533,6 +922,52 @@ impl Decor for Dead {
     fn inner_set_title(&self, title: String) {
         tracing::warn!("set_title not implemented for this surface: {:?}", title);
     }
+
+    fn set_app_id(&self, app_id: String) {
+        tracing::warn!("set_app_id not implemented for this surface: {:?}", app_id);
+    }
+
+    fn window_state(&self) -> window::WindowState {
+        window::WindowState::Restored
+    }
+
+    fn begin_move_drag(&self, _: &wl_seat::WlSeat, _: u32) {
+        tracing::warn!("begin_move_drag not implemented for this surface");
+    }
+
+    fn begin_resize_drag(&self, _: &wl_seat::WlSeat, _: u32, _: window::ResizeEdge) {
+        tracing::warn!("begin_resize_drag not implemented for this surface");
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool, _: Option<wl_output::WlOutput>) {
+        tracing::warn!(
+            "set_fullscreen not implemented for this surface: {:?}",
+            fullscreen
+        );
+    }
+
+    fn set_resizable(&self, resizable: bool) {
+        tracing::warn!(
+            "set_resizable not implemented for this surface: {:?}",
+            resizable
+        );
+    }
+
+    fn set_min_size(&self, size: kurbo::Size) {
+        tracing::warn!("set_min_size not implemented for this surface: {:?}", size);
+    }
+
+    fn set_max_size(&self, size: Option<kurbo::Size>) {
+        tracing::warn!("set_max_size not implemented for this surface: {:?}", size);
+    }
+
+    fn frame(&self) -> Option<decorations::Frame> {
+        None
+    }
+
+    fn xdg_toplevel(&self) -> Option<wlc::Main<xdg_toplevel::XdgToplevel>> {
+        None
+    }
 }
 
 impl Outputs for Dead {
@@ -561,6 +996,14 @@ impl Handle for Dead {
         tracing::warn!("set_size invoked on a dead surface {:?}", dim);
     }
 
+    fn get_position(&self) -> kurbo::Point {
+        kurbo::Point::ZERO
+    }
+
+    fn set_position(&self, position: kurbo::Point) {
+        tracing::warn!("set_position invoked on a dead surface {:?}", position);
+    }
+
     fn request_anim_frame(&self) {
         tracing::warn!("request_anim_frame invoked on a dead surface")
     }
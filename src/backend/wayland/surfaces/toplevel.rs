@@ -1,10 +1,14 @@
 use wayland_client as wlc;
+use wayland_protocols::unstable::xdg_decoration::v1::client::zxdg_toplevel_decoration_v1::{
+    self, ZxdgToplevelDecorationV1,
+};
 use wayland_protocols::xdg_shell::client::xdg_surface;
 use wayland_protocols::xdg_shell::client::xdg_toplevel;
 
 use crate::kurbo;
 use crate::window;
 
+use super::decorations;
 use super::error;
 use super::surface;
 use super::Compositor;
@@ -18,11 +22,118 @@ struct Inner {
     wl_surface: surface::Surface,
     pub(super) xdg_surface: wlc::Main<xdg_surface::XdgSurface>,
     pub(super) xdg_toplevel: wlc::Main<xdg_toplevel::XdgToplevel>,
+    /// The most recently requested title, which the decorations we draw show too.
+    title: std::rc::Rc<std::cell::RefCell<String>>,
+    /// The window state from the most recent configure event.
+    window_state: std::rc::Rc<std::cell::Cell<window::WindowState>>,
+    decoration: Option<wlc::Main<ZxdgToplevelDecorationV1>>,
+    /// The decorations we draw, when the compositor leaves them to us.
+    frame: std::rc::Rc<std::cell::RefCell<Option<decorations::Frame>>>,
+    limits: std::rc::Rc<std::cell::Cell<Limits>>,
+    /// The size the application asked for with `set_size`, which configures don't override
+    /// while the window is floating.
+    preferred: std::rc::Rc<std::cell::Cell<Option<kurbo::Size>>>,
+    /// Whether the most recent configure left the window floating, rather than maximized,
+    /// fullscreen or tiled.
+    floating: std::rc::Rc<std::cell::Cell<bool>>,
+    layout: std::rc::Rc<Layout>,
+}
+
+/// Applies a size, from a configure or the one the application asked for if it's zero, and
+/// lays out the decorations around it.
+type Layout = dyn Fn(&xdg_surface::XdgSurface, Option<kurbo::Size>);
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            frame.destroy();
+        }
+        if let Some(decoration) = &self.decoration {
+            decoration.destroy();
+        }
+    }
 }
 
 impl From<Inner> for std::sync::Arc<surface::Data> {
     fn from(s: Inner) -> std::sync::Arc<surface::Data> {
-        std::sync::Arc::<surface::Data>::from(s.wl_surface)
+        std::sync::Arc::<surface::Data>::from(s.wl_surface.clone())
+    }
+}
+
+/// The size limits of the window content, in display points, which are also the surface
+/// coordinates the compositor expects.
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    min: kurbo::Size,
+    max: Option<kurbo::Size>,
+    /// The size of a window which can't be resized, which is both its minimum and maximum.
+    fixed: Option<kurbo::Size>,
+}
+
+impl Limits {
+    fn resizable(&self) -> bool {
+        self.fixed.is_none()
+    }
+
+    fn min(&self) -> kurbo::Size {
+        self.fixed.unwrap_or(self.min)
+    }
+
+    /// The maximum, which never goes below the minimum since contradictory limits are a protocol
+    /// error.
+    fn max(&self) -> Option<kurbo::Size> {
+        if self.fixed.is_some() {
+            return self.fixed;
+        }
+        self.max.map(|max| {
+            kurbo::Size::new(
+                max.width.max(self.min.width),
+                max.height.max(self.min.height),
+            )
+        })
+    }
+
+    /// The size of the content after a configure, given `requested` without the decorations,
+    /// where zero leaves the size to us.
+    ///
+    /// A window which isn't floating has to take the size the compositor gives it. For a
+    /// floating window it's only a suggestion, so the size the application asked for wins, if
+    /// it asked for one. Otherwise it falls back to `initial`.
+    fn configured(
+        &self,
+        requested: kurbo::Size,
+        floating: bool,
+        preferred: Option<kurbo::Size>,
+        initial: kurbo::Size,
+    ) -> kurbo::Size {
+        match preferred {
+            Some(preferred) if floating => self.clamp(preferred),
+            _ if requested.is_empty() => self.clamp(preferred.unwrap_or(initial)),
+            _ if !floating => requested,
+            _ => self.clamp(requested),
+        }
+    }
+
+    fn clamp(&self, dim: kurbo::Size) -> kurbo::Size {
+        let min = self.min();
+        let dim = kurbo::Size::new(dim.width.max(min.width), dim.height.max(min.height));
+        match self.max() {
+            Some(max) => kurbo::Size::new(dim.width.min(max.width), dim.height.min(max.height)),
+            None => dim,
+        }
+    }
+
+    /// Tell the compositor about the limits, which it applies to the window geometry, so they
+    /// include any decorations we draw.
+    fn send(&self, xdg_toplevel: &xdg_toplevel::XdgToplevel, insets: kurbo::Insets) {
+        let decorations = insets.size();
+        let min = self.min() + decorations;
+        xdg_toplevel.set_min_size(min.width as i32, min.height as i32);
+        // Zero means there's no maximum.
+        let max = self
+            .max()
+            .map_or(kurbo::Size::ZERO, |max| max + decorations);
+        xdg_toplevel.set_max_size(max.width as i32, max.height as i32);
     }
 }
 
@@ -37,23 +148,182 @@ impl Surface {
         handler: Box<dyn window::WinHandler>,
         size: kurbo::Size,
         min_size: Option<kurbo::Size>,
+        max_size: Option<kurbo::Size>,
+        show_titlebar: bool,
+        resizable: bool,
     ) -> Self {
-        let min_size = min_size.unwrap_or_else(|| kurbo::Size::from((1.0, 1.0)));
+        let limits = std::rc::Rc::new(std::cell::Cell::new(Limits {
+            min: min_size.unwrap_or_else(|| kurbo::Size::from((1.0, 1.0))),
+            max: max_size,
+            fixed: match resizable {
+                true => None,
+                false => Some(size),
+            },
+        }));
         let compositor = CompositorHandle::new(c);
         let wl_surface = surface::Surface::new(compositor.clone(), handler, kurbo::Size::ZERO);
         let xdg_surface = compositor.get_xdg_surface(&wl_surface.inner.wl_surface.borrow());
         let xdg_toplevel = xdg_surface.get_toplevel();
+        let window_state = std::rc::Rc::new(std::cell::Cell::new(window::WindowState::Restored));
+        let frame = std::rc::Rc::new(std::cell::RefCell::new(None));
+        let title = std::rc::Rc::new(std::cell::RefCell::new(String::new()));
+        // The size from the most recent toplevel configure, which is applied once the
+        // configure is done. Zero means we pick the size.
+        let pending = std::rc::Rc::new(std::cell::Cell::new(None::<kurbo::Size>));
+        let preferred = std::rc::Rc::new(std::cell::Cell::new(None::<kurbo::Size>));
+        let floating = std::rc::Rc::new(std::cell::Cell::new(true));
+
+        // Without the decoration protocol, we can't know whether the compositor draws them, so
+        // we assume it doesn't.
+        let client_side = std::rc::Rc::new(std::cell::Cell::new(show_titlebar));
+        let decoration = compositor.zxdg_decoration_manager_v1().map(|manager| {
+            let decoration = manager.get_toplevel_decoration(&xdg_toplevel);
+            decoration.quick_assign({
+                let client_side = client_side.clone();
+                move |_, event, _| match event {
+                    zxdg_toplevel_decoration_v1::Event::Configure { mode } => client_side.set(
+                        show_titlebar && mode == zxdg_toplevel_decoration_v1::Mode::ClientSide,
+                    ),
+                    _ => tracing::warn!("unhandled toplevel decoration event {:?}", event),
+                }
+            });
+            decoration.set_mode(match show_titlebar {
+                true => zxdg_toplevel_decoration_v1::Mode::ServerSide,
+                false => zxdg_toplevel_decoration_v1::Mode::ClientSide,
+            });
+            decoration
+        });
+
+        // Applies the size from a configure, or the one the application asked for, if any.
+        let layout = std::rc::Rc::new({
+            let wl_surface = wl_surface.clone();
+            let xdg_toplevel = xdg_toplevel.clone();
+            let window_state = window_state.clone();
+            let frame = frame.clone();
+            let title = title.clone();
+            let limits = limits.clone();
+            let preferred = preferred.clone();
+            let floating = floating.clone();
+            move |xdg_surface: &xdg_surface::XdgSurface, requested: Option<kurbo::Size>| {
+                // The decorations come out of the size the compositor asked for.
+                let mut frame = frame.borrow_mut();
+                // Fullscreen windows go without decorations.
+                let fullscreen = window_state.get() == window::WindowState::Fullscreen;
+                match (client_side.get() && !fullscreen, frame.is_some()) {
+                    (true, false) => {
+                        *frame = decorations::Frame::new(
+                            &compositor,
+                            &wl_surface,
+                            &xdg_toplevel,
+                            &window_state,
+                            limits.get().resizable(),
+                            &title.borrow(),
+                        );
+                    }
+                    (false, true) => {
+                        if let Some(frame) = frame.take() {
+                            frame.destroy();
+                        }
+                    }
+                    _ => {}
+                }
+                let insets = frame
+                    .as_ref()
+                    .map_or(kurbo::Insets::ZERO, decorations::Frame::insets);
+
+                if let Some(requested) = requested {
+                    let requested = match requested.is_empty() {
+                        true => requested,
+                        false => requested - insets.size(),
+                    };
+                    let dim =
+                        limits
+                            .get()
+                            .configured(requested, floating.get(), preferred.get(), size);
+                    // This tells the handler about the size we settled on, if it changed.
+                    wl_surface.update_dimensions(dim);
+                }
+                let dim = wl_surface.inner.logical_size.get();
+                if let Some(frame) = &*frame {
+                    frame.resize(dim);
+                }
+                // The window geometry includes the titlebar, but not the borders.
+                xdg_surface.set_window_geometry(
+                    -insets.x0 as i32,
+                    -insets.y0 as i32,
+                    (dim.width + insets.x_value()) as i32,
+                    (dim.height + insets.y_value()) as i32,
+                );
+                limits.get().send(&xdg_toplevel, insets);
+            }
+        });
+
+        // Applies a configure, and acks it.
+        let configure = std::rc::Rc::new({
+            let wl_surface = wl_surface.clone();
+            let xdg_toplevel = xdg_toplevel.clone();
+            let title = title.clone();
+            let pending = pending.clone();
+            let layout = layout.clone();
+            move |xdg_surface: &xdg_surface::XdgSurface, serial: u32| {
+                layout(xdg_surface, pending.take());
+                // Like the buffer scale and the regions of a replaced surface, the title doesn't
+                // outlive the surface it was set on, so the first configure of a (re)mapped one
+                // sends it again.
+                if !wl_surface.inner.configured.get() && !title.borrow().is_empty() {
+                    xdg_toplevel.set_title(title.borrow().clone());
+                }
+                xdg_surface.ack_configure(serial);
+                wl_surface.set_configured();
+                wl_surface.resize(wl_surface.get_size());
+                wl_surface.request_paint();
+            }
+        });
+        // The serial of the latest configure, while we wait for a frame to apply it.
+        let deferred = std::rc::Rc::new(std::cell::Cell::new(None::<u32>));
+        // Whether the latest toplevel configure is part of an interactive resize.
+        let resizing = std::rc::Rc::new(std::cell::Cell::new(false));
 
         // register to receive xdg_surface events.
         xdg_surface.quick_assign({
             let wl_surface = wl_surface.clone();
+            let resizing = resizing.clone();
             move |xdg_surface, event, _| {
                 tracing::debug!("xdg_surface event configure {:?}", event);
                 match event {
+                    // The first configure maps the window, and any other configure outside of
+                    // a resize may not be followed by another, so they're applied straight away.
+                    // A newer serial acks the deferred configure too.
+                    xdg_surface::Event::Configure { serial }
+                        if !wl_surface.inner.configured.get() || !resizing.get() =>
+                    {
+                        deferred.take();
+                        configure(&xdg_surface, serial)
+                    }
+                    // During an interactive resize the compositor sends configures much faster
+                    // than we can paint, so we apply the latest one once per frame. Acking it
+                    // acks the ones before it too.
                     xdg_surface::Event::Configure { serial } => {
-                        xdg_surface.ack_configure(serial);
-                        wl_surface.resize(wl_surface.get_size());
-                        wl_surface.request_paint();
+                        if deferred.replace(Some(serial)).is_some() {
+                            return;
+                        }
+                        let callback = wl_surface.inner.wl_surface.borrow().frame();
+                        callback.quick_assign({
+                            let configure = configure.clone();
+                            let deferred = deferred.clone();
+                            move |_, event, _| match event {
+                                wlc::protocol::wl_callback::Event::Done { .. } => {
+                                    if let Some(serial) = deferred.take() {
+                                        configure(&xdg_surface, serial);
+                                    }
+                                }
+                                _ => tracing::warn!("unhandled wl_callback event {:?}", event),
+                            }
+                        });
+                        // The frame callback only takes effect once the surface is committed.
+                        wl_surface
+                            .inner
+                            .schedule_deferred_task(surface::DeferredTask::Commit);
                     }
                     _ => tracing::warn!("unhandled xdg_surface event {:?}", event),
                 }
@@ -62,6 +332,10 @@ impl Surface {
 
         xdg_toplevel.quick_assign({
             let wl_surface = wl_surface.clone();
+            let window_state = window_state.clone();
+            let pending = pending.clone();
+            let preferred = preferred.clone();
+            let floating = floating.clone();
             move |_xdg_toplevel, event, a3| match event {
                 xdg_toplevel::Event::Configure {
                     width,
@@ -79,20 +353,55 @@ impl Surface {
                     // If the width or height arguments are zero, it means the client should decide its own window dimension.
                     // This may happen when the compositor needs to configure the state of the surface
                     // but doesn't have any information about any previous or expected dimension.
-                    let (width, height) = if width == 0 || height == 0 {
-                        (size.width, size.height)
+                    let requested = if width == 0 || height == 0 {
+                        kurbo::Size::ZERO
                     } else {
-                        (width as f64, height as f64)
+                        kurbo::Size::new(width as f64, height as f64)
                     };
+                    pending.set(Some(requested));
 
-                    let dim =
-                        kurbo::Size::new(width.max(min_size.width), height.max(min_size.height));
+                    // `states` is an array of native endian u32s. Wayland has no way to observe
+                    // that a window is minimized, so anything other than fullscreen or maximized
+                    // is restored.
+                    let states: Vec<_> = states
+                        .chunks_exact(4)
+                        .map(|raw| u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]))
+                        .filter_map(xdg_toplevel::State::from_raw)
+                        .collect();
 
-                    wl_surface.update_dimensions(dim);
+                    // The size the application asked for lasts until the user resizes the window.
+                    resizing.set(states.contains(&xdg_toplevel::State::Resizing));
+                    if resizing.get() {
+                        preferred.set(None);
+                    }
+                    floating.set(!states.iter().any(|state| {
+                        matches!(
+                            state,
+                            xdg_toplevel::State::Maximized
+                                | xdg_toplevel::State::Fullscreen
+                                | xdg_toplevel::State::TiledLeft
+                                | xdg_toplevel::State::TiledRight
+                                | xdg_toplevel::State::TiledTop
+                                | xdg_toplevel::State::TiledBottom
+                        )
+                    }));
+
+                    wl_surface
+                        .inner
+                        .set_activated(states.contains(&xdg_toplevel::State::Activated));
+                    window_state.set(if states.contains(&xdg_toplevel::State::Fullscreen) {
+                        window::WindowState::Fullscreen
+                    } else if states.contains(&xdg_toplevel::State::Maximized) {
+                        window::WindowState::Maximized
+                    } else {
+                        window::WindowState::Restored
+                    });
                 }
                 xdg_toplevel::Event::Close => {
                     tracing::info!("xdg close event {:?}", event);
-                    wl_surface.inner.handler.borrow_mut().request_close();
+                    wl_surface
+                        .inner
+                        .with_handler(|handler| handler.request_close());
                 }
                 _ => tracing::info!("unimplemented event {:?}", event),
             }
@@ -102,19 +411,27 @@ impl Surface {
             wl_surface,
             xdg_toplevel,
             xdg_surface,
+            title,
+            window_state,
+            decoration,
+            frame,
+            limits,
+            preferred,
+            floating,
+            layout,
         };
 
         inner
-            .xdg_toplevel
-            .set_min_size(min_size.width as i32, min_size.height as i32);
+            .limits
+            .get()
+            .send(&inner.xdg_toplevel, kurbo::Insets::ZERO);
         inner.xdg_toplevel.set_maximized();
 
-        let handle = Self {
+        // The caller is responsible for the initial commit, once it has finished configuring the
+        // toplevel.
+        Self {
             inner: std::sync::Arc::new(inner),
-        };
-
-        handle.commit();
-        handle
+        }
     }
 
     pub(crate) fn with_handler<T, F: FnOnce(&mut dyn window::WinHandler) -> T>(
@@ -124,8 +441,111 @@ impl Surface {
         std::sync::Arc::<surface::Data>::from(self).with_handler(f)
     }
 
-    pub(crate) fn commit(&self) {
-        self.inner.wl_surface.commit();
+    /// The space the decorations we draw take up around the window.
+    fn frame_insets(&self) -> kurbo::Insets {
+        self.frame()
+            .map_or(kurbo::Insets::ZERO, |frame| frame.insets())
+    }
+
+    fn set_limits(&self, limits: Limits) {
+        self.inner.limits.set(limits);
+        limits.send(&self.inner.xdg_toplevel, self.frame_insets());
+        // Like the rest of the toplevel state, the limits apply on the next commit.
+        self.inner
+            .wl_surface
+            .inner
+            .schedule_deferred_task(surface::DeferredTask::Commit);
+    }
+
+    /// Make this window a child of `parent`, which the compositor keeps it above. Dialogs use
+    /// this, and compositors may decorate them as such.
+    pub(crate) fn set_parent(&self, parent: &xdg_toplevel::XdgToplevel) {
+        self.inner.xdg_toplevel.set_parent(Some(parent));
+        self.inner
+            .wl_surface
+            .inner
+            .schedule_deferred_task(surface::DeferredTask::Commit);
+    }
+}
+
+impl Handle for Surface {
+    fn get_size(&self) -> kurbo::Size {
+        self.inner.wl_surface.get_size()
+    }
+
+    /// Ask for a new size of the content, which leaves maximized and fullscreen.
+    fn set_size(&self, dim: kurbo::Size) {
+        self.inner.preferred.set(Some(dim));
+        match self.inner.window_state.get() {
+            // The compositor sends a configure once it has left the state, which picks up
+            // the new size.
+            window::WindowState::Maximized => self.inner.xdg_toplevel.unset_maximized(),
+            window::WindowState::Fullscreen => self.inner.xdg_toplevel.unset_fullscreen(),
+            // The first configure picks up the size, and tiled windows keep the compositor's.
+            _ if !self.inner.wl_surface.inner.configured.get() || !self.inner.floating.get() => {}
+            _ => {
+                (self.inner.layout)(&self.inner.xdg_surface, Some(kurbo::Size::ZERO));
+                self.inner.wl_surface.request_paint();
+            }
+        }
+    }
+
+    fn get_position(&self) -> kurbo::Point {
+        self.inner.wl_surface.get_position()
+    }
+
+    fn set_position(&self, position: kurbo::Point) {
+        self.inner.wl_surface.set_position(position);
+    }
+
+    fn request_anim_frame(&self) {
+        self.inner.wl_surface.request_anim_frame()
+    }
+
+    fn invalidate(&self) {
+        self.inner.wl_surface.invalidate()
+    }
+
+    fn invalidate_rect(&self, rect: kurbo::Rect) {
+        self.inner.wl_surface.invalidate_rect(rect)
+    }
+
+    fn remove_text_field(&self, token: crate::TextFieldToken) {
+        self.inner.wl_surface.remove_text_field(token)
+    }
+
+    fn set_focused_text_field(&self, active_field: Option<crate::TextFieldToken>) {
+        self.inner.wl_surface.set_focused_text_field(active_field)
+    }
+
+    fn get_idle_handle(&self) -> super::idle::Handle {
+        self.inner.wl_surface.get_idle_handle()
+    }
+
+    fn get_scale(&self) -> crate::Scale {
+        self.inner.wl_surface.get_scale()
+    }
+
+    fn run_idle(&self) {
+        self.inner.wl_surface.run_idle();
+    }
+
+    fn release(&self) {
+        // Everything attached to the toplevel has to go before it, and the roles before the
+        // surface. Destroying an object twice is harmless, so `Inner::drop` can do this again.
+        if let Some(frame) = self.inner.frame.take() {
+            frame.destroy();
+        }
+        if let Some(decoration) = &self.inner.decoration {
+            decoration.destroy();
+        }
+        self.inner.xdg_toplevel.destroy();
+        self.inner.xdg_surface.destroy();
+        self.inner.wl_surface.release()
+    }
+
+    fn data(&self) -> Option<std::sync::Arc<surface::Data>> {
+        self.inner.wl_surface.data()
     }
 }
 
@@ -142,7 +562,99 @@ impl Popup for Surface {
 
 impl Decor for Surface {
     fn inner_set_title(&self, title: String) {
-        self.inner.xdg_toplevel.set_title(title);
+        if *self.inner.title.borrow() == title {
+            return;
+        }
+        self.inner.xdg_toplevel.set_title(title.clone());
+        if let Some(frame) = self.frame() {
+            frame.set_title(&title);
+        }
+        self.inner.title.replace(title);
+        // Some compositors only pick up the new title on the next commit.
+        self.inner
+            .wl_surface
+            .inner
+            .schedule_deferred_task(surface::DeferredTask::Commit);
+    }
+
+    fn set_app_id(&self, app_id: String) {
+        self.inner.xdg_toplevel.set_app_id(app_id);
+        self.inner
+            .wl_surface
+            .inner
+            .schedule_deferred_task(surface::DeferredTask::Commit);
+    }
+
+    fn window_state(&self) -> window::WindowState {
+        self.inner.window_state.get()
+    }
+
+    fn begin_move_drag(&self, seat: &wlc::protocol::wl_seat::WlSeat, serial: u32) {
+        self.inner.xdg_toplevel._move(seat, serial);
+    }
+
+    fn begin_resize_drag(
+        &self,
+        seat: &wlc::protocol::wl_seat::WlSeat,
+        serial: u32,
+        edge: window::ResizeEdge,
+    ) {
+        if !self.inner.limits.get().resizable() {
+            tracing::debug!("not resizing a window which isn't resizable");
+            return;
+        }
+        let edge = match edge {
+            window::ResizeEdge::Top => xdg_toplevel::ResizeEdge::Top,
+            window::ResizeEdge::Bottom => xdg_toplevel::ResizeEdge::Bottom,
+            window::ResizeEdge::Left => xdg_toplevel::ResizeEdge::Left,
+            window::ResizeEdge::Right => xdg_toplevel::ResizeEdge::Right,
+            window::ResizeEdge::TopLeft => xdg_toplevel::ResizeEdge::TopLeft,
+            window::ResizeEdge::TopRight => xdg_toplevel::ResizeEdge::TopRight,
+            window::ResizeEdge::BottomLeft => xdg_toplevel::ResizeEdge::BottomLeft,
+            window::ResizeEdge::BottomRight => xdg_toplevel::ResizeEdge::BottomRight,
+        };
+        self.inner.xdg_toplevel.resize(seat, serial, edge);
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool, output: Option<wlc::protocol::wl_output::WlOutput>) {
+        match fullscreen {
+            true => self.inner.xdg_toplevel.set_fullscreen(output.as_ref()),
+            false => self.inner.xdg_toplevel.unset_fullscreen(),
+        }
+    }
+
+    fn set_resizable(&self, resizable: bool) {
+        let limits = self.inner.limits.get();
+        if limits.resizable() == resizable {
+            return;
+        }
+        // The window keeps the size it has now.
+        let fixed = match resizable {
+            true => None,
+            false => Some(self.inner.wl_surface.inner.logical_size.get()),
+        };
+        self.set_limits(Limits { fixed, ..limits });
+        if let Some(frame) = self.frame() {
+            frame.set_resizable(resizable);
+        }
+    }
+
+    fn set_min_size(&self, min: kurbo::Size) {
+        let limits = self.inner.limits.get();
+        self.set_limits(Limits { min, ..limits });
+    }
+
+    fn set_max_size(&self, max: Option<kurbo::Size>) {
+        let limits = self.inner.limits.get();
+        self.set_limits(Limits { max, ..limits });
+    }
+
+    fn frame(&self) -> Option<decorations::Frame> {
+        self.inner.frame.borrow().clone()
+    }
+
+    fn xdg_toplevel(&self) -> Option<wlc::Main<xdg_toplevel::XdgToplevel>> {
+        Some(self.inner.xdg_toplevel.clone())
     }
 }
 
@@ -160,7 +672,7 @@ impl From<Surface> for std::sync::Arc<surface::Data> {
 
 impl From<Surface> for Box<dyn Handle> {
     fn from(s: Surface) -> Box<dyn Handle> {
-        Box::new(s.inner.wl_surface.clone()) as Box<dyn Handle>
+        Box::new(s) as Box<dyn Handle>
     }
 }
 
@@ -181,3 +693,40 @@ impl From<Surface> for Box<dyn Popup> {
         Box::new(s) as Box<dyn Popup>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        min: kurbo::Size::new(100., 100.),
+        max: None,
+        fixed: None,
+    };
+    const INITIAL: kurbo::Size = kurbo::Size::new(800., 600.);
+
+    #[test]
+    fn zero_configure_keeps_our_size() {
+        let asked = kurbo::Size::new(640., 480.);
+        let dim = LIMITS.configured(kurbo::Size::ZERO, true, None, INITIAL);
+        assert_eq!(dim, INITIAL);
+        let dim = LIMITS.configured(kurbo::Size::ZERO, true, Some(asked), INITIAL);
+        assert_eq!(dim, asked);
+        // Our size still has to fit the limits.
+        let dim = LIMITS.configured(kurbo::Size::ZERO, true, Some(kurbo::Size::ZERO), INITIAL);
+        assert_eq!(dim, LIMITS.min);
+    }
+
+    #[test]
+    fn fixed_configure_is_obeyed() {
+        let asked = kurbo::Size::new(640., 480.);
+        let given = kurbo::Size::new(1920., 1080.);
+        // Maximized or tiled, even below the minimum.
+        assert_eq!(LIMITS.configured(given, false, Some(asked), INITIAL), given);
+        let small = kurbo::Size::new(50., 50.);
+        assert_eq!(LIMITS.configured(small, false, None, INITIAL), small);
+        // Floating, where it's only a suggestion.
+        assert_eq!(LIMITS.configured(given, true, Some(asked), INITIAL), asked);
+        assert_eq!(LIMITS.configured(given, true, None, INITIAL), given);
+    }
+}
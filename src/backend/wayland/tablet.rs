@@ -0,0 +1,229 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Graphics tablet (stylus) input.
+use wayland_client as wl;
+use wayland_protocols::unstable::tablet::v2::client::{
+    zwp_tablet_manager_v2::ZwpTabletManagerV2, zwp_tablet_pad_group_v2, zwp_tablet_pad_v2,
+    zwp_tablet_seat_v2, zwp_tablet_tool_v2, zwp_tablet_v2,
+};
+
+use crate::kurbo::Point;
+use crate::pointer::{
+    Angle, PenInclination, PenInfo, PointerButton, PointerButtons, PointerEvent, PointerId,
+    PointerType,
+};
+
+use super::application::Data;
+use super::seat::Seat;
+
+/// Keeps tool ids apart from those of touches and the mouse.
+const TOOL_ID: u64 = 1 << 63;
+
+// Buttons on the side of the stylus, from linux/input-event-codes.h.
+const BTN_STYLUS: u32 = 0x14b;
+const BTN_STYLUS2: u32 = 0x14c;
+const BTN_STYLUS3: u32 = 0x149;
+
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Move,
+    Down(PointerButton),
+    Up(PointerButton),
+    Leave,
+}
+
+/// A stylus (or eraser, ...) of a tablet.
+#[derive(Debug, Default)]
+struct Tool {
+    eraser: bool,
+    /// The window the tool is in proximity of.
+    window: Option<u64>,
+    pos: Point,
+    buttons: PointerButtons,
+    /// In the range zero to one.
+    pressure: f64,
+    /// In degrees.
+    tilt: (f64, f64),
+    /// In degrees.
+    rotation: f64,
+    /// What happened in the current frame.
+    changes: Vec<Change>,
+}
+
+/// Listen to the tablets of the seat with the given registry `id`, if the compositor supports
+/// them.
+pub(super) fn attach(
+    appdata: &std::sync::Arc<Data>,
+    manager: &wl::Main<ZwpTabletManagerV2>,
+    id: u32,
+    seat: &mut Seat,
+) {
+    if seat.tablet_seat.is_some() {
+        return;
+    }
+    let tablet_seat = manager.get_tablet_seat(&seat.wl_seat);
+    tablet_seat.quick_assign({
+        let appdata = appdata.clone();
+        let seat = id;
+        move |_, event, _| match event {
+            zwp_tablet_seat_v2::Event::ToolAdded { id } => {
+                let mut tool = Tool::default();
+                let appdata = appdata.clone();
+                id.quick_assign(move |wobj, event, _| {
+                    consume(&appdata, seat, &wobj, &mut tool, event)
+                });
+            }
+            zwp_tablet_seat_v2::Event::TabletAdded { id } => {
+                // We only care about the tools.
+                id.quick_assign(|wobj, event, _| {
+                    if let zwp_tablet_v2::Event::Removed = event {
+                        wobj.destroy();
+                    }
+                });
+            }
+            zwp_tablet_seat_v2::Event::PadAdded { id } => {
+                // Pads (the buttons, rings and strips on the tablet) aren't supported yet.
+                id.quick_assign(|wobj, event, _| match event {
+                    zwp_tablet_pad_v2::Event::Group { pad_group } => {
+                        pad_group.quick_assign(|_, event, _| match event {
+                            zwp_tablet_pad_group_v2::Event::Ring { ring } => {
+                                ring.quick_assign(|_, _, _| {});
+                            }
+                            zwp_tablet_pad_group_v2::Event::Strip { strip } => {
+                                strip.quick_assign(|_, _, _| {});
+                            }
+                            _ => {}
+                        });
+                    }
+                    zwp_tablet_pad_v2::Event::Removed => wobj.destroy(),
+                    _ => {}
+                });
+            }
+            _ => tracing::warn!("unhandled tablet seat event {:?}", event),
+        }
+    });
+    seat.tablet_seat = Some(tablet_seat);
+}
+
+fn consume(
+    appdata: &Data,
+    seat: u32,
+    wobj: &wl::Main<zwp_tablet_tool_v2::ZwpTabletToolV2>,
+    tool: &mut Tool,
+    event: zwp_tablet_tool_v2::Event,
+) {
+    use zwp_tablet_tool_v2::Event;
+    match event {
+        Event::Type { tool_type } => {
+            tool.eraser = tool_type == zwp_tablet_tool_v2::Type::Eraser;
+        }
+        Event::ProximityIn { surface, .. } => match appdata.find_window(&surface) {
+            Some(window) => {
+                tool.window = Some(window.id());
+                tool.changes.push(Change::Move);
+            }
+            None => tracing::debug!("tablet tool over an unknown surface {:?}", surface),
+        },
+        Event::ProximityOut => tool.changes.push(Change::Leave),
+        Event::Down { .. } => tool.changes.push(Change::Down(PointerButton::Left)),
+        Event::Up => tool.changes.push(Change::Up(PointerButton::Left)),
+        Event::Button { button, state, .. } => {
+            let button = match button {
+                BTN_STYLUS => PointerButton::Middle,
+                BTN_STYLUS2 => PointerButton::Right,
+                BTN_STYLUS3 => PointerButton::X1,
+                _ => return,
+            };
+            tool.changes.push(match state {
+                zwp_tablet_tool_v2::ButtonState::Pressed => Change::Down(button),
+                _ => Change::Up(button),
+            });
+        }
+        Event::Motion { x, y } => {
+            tool.pos = Point::new(x, y);
+            tool.changes.push(Change::Move);
+        }
+        Event::Pressure { pressure } => tool.pressure = pressure as f64 / 65535.,
+        Event::Tilt { tilt_x, tilt_y } => tool.tilt = (tilt_x, tilt_y),
+        Event::Rotation { degrees } => tool.rotation = degrees,
+        Event::Frame { .. } => dispatch(appdata, seat, wobj.as_ref().id(), tool),
+        Event::Removed => wobj.destroy(),
+        _ => {}
+    }
+}
+
+fn dispatch(appdata: &Data, seat: u32, id: u32, tool: &mut Tool) {
+    let changes = std::mem::take(&mut tool.changes);
+    let window = match tool.window {
+        Some(window) => window,
+        None => return,
+    };
+    if matches!(changes.last(), Some(Change::Leave)) {
+        tool.window = None;
+    }
+    let data = match appdata.handles.borrow().get(&window).and_then(|w| w.data()) {
+        Some(data) => data,
+        None => return,
+    };
+
+    let info = PenInfo {
+        pressure: tool.pressure,
+        inclination: PenInclination::from_tilt(tool.tilt.0, tool.tilt.1).unwrap_or_default(),
+        twist: Angle::degrees(tool.rotation.rem_euclid(360.)),
+        ..PenInfo::default()
+    };
+    let mods = appdata.keyboard.modifiers(seat);
+    // A frame with several motion events only needs to report the final position once.
+    let mut moved = false;
+    for change in changes {
+        let button = match change {
+            Change::Down(button) => {
+                tool.buttons.insert(button);
+                button
+            }
+            Change::Up(button) => {
+                tool.buttons.remove(button);
+                button
+            }
+            Change::Move if moved => continue,
+            Change::Move => {
+                moved = true;
+                PointerButton::None
+            }
+            Change::Leave => PointerButton::None,
+        };
+        let event = PointerEvent {
+            pointer_id: PointerId(TOOL_ID | id as u64),
+            is_primary: true,
+            pointer_type: match tool.eraser {
+                true => PointerType::Eraser(info.clone()),
+                false => PointerType::Pen(info.clone()),
+            },
+            // Surface coordinates are already in display points.
+            pos: tool.pos,
+            buttons: tool.buttons,
+            modifiers: mods,
+            button,
+            focus: false,
+            count: 0,
+        };
+        data.with_handler(|handler| match change {
+            Change::Move => handler.pointer_move(&event),
+            Change::Down(_) => handler.pointer_down(&event),
+            Change::Up(_) => handler.pointer_up(&event),
+            Change::Leave => handler.pointer_leave(),
+        });
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Input method (IME) support through the text-input protocol.
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wayland_client as wl;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    self, ZwpTextInputV3,
+};
+
+use crate::kurbo::Rect;
+use crate::text::{Affinity, Selection};
+use crate::window::WinHandler;
+use crate::TextFieldToken;
+
+use super::application::Data;
+use super::seat::Seat;
+use super::surfaces::surface;
+
+/// Changes from the input method, which are applied together once they're done.
+#[derive(Debug, Default)]
+struct Pending {
+    /// The text being composed, and the cursor within it.
+    preedit: Option<(String, i32, i32)>,
+    /// Text to insert.
+    commit: Option<String>,
+    /// How many bytes to delete before and after the cursor.
+    delete: Option<(u32, u32)>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    wobj: wl::Main<ZwpTextInputV3>,
+    /// The window which has the keyboard focus of the seat.
+    window: Cell<Option<u64>>,
+    /// The text field the input method is enabled for.
+    field: Cell<Option<TextFieldToken>>,
+    pending: RefCell<Pending>,
+}
+
+/// The text input of a seat.
+#[derive(Debug, Clone)]
+pub(crate) struct TextInput {
+    inner: Rc<Inner>,
+}
+
+impl TextInput {
+    /// Give the seat a text input, if the compositor supports them.
+    pub(super) fn attach(
+        appdata: &std::sync::Arc<Data>,
+        manager: &wl::Main<ZwpTextInputManagerV3>,
+        seat: &mut Seat,
+    ) {
+        if seat.text_input.is_some() {
+            return;
+        }
+        let input = TextInput {
+            inner: Rc::new(Inner {
+                wobj: manager.get_text_input(&seat.wl_seat),
+                window: Cell::new(None),
+                field: Cell::new(None),
+                pending: Default::default(),
+            }),
+        };
+        input.inner.wobj.quick_assign({
+            let input = input.clone();
+            let appdata = appdata.clone();
+            move |_, event, _| input.consume(&appdata, event)
+        });
+        seat.text_input = Some(input);
+    }
+
+    pub(super) fn destroy(&self) {
+        self.inner.wobj.destroy();
+    }
+
+    fn consume(&self, appdata: &Data, event: zwp_text_input_v3::Event) {
+        match event {
+            zwp_text_input_v3::Event::Enter { surface } => {
+                let window = match appdata.find_window(&surface) {
+                    Some(w) => w,
+                    None => return,
+                };
+                let data = match window.data() {
+                    Some(data) => data,
+                    None => return,
+                };
+                self.inner.window.set(Some(window.id()));
+                data.text_inputs.borrow_mut().push(self.clone());
+                self.update(&data);
+            }
+            zwp_text_input_v3::Event::Leave { .. } => {
+                let data = match self.window_data(appdata) {
+                    Some(data) => data,
+                    None => return,
+                };
+                data.text_inputs
+                    .borrow_mut()
+                    .retain(|input| !Rc::ptr_eq(&input.inner, &self.inner));
+                // Anything still being composed is abandoned.
+                self.inner.pending.take();
+                if let Some(token) = self.inner.field.take() {
+                    data.with_handler(|handler| apply(handler, token, Pending::default()));
+                }
+                self.inner.window.set(None);
+            }
+            zwp_text_input_v3::Event::PreeditString {
+                text,
+                cursor_begin,
+                cursor_end,
+            } => {
+                self.inner.pending.borrow_mut().preedit =
+                    text.map(|text| (text, cursor_begin, cursor_end));
+            }
+            zwp_text_input_v3::Event::CommitString { text } => {
+                self.inner.pending.borrow_mut().commit = text;
+            }
+            zwp_text_input_v3::Event::DeleteSurroundingText {
+                before_length,
+                after_length,
+            } => {
+                self.inner.pending.borrow_mut().delete = Some((before_length, after_length));
+            }
+            zwp_text_input_v3::Event::Done { .. } => {
+                let pending = self.inner.pending.take();
+                let token = match self.inner.field.get() {
+                    Some(token) => token,
+                    None => return,
+                };
+                if let Some(data) = self.window_data(appdata) {
+                    data.with_handler(|handler| apply(handler, token, pending));
+                }
+            }
+            _ => tracing::warn!("unhandled text input event {:?}", event),
+        }
+    }
+
+    fn window_data(&self, appdata: &Data) -> Option<std::sync::Arc<surface::Data>> {
+        let window = appdata
+            .handles
+            .borrow()
+            .get(&self.inner.window.get()?)
+            .cloned()?;
+        window.data()
+    }
+
+    /// Tell the input method about the focused text field of the window.
+    pub(crate) fn update(&self, data: &surface::Data) {
+        let wobj = &self.inner.wobj;
+        let field = data.active_text_input.get();
+        if self.inner.field.replace(field) != field {
+            match field {
+                // Enabling again resets the input method for the new field.
+                Some(_) => {
+                    wobj.enable();
+                    wobj.set_content_type(
+                        zwp_text_input_v3::ContentHint::None,
+                        zwp_text_input_v3::ContentPurpose::Normal,
+                    );
+                }
+                None => wobj.disable(),
+            }
+        }
+
+        if let Some(token) = field {
+            if let Some(Some(rect)) = data.with_handler(|handler| cursor_rect(handler, token)) {
+                let rect = rect.expand();
+                wobj.set_cursor_rectangle(
+                    rect.x0 as i32,
+                    rect.y0 as i32,
+                    rect.width() as i32,
+                    rect.height() as i32,
+                );
+            }
+        }
+        wobj.commit();
+    }
+}
+
+/// Where the cursor of the text field is, so the input method can show its popup next to it.
+fn cursor_rect(handler: &mut dyn WinHandler, token: TextFieldToken) -> Option<Rect> {
+    let doc = handler.acquire_input_lock(token, false);
+    let active = doc.selection().active;
+    let line = doc.line_range(active, Affinity::Downstream);
+    let rect = doc
+        .slice_bounding_box(active..active.min(line.end))
+        .or_else(|| doc.bounding_box());
+    handler.release_input_lock(token);
+    rect
+}
+
+/// Apply the changes from the input method to the text field, in the order the protocol asks for.
+fn apply(handler: &mut dyn WinHandler, token: TextFieldToken, pending: Pending) {
+    let mut doc = handler.acquire_input_lock(token, true);
+
+    // Remove the text which was being composed.
+    if let Some(range) = doc.composition_range() {
+        doc.replace_range(range.clone(), "");
+        doc.set_composition_range(None);
+        doc.set_selection(Selection::caret(range.start));
+    }
+
+    // Delete around the selection, after first so the start stays put.
+    if let Some((before, after)) = pending.delete {
+        let selection = doc.selection();
+        let range = selection.range();
+        let end = (range.end + after as usize).min(doc.len());
+        if doc.is_char_boundary(end) {
+            doc.replace_range(range.end..end, "");
+        }
+        let start = range.start.saturating_sub(before as usize);
+        let mut removed = 0;
+        if doc.is_char_boundary(start) {
+            doc.replace_range(start..range.start, "");
+            removed = range.start - start;
+        }
+        // Replacing next to the selection collapses it, so put it back, moved by what went
+        // before it.
+        doc.set_selection(Selection::new(
+            selection.anchor - removed,
+            selection.active - removed,
+        ));
+    }
+
+    if let Some(text) = pending.commit {
+        let selection = doc.selection().range();
+        doc.replace_range(selection.clone(), &text);
+        doc.set_selection(Selection::caret(selection.start + text.len()));
+    }
+
+    if let Some((text, begin, end)) = pending.preedit.filter(|(text, ..)| !text.is_empty()) {
+        let selection = doc.selection().range();
+        let composition = selection.start..selection.start + text.len();
+        doc.replace_range(selection, &text);
+        doc.set_composition_range(Some(composition.clone()));
+        // A negative cursor means the input method wants it hidden, we put it at the end.
+        let cursor = match (usize::try_from(begin), usize::try_from(end)) {
+            (Ok(begin), Ok(end)) if begin <= text.len() && end <= text.len() => {
+                Selection::new(composition.start + begin, composition.start + end)
+            }
+            _ => Selection::caret(composition.end),
+        };
+        doc.set_selection(cursor);
+    }
+
+    handler.release_input_lock(token);
+}
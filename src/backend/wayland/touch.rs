@@ -0,0 +1,178 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Touchscreen input.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use wayland_client as wl;
+use wayland_client::protocol::wl_touch;
+
+use crate::kurbo::{Point, Size};
+use crate::pointer::{
+    PointerButton, PointerButtons, PointerEvent, PointerId, PointerType, TouchInfo,
+};
+
+use super::application::Data;
+
+/// A finger on the screen.
+#[derive(Debug, Clone)]
+struct TouchPoint {
+    window: u64,
+    /// In the window's display points, which are the same as surface coordinates on wayland.
+    pos: Point,
+    contact_geometry: Size,
+    is_primary: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Down,
+    Move,
+    Up,
+    Cancel,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    points: BTreeMap<i32, TouchPoint>,
+    /// Changes to the touch points, sent to the windows at the end of the frame.
+    queue: Vec<(i32, Phase)>,
+}
+
+/// Listen to the touch events of the seat with the given registry `seat` id.
+pub(super) fn attach(
+    appdata: &std::sync::Arc<Data>,
+    seat: u32,
+    touch: &wl::Main<wl_touch::WlTouch>,
+) {
+    let state = Rc::new(RefCell::new(State::default()));
+    touch.quick_assign({
+        let appdata = appdata.clone();
+        move |_, event, _| consume(&appdata, seat, &mut state.borrow_mut(), event)
+    });
+}
+
+fn consume(appdata: &Data, seat: u32, state: &mut State, event: wl_touch::Event) {
+    match event {
+        wl_touch::Event::Down {
+            surface, id, x, y, ..
+        } => {
+            let window = match appdata.find_window(&surface) {
+                Some(w) => w,
+                None => {
+                    tracing::debug!("touch on an unknown surface {:?}", surface);
+                    return;
+                }
+            };
+            // Like on the web, the first finger down is the primary one.
+            let is_primary = state.points.values().all(|point| !point.is_primary);
+            state.points.insert(
+                id,
+                TouchPoint {
+                    window: window.id(),
+                    pos: Point::new(x, y),
+                    contact_geometry: Size::new(1., 1.),
+                    is_primary,
+                },
+            );
+            state.queue.push((id, Phase::Down));
+        }
+        wl_touch::Event::Motion { id, x, y, .. } => {
+            if let Some(point) = state.points.get_mut(&id) {
+                point.pos = Point::new(x, y);
+                if !state.queue.contains(&(id, Phase::Move)) {
+                    state.queue.push((id, Phase::Move));
+                }
+            }
+        }
+        wl_touch::Event::Shape { id, major, minor } => {
+            if let Some(point) = state.points.get_mut(&id) {
+                point.contact_geometry = Size::new(major, minor);
+            }
+        }
+        wl_touch::Event::Up { id, .. } => {
+            if state.points.contains_key(&id) {
+                state.queue.push((id, Phase::Up));
+            }
+        }
+        wl_touch::Event::Frame => dispatch(appdata, seat, state),
+        wl_touch::Event::Cancel => {
+            // The compositor took over all the touches, e.g. for a gesture.
+            state.queue.clear();
+            let ids: Vec<i32> = state.points.keys().copied().collect();
+            state
+                .queue
+                .extend(ids.into_iter().map(|id| (id, Phase::Cancel)));
+            dispatch(appdata, seat, state);
+        }
+        wl_touch::Event::Orientation { .. } => {}
+        _ => tracing::warn!("unhandled touch event {:?}", event),
+    }
+}
+
+fn dispatch(appdata: &Data, seat: u32, state: &mut State) {
+    let mods = appdata.keyboard.modifiers(seat);
+    for (id, phase) in std::mem::take(&mut state.queue) {
+        let point = match phase {
+            Phase::Up | Phase::Cancel => state.points.remove(&id),
+            Phase::Down | Phase::Move => state.points.get(&id).cloned(),
+        };
+        let point = match point {
+            Some(point) => point,
+            None => continue,
+        };
+        let window = match appdata.handles.borrow().get(&point.window).cloned() {
+            Some(w) => w,
+            None => continue,
+        };
+        let data = match window.data() {
+            Some(data) => data,
+            None => continue,
+        };
+
+        let (button, buttons) = match phase {
+            Phase::Down => (
+                PointerButton::Left,
+                PointerButtons::new().with(PointerButton::Left),
+            ),
+            Phase::Move => (
+                PointerButton::None,
+                PointerButtons::new().with(PointerButton::Left),
+            ),
+            Phase::Up | Phase::Cancel => (PointerButton::Left, PointerButtons::new()),
+        };
+        let event = PointerEvent {
+            pointer_id: PointerId((seat as u64) << 32 | id as u32 as u64),
+            is_primary: point.is_primary,
+            pointer_type: PointerType::Touch(TouchInfo {
+                contact_geometry: point.contact_geometry,
+                ..TouchInfo::default()
+            }),
+            pos: point.pos,
+            buttons,
+            modifiers: mods,
+            button,
+            focus: false,
+            count: 0,
+        };
+        data.with_handler(|handler| match phase {
+            Phase::Down => handler.pointer_down(&event),
+            Phase::Move => handler.pointer_move(&event),
+            Phase::Up => handler.pointer_up(&event),
+            Phase::Cancel => handler.pointer_cancel(&event),
+        });
+    }
+}
@@ -14,6 +14,8 @@
 #![allow(clippy::single_match)]
 
 use tracing;
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_protocols::staging::xdg_activation::v1::client::xdg_activation_token_v1;
 use wayland_protocols::xdg_shell::client::xdg_popup;
 use wayland_protocols::xdg_shell::client::xdg_positioner;
 use wayland_protocols::xdg_shell::client::xdg_surface;
@@ -24,14 +26,18 @@ use raw_window_handle::{
 };
 
 use super::application::{self, Timer};
-use super::{error::Error, menu::Menu, outputs, surfaces};
+use super::{clipboard, context_menu, error::Error, menu::Menu, outputs, pointer_lock, surfaces};
 
 use crate::{
+    clipboard::ClipboardFormat,
     dialog::FileDialogOptions,
     error::Error as ShellError,
+    keyboard::KeyEvent,
     kurbo::{Insets, Point, Rect, Size},
     mouse::{Cursor, CursorDesc},
+    region::Region,
     scale::Scale,
+    screen::Monitor,
     text::Event,
     window::{self, FileDialogToken, TimerToken, WinHandler, WindowLevel},
     TextFieldToken,
@@ -47,6 +53,17 @@ struct Inner {
     pub(super) outputs: Box<dyn surfaces::Outputs>,
     pub(super) popup: Box<dyn surfaces::Popup>,
     pub(super) appdata: std::sync::Weak<application::Data>,
+    /// There's no menu bar on wayland, but the hotkeys of the menu still send its commands.
+    menu: std::cell::RefCell<Option<Menu>>,
+    /// Whether the handler said the pointer is over a titlebar it draws itself.
+    handle_titlebar: std::cell::Cell<bool>,
+    /// Whether the cursor is shown while it's over the window.
+    cursor_visible: std::cell::Cell<bool>,
+    /// The id of the window this one is a dialog of, which closes it when it closes.
+    parent: std::cell::Cell<Option<u64>>,
+    /// Publishes the accessibility tree over AT-SPI, if the accessibility bus is available.
+    #[cfg(feature = "accesskit")]
+    accesskit_adapter: std::cell::RefCell<Option<accesskit_unix::Adapter>>,
 }
 
 #[derive(Clone)]
@@ -90,6 +107,12 @@ impl WindowHandle {
                 surface: surface.into(),
                 popup: popup.into(),
                 appdata: appdata.into(),
+                menu: std::cell::RefCell::new(None),
+                handle_titlebar: std::cell::Cell::new(false),
+                cursor_visible: std::cell::Cell::new(true),
+                parent: std::cell::Cell::new(None),
+                #[cfg(feature = "accesskit")]
+                accesskit_adapter: std::cell::RefCell::new(None),
             }),
         }
     }
@@ -102,24 +125,65 @@ impl WindowHandle {
         tracing::debug!("show initiated");
     }
 
-    pub fn resizable(&self, _resizable: bool) {
-        tracing::warn!("resizable is unimplemented on wayland");
+    pub fn resizable(&self, resizable: bool) {
+        self.inner.decor.set_resizable(resizable);
     }
 
     pub fn show_titlebar(&self, _show_titlebar: bool) {
         tracing::warn!("show_titlebar is unimplemented on wayland");
     }
 
-    pub fn set_position(&self, _position: Point) {
-        tracing::warn!("set_position is unimplemented on wayland");
+    pub fn lock_pointer(&self) {
+        let appdata = match self.inner.appdata.upgrade() {
+            Some(appdata) => appdata,
+            None => return,
+        };
+        if let Some(data) = self.data() {
+            pointer_lock::lock(&appdata, self.id(), &data.wl_surface());
+        }
+    }
+
+    pub fn unlock_pointer(&self) {
+        if let Some(appdata) = self.inner.appdata.upgrade() {
+            appdata.pointer.unlock_window(self.id());
+        }
+    }
+
+    pub fn begin_resize_drag(&self, edge: window::ResizeEdge) {
+        let appdata = match self.inner.appdata.upgrade() {
+            Some(appdata) => appdata,
+            None => return,
+        };
+        // The press which started the drag is the most recent one.
+        let (seat, serial) = match appdata.pointer.button_press() {
+            Some(press) => press,
+            None => {
+                tracing::warn!("unable to resize the window without a button press");
+                return;
+            }
+        };
+        self.inner.decor.begin_resize_drag(&seat, serial, edge);
+    }
+
+    /// Move a popup relative to its parent. Wayland doesn't let other windows pick their
+    /// position, so this does nothing for them.
+    pub fn set_position(&self, position: Point) {
+        self.inner.surface.set_position(position);
     }
 
+    /// The position of a popup relative to its parent. Wayland doesn't tell other windows where
+    /// they are, so they're always at the origin.
     pub fn get_position(&self) -> Point {
-        tracing::warn!("get_position is unimplemented on wayland");
-        Point::ZERO
+        self.inner.surface.get_position()
+    }
+
+    /// The decorations we draw for the window, if any.
+    pub(super) fn frame(&self) -> Option<surfaces::decorations::Frame> {
+        self.inner.decor.frame()
     }
 
     pub fn content_insets(&self) -> Insets {
+        // Like on GTK, neither the window size nor the insets include the decorations we draw.
         Insets::from(0.)
     }
 
@@ -131,29 +195,107 @@ impl WindowHandle {
         self.inner.surface.get_size()
     }
 
+    pub fn set_fullscreen(&self, fullscreen: bool, monitor: Option<Monitor>) {
+        let appdata = match self.inner.appdata.upgrade() {
+            Some(appdata) => appdata,
+            None => return,
+        };
+        // Monitors are made from outputs, so they have the same logical rect.
+        let output = monitor.and_then(|monitor| {
+            let outputs = appdata.outputs.borrow();
+            let output = outputs
+                .values()
+                .find(|meta| meta.logical_rect() == monitor.virtual_rect())
+                .and_then(|meta| meta.output.clone());
+            if output.is_none() {
+                tracing::warn!("no output for {}, the compositor will pick one", monitor);
+            }
+            output
+        });
+        self.inner.decor.set_fullscreen(fullscreen, output);
+    }
+
+    pub fn set_min_size(&self, size: Size) {
+        self.inner.decor.set_min_size(size);
+    }
+
+    pub fn set_max_size(&self, size: Option<Size>) {
+        self.inner.decor.set_max_size(size);
+    }
+
     pub fn set_window_state(&mut self, _current_state: window::WindowState) {
         tracing::warn!("set_window_state is unimplemented on wayland");
     }
 
     pub fn get_window_state(&self) -> window::WindowState {
-        tracing::warn!("get_window_state is unimplemented on wayland");
-        window::WindowState::Maximized
+        self.inner.decor.window_state()
     }
 
-    pub fn handle_titlebar(&self, _val: bool) {
-        tracing::warn!("handle_titlebar is unimplemented on wayland");
+    pub fn handle_titlebar(&self, val: bool) {
+        self.inner.handle_titlebar.set(val);
+    }
+
+    /// Start moving the window, after a left press over a titlebar the handler draws itself.
+    pub(super) fn begin_move_drag(&self, seat: &WlSeat, serial: u32) {
+        if self.inner.handle_titlebar.replace(false) {
+            self.inner.decor.begin_move_drag(seat, serial);
+        }
+    }
+
+    pub fn start_drag(&self, formats: &[ClipboardFormat], icon: Option<window::DragIcon>) {
+        let appdata = match self.inner.appdata.upgrade() {
+            Some(appdata) => appdata,
+            None => return,
+        };
+        let data = match self.data() {
+            Some(data) => data,
+            None => return,
+        };
+        let contents = clipboard::Clipboard::contents(formats);
+        appdata.clipboard.start_drag(
+            &appdata,
+            self,
+            &data.wl_surface(),
+            data.scale.get(),
+            contents,
+            icon,
+        );
     }
 
     /// Close the window.
+    ///
+    /// This closes its dialogs first, destroys the wayland objects of the window, then tells the
+    /// handler. The event loop stops once the last window is closed.
     pub fn close(&self) {
         if let Some(appdata) = self.inner.appdata.upgrade() {
             tracing::trace!(
                 "closing window initiated {:?}",
                 appdata.active_surface_id.borrow()
             );
-            appdata.handles.borrow_mut().remove(&self.id());
+            if appdata.handles.borrow_mut().remove(&self.id()).is_none() {
+                tracing::error!("window {} was already closed", self.id());
+                return;
+            }
+            let children: Vec<_> = appdata
+                .handles
+                .borrow()
+                .values()
+                .filter(|handle| handle.inner.parent.get() == Some(self.id()))
+                .cloned()
+                .collect();
+            for child in children {
+                child.close();
+            }
             appdata.active_surface_id.borrow_mut().pop_front();
+            // A pointer lock on the surface, pending or not, must go before the surface does.
+            appdata.pointer.unlock_window(self.id());
+            #[cfg(feature = "accesskit")]
+            self.inner.accesskit_adapter.take();
+            let data = self.inner.surface.data();
             self.inner.surface.release();
+            if let Some(data) = data {
+                data.close();
+            }
             tracing::trace!(
                 "closing window completed {:?}",
                 appdata.active_surface_id.borrow()
@@ -162,8 +304,45 @@ impl WindowHandle {
     }
 
     /// Bring this window to the front of the window stack and give it focus.
+    ///
+    /// This needs the xdg-activation protocol, without it this only warns. Even with it, the
+    /// compositor decides whether to focus the window, and may just mark it as wanting attention.
     pub fn bring_to_front_and_focus(&self) {
-        tracing::warn!("unimplemented bring_to_front_and_focus initiated");
+        let appdata = match self.inner.appdata.upgrade() {
+            Some(appdata) => appdata,
+            None => return,
+        };
+        let activation = match &appdata.xdg_activation_v1 {
+            Some(activation) => activation.clone(),
+            None => {
+                tracing::warn!("bring_to_front_and_focus is not supported by the compositor");
+                return;
+            }
+        };
+        let surface = match self.data() {
+            Some(data) => data.wl_surface(),
+            None => return,
+        };
+
+        let token = activation.get_activation_token();
+        token.quick_assign(move |token, event, _| match event {
+            xdg_activation_token_v1::Event::Done { token: id } => {
+                activation.activate(id, &surface);
+                token.destroy();
+            }
+            _ => tracing::warn!("unhandled activation token event {:?}", event),
+        });
+        // Compositors are more willing to focus a window in response to input, so pass along the
+        // most recent click, if it was in one of our windows.
+        let focus = appdata
+            .pointer
+            .press_focus()
+            .and_then(|id| appdata.handles.borrow().get(&id).and_then(|w| w.data()));
+        if let (Some((seat, serial)), Some(focus)) = (appdata.pointer.button_press(), focus) {
+            token.set_serial(serial, &seat);
+            token.set_surface(&focus.wl_surface());
+        }
+        token.commit();
     }
 
     /// Request a new paint, but without invalidating anything.
@@ -194,8 +373,11 @@ impl WindowHandle {
         self.inner.surface.set_focused_text_field(active_field);
     }
 
-    pub fn update_text_field(&self, _token: TextFieldToken, _update: Event) {
-        // noop until we get a real text input implementation
+    pub fn update_text_field(&self, token: TextFieldToken, _update: Event) {
+        // The input method only needs to know where the cursor went, which we check every time.
+        if let Some(data) = self.inner.surface.data() {
+            data.update_text_field(token);
+        }
     }
 
     pub fn request_timer(&self, deadline: std::time::Instant) -> TimerToken {
@@ -238,6 +420,27 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.inner.cursor_visible.set(visible);
+        if let Some(appdata) = self.inner.appdata.upgrade() {
+            appdata.pointer.set_hidden(self.id(), !visible);
+        }
+    }
+
+    pub(super) fn cursor_visible(&self) -> bool {
+        self.inner.cursor_visible.get()
+    }
+
+    pub fn set_input_region(&self, region: Option<Region>) {
+        if let Some(data) = self.data() {
+            data.set_input_region(region);
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        matches!(self.data(), Some(data) if data.is_visible())
+    }
+
     pub fn make_cursor(&self, _desc: &CursorDesc) -> Option<Cursor> {
         tracing::warn!("unimplemented make_cursor initiated");
         None
@@ -263,18 +466,71 @@ impl WindowHandle {
         Ok(self.inner.surface.get_scale())
     }
 
-    pub fn set_menu(&self, _menu: Menu) {
-        tracing::warn!("set_menu not implement for wayland");
+    // TODO: export the menu over dbusmenu, for compositors and panels which show app menus.
+    pub fn set_menu(&self, menu: Menu) {
+        self.inner.menu.replace(Some(menu));
     }
 
-    pub fn show_context_menu(&self, _menu: Menu, _pos: Point) {
-        tracing::warn!("show_context_menu not implement for wayland");
+    /// The command of the window's menu item with `event` as its hotkey, if there is one.
+    pub(super) fn menu_command(&self, event: &KeyEvent) -> Option<u32> {
+        self.inner.menu.borrow().as_ref()?.command_for(event)
+    }
+
+    pub fn show_context_menu(&self, menu: Menu, pos: Point) {
+        let appdata = match self.inner.appdata.upgrade() {
+            Some(appdata) => appdata,
+            None => return,
+        };
+        // Without a grab the menu couldn't be dismissed by clicking elsewhere. The grab has to
+        // be on the seat of the click which opened the menu.
+        let grab = match appdata.pointer.button_press() {
+            Some(press) => press,
+            None => {
+                tracing::warn!("unable to show a context menu without a button press");
+                return;
+            }
+        };
+
+        let handler = context_menu::Handler::new(menu, self.clone());
+        let draw = handler.draw();
+        // Open to the bottom right of `pos`, and let the compositor flip or slide the menu when
+        // that would put it off screen.
+        let config = surfaces::popup::Config {
+            anchor_rect: (pos, Size::new(1., 1.)),
+            anchor: xdg_positioner::Anchor::BottomRight,
+            gravity: xdg_positioner::Gravity::BottomRight,
+            constraint_adjustment: xdg_positioner::ConstraintAdjustment::FlipX
+                | xdg_positioner::ConstraintAdjustment::FlipY
+                | xdg_positioner::ConstraintAdjustment::SlideX
+                | xdg_positioner::ConstraintAdjustment::SlideY,
+            grab: Some(grab),
+            ..surfaces::popup::Config::default()
+        }
+        .with_size(handler.size());
+
+        match popup::create(
+            self,
+            &config,
+            self.inner.appdata.clone(),
+            Some(Box::new(handler)),
+        ) {
+            Ok(handle) => {
+                if let Some(data) = handle.data() {
+                    data.draw.replace(Some(draw));
+                }
+            }
+            Err(cause) => tracing::error!("unable to show context menu {:?}", cause),
+        }
     }
 
     pub fn set_title(&self, title: impl Into<String>) {
         self.inner.decor.set_title(title);
     }
 
+    pub fn set_app_id(&self, app_id: &str) {
+        self.inner.decor.set_app_id(app_id.into());
+    }
+
     pub(super) fn run_idle(&self) {
         self.inner.surface.run_idle();
     }
@@ -286,9 +542,55 @@ impl WindowHandle {
     #[cfg(feature = "accesskit")]
     pub fn update_accesskit_if_active(
         &self,
-        _update_factory: impl FnOnce() -> accesskit::TreeUpdate,
+        update_factory: impl FnOnce() -> accesskit::TreeUpdate,
     ) {
-        // AccessKit doesn't yet support this backend.
+        if let Some(adapter) = &*self.inner.accesskit_adapter.borrow() {
+            adapter.update(update_factory());
+        }
+    }
+
+    /// Publish the handler's accessibility tree, which has to happen once it's connected.
+    #[cfg(feature = "accesskit")]
+    fn init_accesskit(&self, app_name: String) {
+        let data = match self.data() {
+            Some(data) => data,
+            None => return,
+        };
+        let initial_tree = match data.with_handler(|handler| handler.accesskit_tree()) {
+            Some(tree) => tree,
+            None => return,
+        };
+        let action_handler = Box::new(AccessKitActionHandler {
+            idle_handle: self.inner.surface.get_idle_handle(),
+        });
+        let adapter = accesskit_unix::Adapter::new(
+            app_name,
+            "glazier".into(),
+            env!("CARGO_PKG_VERSION").into(),
+            move || initial_tree,
+            action_handler,
+        );
+        if adapter.is_none() {
+            tracing::info!(
+                "the accessibility bus is unavailable, so screen readers won't see the window"
+            );
+        }
+        self.inner.accesskit_adapter.replace(adapter);
+    }
+}
+
+/// Sends the actions a screen reader requests to the handler, on the main thread.
+#[cfg(feature = "accesskit")]
+struct AccessKitActionHandler {
+    idle_handle: IdleHandle,
+}
+
+#[cfg(feature = "accesskit")]
+impl accesskit::ActionHandler for AccessKitActionHandler {
+    fn do_action(&self, request: accesskit::ActionRequest) {
+        self.idle_handle.add_idle_callback(move |handler| {
+            handler.accesskit_action(request);
+        });
     }
 }
 
@@ -300,6 +602,10 @@ impl PartialEq for WindowHandle {
 
 impl Eq for WindowHandle {}
 
+/// The cross platform `WindowHandle` is `Default`, so that handlers can hold one before they're
+/// connected to a window.
+///
+/// The default handle isn't backed by a wayland surface, so it warns and does nothing when used.
 impl Default for WindowHandle {
     fn default() -> WindowHandle {
         WindowHandle {
@@ -310,6 +616,12 @@ impl Default for WindowHandle {
                 surface: Box::<surfaces::surface::Dead>::default(),
                 popup: Box::<surfaces::surface::Dead>::default(),
                 appdata: std::sync::Weak::new(),
+                menu: std::cell::RefCell::new(None),
+                handle_titlebar: std::cell::Cell::new(false),
+                cursor_visible: std::cell::Cell::new(true),
+                parent: std::cell::Cell::new(None),
+                #[cfg(feature = "accesskit")]
+                accesskit_adapter: std::cell::RefCell::new(None),
             }),
         }
     }
@@ -317,35 +629,52 @@ impl Default for WindowHandle {
 
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
-        tracing::error!("HasRawWindowHandle trait not implemented for wasm.");
-        RawWindowHandle::Wayland(WaylandWindowHandle::empty())
+        let mut handle = WaylandWindowHandle::empty();
+        // Looked up each time, as the surface can be replaced, see `WinHandler::surface_ready`.
+        match self.data() {
+            Some(data) => handle.surface = data.wl_surface().as_ref().c_ptr() as *mut _,
+            None => tracing::error!("Failed to get the wayland surface, returning empty handle"),
+        }
+        RawWindowHandle::Wayland(handle)
     }
 }
 
 unsafe impl HasRawDisplayHandle for WindowHandle {
     fn raw_display_handle(&self) -> RawDisplayHandle {
-        tracing::error!("HasDisplayHandle trait not implemented for wayland.");
-        RawDisplayHandle::Wayland(WaylandDisplayHandle::empty())
+        match self.inner.appdata.upgrade() {
+            Some(appdata) => appdata.raw_display_handle(),
+            None => {
+                tracing::error!("Failed to get the wayland display, returning incomplete handle");
+                RawDisplayHandle::Wayland(WaylandDisplayHandle::empty())
+            }
+        }
     }
 }
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct CustomCursor;
 
+/// The size of a window which didn't ask for one, in display points.
+const DEFAULT_SIZE: Size = Size::new(800., 600.);
+
 /// Builder abstraction for creating new windows
 pub(crate) struct WindowBuilder {
     appdata: std::sync::Weak<application::Data>,
     handler: Option<Box<dyn WinHandler>>,
     title: String,
+    app_id: Option<String>,
     menu: Option<Menu>,
     position: Option<Point>,
     level: WindowLevel,
+    parent: Option<WindowHandle>,
     state: Option<window::WindowState>,
     // pre-scaled
     size: Size,
     min_size: Option<Size>,
+    max_size: Option<Size>,
     resizable: bool,
     show_titlebar: bool,
+    transparent: bool,
 }
 
 impl WindowBuilder {
@@ -354,14 +683,18 @@ impl WindowBuilder {
             appdata: std::sync::Arc::downgrade(&app.data),
             handler: None,
             title: String::new(),
+            app_id: None,
             menu: None,
             size: Size::new(0.0, 0.0),
             position: None,
             level: WindowLevel::AppWindow,
+            parent: None,
             state: None,
             min_size: None,
+            max_size: None,
             resizable: true,
             show_titlebar: true,
+            transparent: false,
         }
     }
 
@@ -380,6 +713,11 @@ impl WindowBuilder {
         self
     }
 
+    pub fn max_size(mut self, size: Size) -> Self {
+        self.max_size = Some(size);
+        self
+    }
+
     pub fn resizable(mut self, resizable: bool) -> Self {
         self.resizable = resizable;
         self
@@ -390,10 +728,8 @@ impl WindowBuilder {
         self
     }
 
-    pub fn transparent(self, _transparent: bool) -> Self {
-        tracing::warn!(
-            "WindowBuilder::transparent is unimplemented for Wayland, it allows transparency by default"
-        );
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
         self
     }
 
@@ -407,6 +743,11 @@ impl WindowBuilder {
         self
     }
 
+    pub fn parent(mut self, parent: &WindowHandle) -> Self {
+        self.parent = Some(parent.clone());
+        self
+    }
+
     pub fn window_state(mut self, state: window::WindowState) -> Self {
         self.state = Some(state);
         self
@@ -417,16 +758,17 @@ impl WindowBuilder {
         self
     }
 
+    pub fn app_id(mut self, app_id: impl Into<String>) -> Self {
+        self.app_id = Some(app_id.into());
+        self
+    }
+
     pub fn menu(mut self, menu: Menu) -> Self {
         self.menu = Some(menu);
         self
     }
 
     pub fn build(self) -> Result<WindowHandle, ShellError> {
-        if matches!(self.menu, Some(_)) {
-            tracing::warn!("menus unimplemented for wayland");
-        }
-
         let level = self.level.clone();
 
         if let WindowLevel::Modal(parent) = level {
@@ -437,17 +779,55 @@ impl WindowBuilder {
             return self.create_popup(parent);
         }
 
+        if let WindowLevel::Tooltip(parent) = level {
+            return self.create_tooltip(parent);
+        }
+
         let appdata = match self.appdata.upgrade() {
             Some(d) => d,
             None => return Err(ShellError::ApplicationDropped),
         };
 
-        let handler = self.handler.expect("must set a window handler");
+        let size = self.toplevel_size();
+        let handler = match self.handler {
+            Some(handler) => handler,
+            None => {
+                return Err(ShellError::Platform(Error::string(
+                    "window handler required",
+                )))
+            }
+        };
 
-        let surface =
-            surfaces::toplevel::Surface::new(appdata.clone(), handler, self.size, self.min_size);
+        let surface = surfaces::toplevel::Surface::new(
+            appdata.clone(),
+            handler,
+            size,
+            self.min_size,
+            self.max_size,
+            self.show_titlebar,
+            self.resizable,
+        );
 
+        // The title and app id must be set before the initial commit, so the compositor has them
+        // when the window is first mapped. Setting the title schedules that commit.
+        let app_id = self
+            .app_id
+            .unwrap_or_else(|| app_id_from_title(&self.title));
+        if !app_id.is_empty() {
+            (&surface as &dyn surfaces::Decor).set_app_id(app_id.clone());
+        }
         (&surface as &dyn surfaces::Decor).set_title(self.title);
+        std::sync::Arc::<surfaces::surface::Data>::from(&surface).set_opaque(!self.transparent);
+        // Only toplevels can be parents, popups and layer shell surfaces already stay with theirs.
+        match self
+            .parent
+            .as_ref()
+            .map(|parent| parent.inner.decor.xdg_toplevel())
+        {
+            Some(Some(parent)) => surface.set_parent(&parent),
+            Some(None) => tracing::warn!("the parent of a window has to be a toplevel"),
+            None => {}
+        }
 
         let handle = WindowHandle::new(
             surface.clone(),
@@ -456,6 +836,12 @@ impl WindowBuilder {
             surface.clone(),
             self.appdata.clone(),
         );
+        if let Some(menu) = self.menu {
+            handle.set_menu(menu);
+        }
+        if let Some(parent) = &self.parent {
+            handle.inner.parent.set(Some(parent.id()));
+        }
 
         if appdata
             .handles
@@ -477,17 +863,61 @@ impl WindowBuilder {
             let handle = handle.clone();
             move |winhandle| winhandle.connect(&handle.into())
         });
+        #[cfg(feature = "accesskit")]
+        handle.init_accesskit(app_id);
 
         Ok(handle)
     }
 
-    fn create_popup(self, parent: window::WindowHandle) -> Result<WindowHandle, ShellError> {
+    /// The size a window asks for, which it gets if the compositor leaves the size up to us.
+    ///
+    /// Windows which don't give a size fall back to their minimum size, or a default, as a zero
+    /// sized window can't be shown. Sizes below the minimum are clamped later.
+    fn toplevel_size(&self) -> Size {
+        if !self.size.is_empty() {
+            return self.size;
+        }
+        let size = match self.min_size {
+            Some(min_size) if !min_size.is_empty() => min_size,
+            _ => DEFAULT_SIZE,
+        };
+        tracing::debug!("no window size was given, so using {:?}", size);
+        size
+    }
+
+    /// The size of a popup or tooltip, which isn't negotiated with the compositor like windows.
+    fn initial_size(&self) -> Size {
         let dim = self.min_size.unwrap_or(Size::ZERO);
         let dim = Size::new(dim.width.max(1.), dim.height.max(1.));
-        let dim = Size::new(
+        Size::new(
             self.size.width.max(dim.width),
             self.size.height.max(dim.height),
-        );
+        )
+    }
+
+    /// Tooltips are popups without a grab, anchored at their position in the parent, which the
+    /// compositor moves if they wouldn't fit on screen.
+    fn create_tooltip(self, parent: window::WindowHandle) -> Result<WindowHandle, ShellError> {
+        let position = self.position.unwrap_or(Point::ZERO);
+        let config = surfaces::popup::Config {
+            anchor_rect: (position, Size::new(1., 1.)),
+            anchor: xdg_positioner::Anchor::TopLeft,
+            gravity: xdg_positioner::Gravity::BottomRight,
+            constraint_adjustment: xdg_positioner::ConstraintAdjustment::FlipX
+                | xdg_positioner::ConstraintAdjustment::FlipY
+                | xdg_positioner::ConstraintAdjustment::SlideX
+                | xdg_positioner::ConstraintAdjustment::SlideY,
+            ..surfaces::popup::Config::default()
+        }
+        .with_size(self.initial_size());
+
+        tracing::debug!("tooltip {:?}", config);
+
+        popup::create(&parent.0, &config, self.appdata, self.handler)
+    }
+
+    fn create_popup(self, parent: window::WindowHandle) -> Result<WindowHandle, ShellError> {
+        let dim = self.initial_size();
 
         let config = surfaces::popup::Config::default()
             .with_size(dim)
@@ -648,3 +1078,14 @@ pub mod popup {
         Ok(handle)
     }
 }
+
+/// Make an app id for windows which weren't given one out of their title, e.g. "My App" becomes
+/// "my-app".
+fn app_id_from_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
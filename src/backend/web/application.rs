@@ -14,6 +14,8 @@
 
 //! Web implementation of features at the application scope.
 
+use raw_window_handle::{HasRawDisplayHandle, RawDisplayHandle, WebDisplayHandle};
+
 use crate::application::AppHandler;
 
 use super::clipboard::Clipboard;
@@ -46,6 +48,12 @@ impl Application {
     }
 }
 
+unsafe impl HasRawDisplayHandle for Application {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        RawDisplayHandle::Web(WebDisplayHandle::empty())
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct AppHandle;
 
@@ -54,6 +62,17 @@ impl AppHandle {
     where
         F: FnOnce(Option<&mut dyn AppHandler>) + Send + 'static,
     {
-        todo!()
+        tracing::warn!("AppHandle::run_on_main unimplemented for web");
+    }
+
+    pub fn add_idle_callback<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut dyn AppHandler) + Send + 'static,
+    {
+        // This goes through `run_on_main`, so it works once that does.
+        self.run_on_main(move |handler| match handler {
+            Some(handler) => callback(handler),
+            None => tracing::warn!("dropping idle callback, the application has no handler"),
+        })
     }
 }
@@ -29,6 +29,7 @@ pub(crate) fn convert_keyboard_event(
         key: event.key().parse().unwrap_or(KbKey::Unidentified),
         code: convert_code(&event.code()),
         location: convert_location(event.location()),
+        hardware_keycode: None,
         mods,
         repeat: event.repeat(),
         is_composing: event.is_composing(),
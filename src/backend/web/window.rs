@@ -34,10 +34,12 @@ use super::application::Application;
 use super::error::Error;
 use super::keycodes::convert_keyboard_event;
 use super::menu::Menu;
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::{ClickCounter, IdleCallback};
 use crate::dialog::{FileDialogOptions, FileDialogType};
 use crate::error::Error as ShellError;
 use crate::scale::{Scale, ScaledArea};
+use crate::screen::Monitor;
 
 use crate::keyboard::{KeyState, Modifiers};
 use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
@@ -45,7 +47,7 @@ use crate::region::Region;
 use crate::text::{simulate_input, Event};
 use crate::window;
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    DragIcon, FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 
 // This is a macro instead of a function since KeyboardEvent and MouseEvent has identical functions
@@ -381,6 +383,21 @@ impl WindowBuilder {
         self
     }
 
+    pub fn max_size(self, _: Size) -> Self {
+        // Ignored
+        self
+    }
+
+    pub fn app_id(self, _: impl Into<String>) -> Self {
+        // Ignored
+        self
+    }
+
+    pub fn parent(self, _parent: &WindowHandle) -> Self {
+        warn!("parent unimplemented for web");
+        self
+    }
+
     pub fn resizable(self, _resizable: bool) -> Self {
         // Ignored
         self
@@ -514,6 +531,32 @@ impl WindowHandle {
         warn!("show_titlebar unimplemented for web");
     }
 
+    pub fn lock_pointer(&self) {
+        warn!("lock_pointer unimplemented for web");
+    }
+
+    pub fn unlock_pointer(&self) {}
+
+    pub fn begin_resize_drag(&self, _edge: window::ResizeEdge) {
+        warn!("begin_resize_drag unimplemented for web");
+    }
+
+    pub fn set_min_size(&self, _size: Size) {
+        warn!("set_min_size unimplemented for web");
+    }
+
+    pub fn set_max_size(&self, _size: Option<Size>) {
+        warn!("set_max_size unimplemented for web");
+    }
+
+    pub fn set_app_id(&self, _app_id: &str) {
+        warn!("set_app_id unimplemented for web");
+    }
+
+    pub fn set_fullscreen(&self, _fullscreen: bool, _monitor: Option<Monitor>) {
+        warn!("set_fullscreen unimplemented for web");
+    }
+
     pub fn set_position(&self, _position: Point) {
         warn!("WindowHandle::set_position unimplemented for web");
     }
@@ -646,6 +689,23 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("set_cursor_visible unimplemented for web");
+    }
+
+    pub fn set_input_region(&self, _region: Option<Region>) {
+        warn!("set_input_region unimplemented for web");
+    }
+
+    pub fn start_drag(&self, _formats: &[ClipboardFormat], _icon: Option<DragIcon>) {
+        warn!("start_drag unimplemented for web");
+    }
+
+    pub fn is_visible(&self) -> bool {
+        warn!("is_visible unimplemented for web");
+        true
+    }
+
     pub fn make_cursor(&self, _cursor_desc: &CursorDesc) -> Option<Cursor> {
         warn!("Custom cursors are not yet supported in the web backend");
         None
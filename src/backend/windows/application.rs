@@ -21,6 +21,7 @@ use std::ptr;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
+use raw_window_handle::{HasRawDisplayHandle, RawDisplayHandle, WindowsDisplayHandle};
 use winapi::shared::minwindef::{DWORD, FALSE, HINSTANCE};
 use winapi::shared::ntdef::LPCWSTR;
 use winapi::shared::windef::{DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, HCURSOR, HWND};
@@ -231,6 +232,12 @@ impl Application {
 
 type MainThreadCb = Box<dyn FnOnce(Option<&mut dyn AppHandler>) + Send>;
 
+unsafe impl HasRawDisplayHandle for Application {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        RawDisplayHandle::Windows(WindowsDisplayHandle::empty())
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct AppHandle {
     main_thread_id: DWORD,
@@ -250,4 +257,16 @@ impl AppHandle {
             }
         }
     }
+
+    pub fn add_idle_callback<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut dyn AppHandler) + Send + 'static,
+    {
+        // The message loop has no idle step of ours to wait for, so these run with the other
+        // main thread callbacks, once the loop gets `WM_RUN_MAIN_CB_QUEUE`.
+        self.run_on_main(move |handler| match handler {
+            Some(handler) => callback(handler),
+            None => tracing::warn!("dropping idle callback, the application has no handler"),
+        })
+    }
 }
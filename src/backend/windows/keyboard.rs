@@ -612,6 +612,7 @@ impl KeyboardState {
                         key,
                         is_composing: false,
                         location,
+                        hardware_keycode: Some(scan_code),
                         repeat,
                     };
                     Some(event)
@@ -637,6 +638,7 @@ impl KeyboardState {
                     key,
                     is_composing: false,
                     location,
+                    hardware_keycode: Some(scan_code),
                     repeat,
                 };
                 Some(event)
@@ -671,6 +673,7 @@ impl KeyboardState {
                         key,
                         is_composing: false,
                         location,
+                        hardware_keycode: Some(scan_code),
                         repeat,
                     };
                     Some(event)
@@ -59,6 +59,7 @@ use super::menu::Menu;
 use super::timers::TimerSlots;
 use super::util::{self, ToWide, OPTIONAL_FUNCTIONS};
 
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::IdleCallback;
 use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
 use crate::error::Error as ShellError;
@@ -66,10 +67,11 @@ use crate::keyboard::{KbKey, KeyState};
 use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
 use crate::region::Region;
 use crate::scale::{Scalable, Scale, ScaledArea};
+use crate::screen::Monitor;
 use crate::text::{simulate_input, Event};
 use crate::window;
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    DragIcon, FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 
 /// The backend target DPI.
@@ -557,6 +559,10 @@ impl MyWndProc {
                             window::WindowState::Maximized => SW_MAXIMIZE,
                             window::WindowState::Minimized => SW_MINIMIZE,
                             window::WindowState::Restored => SW_RESTORE,
+                            window::WindowState::Fullscreen => {
+                                warn!("fullscreen is unimplemented on windows");
+                                SW_RESTORE
+                            }
                         }
                     } else {
                         SW_SHOWNOACTIVATE
@@ -1240,6 +1246,21 @@ impl WindowBuilder {
         self
     }
 
+    pub fn max_size(self, _size: Size) -> Self {
+        warn!("max_size is unimplemented on windows");
+        self
+    }
+
+    pub fn app_id(self, _app_id: impl Into<String>) -> Self {
+        warn!("app_id is unimplemented on windows");
+        self
+    }
+
+    pub fn parent(self, _parent: &WindowHandle) -> Self {
+        warn!("parent is unimplemented on windows");
+        self
+    }
+
     pub fn resizable(mut self, resizable: bool) -> Self {
         self.resizable = resizable;
         self
@@ -1679,6 +1700,32 @@ impl WindowHandle {
         self.defer(DeferredOp::ShowTitlebar(show_titlebar));
     }
 
+    pub fn lock_pointer(&self) {
+        warn!("lock_pointer is unimplemented on windows");
+    }
+
+    pub fn unlock_pointer(&self) {}
+
+    pub fn begin_resize_drag(&self, _edge: window::ResizeEdge) {
+        warn!("begin_resize_drag is unimplemented on windows");
+    }
+
+    pub fn set_min_size(&self, _size: Size) {
+        warn!("set_min_size is unimplemented on windows");
+    }
+
+    pub fn set_max_size(&self, _size: Option<Size>) {
+        warn!("set_max_size is unimplemented on windows");
+    }
+
+    pub fn set_app_id(&self, _app_id: &str) {
+        warn!("set_app_id is unimplemented on windows");
+    }
+
+    pub fn set_fullscreen(&self, _fullscreen: bool, _monitor: Option<Monitor>) {
+        warn!("set_fullscreen is unimplemented on windows");
+    }
+
     pub fn set_position(&self, position: Point) {
         self.defer(DeferredOp::SetWindowState(window::WindowState::Restored));
         if let Some(w) = self.state.upgrade() {
@@ -1889,6 +1936,25 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("set_cursor_visible is unimplemented on windows");
+    }
+
+    pub fn set_input_region(&self, _region: Option<Region>) {
+        warn!("set_input_region is unimplemented on windows");
+    }
+
+    pub fn start_drag(&self, _formats: &[ClipboardFormat], _icon: Option<DragIcon>) {
+        warn!("start_drag is unimplemented on windows");
+    }
+
+    pub fn is_visible(&self) -> bool {
+        match self.get_hwnd() {
+            Some(hwnd) => unsafe { IsWindowVisible(hwnd) != 0 },
+            None => false,
+        }
+    }
+
     pub fn make_cursor(&self, cursor_desc: &CursorDesc) -> Option<Cursor> {
         if let Some(hwnd) = self.get_hwnd() {
             unsafe {
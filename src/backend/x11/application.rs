@@ -22,6 +22,7 @@ use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Error};
+use raw_window_handle::{HasRawDisplayHandle, RawDisplayHandle, XcbDisplayHandle};
 use x11rb::connection::{Connection, RequestConnection};
 use x11rb::protocol::render::{self, ConnectionExt as _, Pictformat};
 use x11rb::protocol::xinput::ChangeReason;
@@ -276,6 +277,15 @@ impl Application {
     }
 }
 
+unsafe impl HasRawDisplayHandle for Application {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        let mut handle = XcbDisplayHandle::empty();
+        handle.connection = self.connection().get_raw_xcb_connection();
+        handle.screen = self.screen_num() as i32;
+        RawDisplayHandle::Xcb(handle)
+    }
+}
+
 impl AppInner {
     fn new() -> Result<Rc<AppInner>, Error> {
         // If we want to support OpenGL, we will need to open a connection with Xlib support (see
@@ -877,7 +887,19 @@ impl AppHandle {
     where
         F: FnOnce(Option<&mut dyn AppHandler>) + Send + 'static,
     {
-        todo!()
+        tracing::warn!("AppHandle::run_on_main is currently unimplemented for X11 backend.");
+    }
+
+    pub fn add_idle_callback<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut dyn AppHandler) + Send + 'static,
+    {
+        // This goes through `run_on_main`, so it works once that does. The idle queue of the
+        // event loop would then be the place to wait for it to go quiet.
+        self.run_on_main(move |handler| match handler {
+            Some(handler) => callback(handler),
+            None => tracing::warn!("dropping idle callback, the application has no handler"),
+        })
     }
 }
 
@@ -24,7 +24,7 @@ use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use crate::pointer::{
-    Angle, MouseInfo, PenInclination, PenInfo, PointerId, PointerType, TouchInfo,
+    Angle, MouseInfo, PenInclination, PenInfo, PointerId, PointerType, TouchInfo, WheelSource,
 };
 use crate::scale::Scalable;
 use anyhow::{anyhow, Context, Error};
@@ -37,7 +37,7 @@ use x11rb::protocol::xinput::{self, DeviceType, ModifierInfo, TouchEventFlags};
 use x11rb::protocol::xproto::{
     self, AtomEnum, ChangeWindowAttributesAux, ColormapAlloc, ConfigureNotifyEvent,
     ConfigureWindowAux, ConnectionExt, EventMask, ImageOrder as X11ImageOrder, KeyButMask,
-    PropMode, Visualtype, WindowClass,
+    MapState, PropMode, Visualtype, WindowClass,
 };
 use x11rb::wrapper::ConnectionExt as _;
 use x11rb::xcb_ffi::XCBConnection;
@@ -48,6 +48,7 @@ use raw_window_handle::{
 };
 
 use crate::backend::shared::Timer;
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::IdleCallback;
 use crate::dialog::FileDialogOptions;
 use crate::error::Error as ShellError;
@@ -56,9 +57,11 @@ use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
 use crate::mouse::{Cursor, CursorDesc};
 use crate::region::Region;
 use crate::scale::Scale;
+use crate::screen::Monitor;
 use crate::text::{simulate_input, Event};
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    DragIcon, FileDialogToken, IdleToken, ResizeEdge, TextFieldToken, TimerToken, WinHandler,
+    WindowLevel,
 };
 use crate::{window, KeyEvent, PointerButton, PointerButtons, PointerEvent, ScaledArea};
 
@@ -161,6 +164,21 @@ impl WindowBuilder {
         self
     }
 
+    pub fn max_size(self, _max_size: Size) -> Self {
+        warn!("WindowBuilder::max_size is currently unimplemented for X11 backend.");
+        self
+    }
+
+    pub fn app_id(self, _app_id: impl Into<String>) -> Self {
+        warn!("WindowBuilder::app_id is currently unimplemented for X11 backend.");
+        self
+    }
+
+    pub fn parent(self, _parent: &WindowHandle) -> Self {
+        warn!("WindowBuilder::parent is currently unimplemented for X11 backend.");
+        self
+    }
+
     pub fn resizable(mut self, resizable: bool) -> Self {
         self.resizable = resizable;
         self
@@ -398,6 +416,7 @@ impl WindowBuilder {
                 window::WindowState::Maximized => WmHintsState::Normal,
                 window::WindowState::Minimized => WmHintsState::Iconic,
                 window::WindowState::Restored => WmHintsState::Normal,
+                window::WindowState::Fullscreen => WmHintsState::Normal,
             });
         }
         log_x11!(hints.set(conn, id).context("set wm hints"));
@@ -597,6 +616,18 @@ impl Window {
         self.destroy();
     }
 
+    /// Whether the window is mapped, and so are all of its ancestors.
+    fn is_visible(&self) -> bool {
+        fn _is_visible(window: &Window) -> Result<bool, Error> {
+            let conn = window.app.connection();
+            let attributes = conn.get_window_attributes(window.id)?.reply()?;
+            Ok(attributes.map_state == MapState::VIEWABLE)
+        }
+        let visible = _is_visible(self);
+        log_x11!(&visible);
+        visible.unwrap_or(false)
+    }
+
     /// Set whether the window should be resizable
     fn resizable(&self, resizable: bool) {
         let conn = self.app.connection();
@@ -823,6 +854,8 @@ impl Window {
             is_primary: false,
             pointer_type: PointerType::Mouse(MouseInfo {
                 wheel_delta: Default::default(),
+                wheel_source: None,
+                wheel_end: false,
             }),
             pos: Point::new(ev_x, ev_y).to_dp(scale),
             buttons: pointer_buttons(mods),
@@ -902,12 +935,16 @@ impl Window {
                 super::pointer::DeviceKind::Touch | super::pointer::DeviceKind::Mouse => {
                     PointerType::Mouse(MouseInfo {
                         wheel_delta: Vec2::ZERO,
+                        wheel_source: None,
+                        wheel_end: false,
                     })
                 }
             }
         } else {
             PointerType::Mouse(MouseInfo {
                 wheel_delta: Vec2::ZERO,
+                wheel_source: None,
+                wheel_end: false,
             })
         };
 
@@ -974,6 +1011,8 @@ impl Window {
         };
         pointer_ev.pointer_type = PointerType::Mouse(MouseInfo {
             wheel_delta: delta.into(),
+            wheel_source: Some(WheelSource::Wheel),
+            wheel_end: false,
         });
         pointer_ev.button = PointerButton::None;
 
@@ -1242,6 +1281,32 @@ impl WindowHandle {
         }
     }
 
+    pub fn lock_pointer(&self) {
+        warn!("WindowHandle::lock_pointer is currently unimplemented for X11 backend.");
+    }
+
+    pub fn unlock_pointer(&self) {}
+
+    pub fn begin_resize_drag(&self, _edge: ResizeEdge) {
+        warn!("WindowHandle::begin_resize_drag is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_min_size(&self, _size: Size) {
+        warn!("WindowHandle::set_min_size is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_max_size(&self, _size: Option<Size>) {
+        warn!("WindowHandle::set_max_size is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_app_id(&self, _app_id: &str) {
+        warn!("WindowHandle::set_app_id is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_fullscreen(&self, _fullscreen: bool, _monitor: Option<Monitor>) {
+        warn!("WindowHandle::set_fullscreen is currently unimplemented for X11 backend.");
+    }
+
     pub fn set_position(&self, position: Point) {
         if let Some(w) = self.window.upgrade() {
             w.set_position(position);
@@ -1380,6 +1445,27 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("WindowHandle::set_cursor_visible is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_input_region(&self, _region: Option<Region>) {
+        warn!("WindowHandle::set_input_region is currently unimplemented for X11 backend.");
+    }
+
+    pub fn start_drag(&self, _formats: &[ClipboardFormat], _icon: Option<DragIcon>) {
+        warn!("WindowHandle::start_drag is currently unimplemented for X11 backend.");
+    }
+
+    pub fn is_visible(&self) -> bool {
+        if let Some(w) = self.window.upgrade() {
+            w.is_visible()
+        } else {
+            error!("Window {} has already been dropped", self.id);
+            false
+        }
+    }
+
     pub fn make_cursor(&self, desc: &CursorDesc) -> Option<Cursor> {
         if let Some(w) = self.window.upgrade() {
             match w.app.render_argb32_pictformat_cursor() {
@@ -1477,6 +1563,7 @@ unsafe impl HasRawDisplayHandle for WindowHandle {
         let mut handle = XcbDisplayHandle::empty();
         if let Some(window) = self.window.upgrade() {
             handle.connection = window.app.connection().get_raw_xcb_connection();
+            handle.screen = window.app.screen_num() as i32;
         } else {
             // Documentation for HasRawWindowHandle encourages filling in all fields possible,
             // leaving those empty that cannot be derived.
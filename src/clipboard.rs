@@ -150,6 +150,16 @@ impl Clipboard {
         self.0.preferred_format(formats)
     }
 
+    /// Whether the system clipboard has data in the given format, e.g. to decide whether a
+    /// "Paste" menu item is enabled.
+    ///
+    /// This doesn't read the data. On Wayland it only looks at the formats the clipboard's owner
+    /// advertised, so it's cheap; other platforms may have to ask the owner. It's `false` when
+    /// the clipboard is empty.
+    pub fn has_format(&self, format: FormatId) -> bool {
+        self.0.preferred_format(&[format]).is_some()
+    }
+
     /// Return data in a given format, if available.
     ///
     /// It is recommended that the [`FormatId`] argument be a format returned by
@@ -42,6 +42,11 @@ pub struct KeyEvent {
     pub code: Code,
     /// Location for keys with multiple instances on common keyboards.
     pub location: Location,
+    /// The platform's own code for the physical key, if the backend knows it.
+    ///
+    /// This is the xkb keycode on Linux (the evdev scancode + 8), the scan code on Windows and
+    /// the `keyCode` on macOS. Prefer `code`, which is the same on every platform.
+    pub hardware_keycode: Option<u32>,
     /// Flags for pressed modifier keys.
     pub mods: Modifiers,
     /// True if the key is currently auto-repeated.
@@ -83,6 +88,7 @@ impl KeyEvent {
             key,
             code: Code::Unidentified,
             location: Location::Standard,
+            hardware_keycode: None,
             state: KeyState::Down,
             mods,
             is_composing: false,
@@ -85,14 +85,15 @@ pub use keyboard::{Code, IntoKey, KbKey, KeyEvent, KeyState, Location, Modifiers
 pub use menu::Menu;
 pub use mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
 pub use pointer::{
-    PenInclination, PointerButton, PointerButtons, PointerEvent, PointerId, PointerType,
+    GestureEvent, GesturePhase, PenInclination, PointerButton, PointerButtons, PointerEvent,
+    PointerId, PointerType, WheelSource,
 };
 pub use region::Region;
 pub use scale::{Scalable, Scale, ScaledArea};
 pub use screen::{Monitor, Screen};
 pub use window::{
-    FileDialogToken, IdleHandle, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowBuilder,
-    WindowHandle, WindowLevel, WindowState,
+    DragIcon, DropAction, FileDialogToken, IdleHandle, IdleToken, PresentationInfo, ResizeEdge,
+    TextFieldToken, TimerToken, WinHandler, WindowBuilder, WindowHandle, WindowLevel, WindowState,
 };
 
 pub use keyboard_types;
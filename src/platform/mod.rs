@@ -20,5 +20,11 @@
 ))]
 pub mod linux;
 
+#[cfg(all(
+    feature = "wayland",
+    any(target_os = "freebsd", target_os = "linux", target_os = "openbsd")
+))]
+pub mod wayland;
+
 #[cfg(any(doc, target_os = "macos"))]
 pub mod mac;
@@ -0,0 +1,75 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wayland specific extensions.
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+/// Wayland specific extensions to [`Application`], for driving it from an event loop other than
+/// [`Application::run`].
+///
+/// The connection must only be dispatched from the thread which created the [`Application`],
+/// as that is where our event handlers live.
+///
+/// [`Application`]: crate::Application
+/// [`Application::run`]: crate::Application::run
+pub trait WaylandApplicationExt {
+    /// The file descriptor of the connection to the compositor.
+    ///
+    /// When it becomes readable, call [`dispatch_pending`].
+    ///
+    /// [`dispatch_pending`]: WaylandApplicationExt::dispatch_pending
+    fn connection_fd(&self) -> RawFd;
+
+    /// Dispatch the events which the compositor has sent, and flush our requests, without
+    /// blocking.
+    ///
+    /// Timers and callbacks sent with [`AppHandle::run_on_main`] are only run by
+    /// [`Application::run`].
+    ///
+    /// [`AppHandle::run_on_main`]: crate::AppHandle::run_on_main
+    /// [`Application::run`]: crate::Application::run
+    fn dispatch_pending(&self) -> Result<(), crate::Error>;
+
+    /// Block until the compositor has handled every request sent so far, dispatching the events
+    /// it sends meanwhile, then flush our requests.
+    ///
+    /// This is useful during setup, for example to wait for a new window's first configure. It
+    /// must be called on the thread which created the [`Application`], and not from inside the
+    /// handlers of events which are being dispatched.
+    ///
+    /// [`Application`]: crate::Application
+    fn roundtrip(&self) -> Result<(), crate::Error>;
+
+    /// The window with keyboard focus, if any.
+    ///
+    /// This is the window which gets key events, and whose serials are used to set the
+    /// clipboard.
+    fn active_window(&self) -> Option<crate::WindowHandle>;
+
+    /// Set how close together the presses of a double (or triple, ...) click must be: at most
+    /// `interval` apart in time, and `distance` display points apart in space.
+    ///
+    /// The defaults are 500 milliseconds and 4 points.
+    fn set_multi_click(&self, interval: Duration, distance: f64);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Application;
+
+    use super::*;
+    use static_assertions as sa;
+    sa::assert_impl_all!(Application: WaylandApplicationExt);
+}
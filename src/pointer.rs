@@ -163,6 +163,26 @@ pub struct TouchInfo {
 #[derive(Debug, Clone, PartialEq)]
 pub struct MouseInfo {
     pub wheel_delta: Vec2,
+    /// What a wheel event's scrolling comes from, on platforms which say.
+    pub wheel_source: Option<WheelSource>,
+    /// Whether a wheel event ends a scroll, because the fingers were lifted from the touchpad.
+    ///
+    /// Such an event doesn't scroll any further, but kinetic scrolling can start from it.
+    pub wheel_end: bool,
+}
+
+/// What is scrolling, for a wheel event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WheelSource {
+    /// A mouse wheel, which scrolls in steps.
+    Wheel,
+    /// A mouse wheel tilted sideways.
+    WheelTilt,
+    /// Fingers on a touchpad, which scroll smoothly.
+    Finger,
+    /// Something else which scrolls smoothly, like a trackball or moving the mouse while holding
+    /// a button.
+    Continuous,
 }
 
 impl Default for PenInfo {
@@ -458,6 +478,8 @@ impl Default for PointerEvent {
             is_primary: true,
             pointer_type: PointerType::Mouse(MouseInfo {
                 wheel_delta: Vec2::ZERO,
+                wheel_source: None,
+                wheel_end: false,
             }),
         }
     }
@@ -470,6 +492,8 @@ impl From<crate::MouseEvent> for PointerEvent {
             is_primary: true,
             pointer_type: PointerType::Mouse(MouseInfo {
                 wheel_delta: m.wheel_delta,
+                wheel_source: None,
+                wheel_end: false,
             }),
             pos: m.pos,
             buttons: m.buttons.into(),
@@ -481,6 +505,43 @@ impl From<crate::MouseEvent> for PointerEvent {
     }
 }
 
+/// The stage a touchpad gesture is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GesturePhase {
+    Begin,
+    Update,
+    End,
+    /// The gesture was abandoned, for example because a finger was lifted too early.
+    Cancel,
+}
+
+/// A multi-finger touchpad gesture.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GestureEvent {
+    /// Fingers moving together across the touchpad.
+    Swipe {
+        phase: GesturePhase,
+        fingers: u32,
+        /// Where the pointer is.
+        pos: Point,
+        /// The movement since the previous event, in display points.
+        delta: Vec2,
+    },
+    /// Fingers moving closer together or further apart, and possibly rotating.
+    Pinch {
+        phase: GesturePhase,
+        fingers: u32,
+        /// Where the pointer is.
+        pos: Point,
+        /// The movement of the fingers' center since the previous event, in display points.
+        delta: Vec2,
+        /// The distance between the fingers relative to when the gesture began.
+        scale: f64,
+        /// The rotation since the previous event, in degrees clockwise.
+        rotation: f64,
+    },
+}
+
 impl PointerEvent {
     // TODO - lots of helper functions - is_hovering?
 
@@ -49,6 +49,9 @@ impl Monitor {
         self.primary
     }
     /// Returns the monitor rectangle in virtual screen coordinates.
+    ///
+    /// On wayland these are the compositor's logical coordinates, that is the output's mode
+    /// size in pixels divided by its scale.
     pub fn virtual_rect(&self) -> Rect {
         self.rect
     }
@@ -56,6 +59,8 @@ impl Monitor {
     /// Returns the monitor working rectangle in virtual screen coordinates.
     /// The working rectangle excludes certain things like the dock and menubar on mac,
     /// and the taskbar on windows.
+    ///
+    /// On wayland this is the same as [`virtual_rect`](Monitor::virtual_rect).
     pub fn virtual_work_rect(&self) -> Rect {
         self.work_rect
     }
@@ -19,17 +19,19 @@ use std::time::Duration;
 
 use crate::application::Application;
 use crate::backend::window as backend;
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::Counter;
 use crate::dialog::{FileDialogOptions, FileInfo};
 use crate::error::Error;
 use crate::keyboard::KeyEvent;
-use crate::kurbo::{Insets, Point, Rect, Size};
+use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
 use crate::menu::Menu;
 use crate::mouse::{Cursor, CursorDesc, MouseEvent};
 use crate::region::Region;
 use crate::scale::Scale;
+use crate::screen::Monitor;
 use crate::text::{Event, InputHandler};
-use crate::PointerEvent;
+use crate::{GestureEvent, PointerEvent};
 
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
@@ -162,12 +164,73 @@ pub enum WindowLevel {
     Modal(WindowHandle),
 }
 
+/// What happened to a painted frame, see [`WinHandler::presented`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentationInfo {
+    /// The frame was shown on screen.
+    Presented {
+        /// When the frame was shown. Like [`WinHandler::frame_time`], this has no particular
+        /// starting point, so only the difference between two times is meaningful.
+        time: Duration,
+        /// The time between refreshes of the screen, if it refreshes at a fixed rate.
+        refresh: Option<Duration>,
+    },
+    /// The frame was never shown, for example because a newer frame replaced it in time.
+    Discarded,
+}
+
+/// An image which follows the pointer during a drag, see [`WindowHandle::start_drag`].
+#[derive(Debug, Clone)]
+pub struct DragIcon {
+    pub(crate) pixels: Vec<u8>,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) hot: Point,
+}
+
+impl DragIcon {
+    /// Creates a new `DragIcon` from premultiplied RGBA `pixels`, row by row.
+    ///
+    /// `hot` is the pixel of the image which is under the pointer, with `(0, 0)` at the top left.
+    pub fn new(pixels: Vec<u8>, width: usize, height: usize, hot: impl Into<Point>) -> Self {
+        DragIcon {
+            pixels,
+            width,
+            height,
+            hot: hot.into(),
+        }
+    }
+}
+
+/// What the target of a drag did with it, see [`WinHandler::dnd_finished`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropAction {
+    /// The target copied the data.
+    Copy,
+    /// The target moved the data, so the source should delete its own copy.
+    Move,
+}
+
 /// Contains the different states a Window can be in.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WindowState {
     Maximized,
     Minimized,
     Restored,
+    Fullscreen,
+}
+
+/// An edge or corner of a window, which the user can drag to resize it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
 }
 
 /// A handle to a platform window object.
@@ -188,6 +251,21 @@ impl WindowHandle {
         self.0.close()
     }
 
+    /// Ask the window to close, as if the user clicked the "X" in the titlebar.
+    ///
+    /// Unlike [`close`], this goes through [`WinHandler::request_close`], which is called later,
+    /// once the current event has been handled. The window only closes if the handler calls
+    /// [`close`] from there; returning without doing so vetoes the request, for example to ask
+    /// about unsaved changes first. The default [`WinHandler::request_close`] always vetoes.
+    ///
+    /// [`close`]: WindowHandle::close
+    pub fn request_close(&self) {
+        match self.get_idle_handle() {
+            Some(idle) => idle.add_idle(|handler| handler.request_close()),
+            None => tracing::warn!("unable to request close, the window has no idle handle"),
+        }
+    }
+
     /// Set whether the window should be resizable
     pub fn resizable(&self, resizable: bool) {
         self.0.resizable(resizable)
@@ -208,21 +286,62 @@ impl WindowHandle {
     /// because this refers to the current location of the mouse, you should probably call this
     /// function in response to every relevant [`WinHandler::mouse_move`].
     ///
-    /// This is currently only implemented on Windows and GTK.
+    /// This is currently only implemented on Windows, GTK and Wayland.
     pub fn handle_titlebar(&self, val: bool) {
         self.0.handle_titlebar(val);
     }
 
+    /// Let the user resize the window by dragging `edge` with the pointer. This can be used to
+    /// implement custom resize borders, and should be called in response to the
+    /// [`WinHandler::mouse_down`] which starts the drag.
+    ///
+    /// This is currently only implemented on Wayland.
+    pub fn begin_resize_drag(&self, edge: ResizeEdge) {
+        self.0.begin_resize_drag(edge)
+    }
+
+    /// Start dragging `formats` out of the window, for example `text/uri-list` to drag files to
+    /// a file manager. This should be called while the pointer button which starts the drag is
+    /// held, usually from [`WinHandler::mouse_move`].
+    ///
+    /// `icon` is shown under the pointer while the drag goes on. The handler hears how the drag
+    /// ended with [`WinHandler::dnd_finished`].
+    ///
+    /// This is currently only implemented on Wayland.
+    pub fn start_drag(&self, formats: &[ClipboardFormat], icon: Option<DragIcon>) {
+        self.0.start_drag(formats, icon)
+    }
+
     /// Set whether the window should show titlebar.
     pub fn show_titlebar(&self, show_titlebar: bool) {
         self.0.show_titlebar(show_titlebar)
     }
 
+    /// Lock the pointer in place and hide it, for example for first-person camera controls.
+    ///
+    /// While locked, movement is reported to [`WinHandler::pointer_move_relative`] instead of
+    /// [`WinHandler::mouse_move`]. The lock ends with [`unlock_pointer`], or when the window
+    /// loses the pointer.
+    ///
+    /// This is currently only implemented on Wayland.
+    ///
+    /// [`unlock_pointer`]: WindowHandle::unlock_pointer
+    pub fn lock_pointer(&self) {
+        self.0.lock_pointer()
+    }
+
+    /// Release a pointer locked by [`lock_pointer`](WindowHandle::lock_pointer), and show it again.
+    pub fn unlock_pointer(&self) {
+        self.0.unlock_pointer()
+    }
+
     /// Sets the position of the window.
     ///
     /// The position is given in [display points], measured relative to the parent window if there
     /// is one, or the origin of the virtual screen if there is no parent.
     ///
+    /// On Wayland, only windows with a parent can be positioned.
+    ///
     /// [display points]: crate::Scale
     pub fn set_position(&self, position: impl Into<Point>) {
         self.0.set_position(position.into())
@@ -233,6 +352,9 @@ impl WindowHandle {
     /// The position is returned in [display points], measured relative to the parent window if
     /// there is one, of the origin of the virtual screen if there is no parent.
     ///
+    /// On Wayland, windows without a parent don't know their position, and are always at the
+    /// origin.
+    ///
     /// [display points]: crate::Scale
     pub fn get_position(&self) -> Point {
         self.0.get_position()
@@ -276,6 +398,39 @@ impl WindowHandle {
         self.0.get_size()
     }
 
+    /// Make the window fullscreen, or return it to a normal window.
+    ///
+    /// The window goes fullscreen on `monitor`, or on one the platform picks if that's `None`.
+    /// Once the platform has made the change, the window gets a [`WinHandler::size`] for its
+    /// new size, and [`get_window_state`] reports [`WindowState::Fullscreen`].
+    ///
+    /// This is currently only implemented on Wayland and GTK, which ignores `monitor`.
+    ///
+    /// [`get_window_state`]: WindowHandle::get_window_state
+    pub fn set_fullscreen(&self, fullscreen: bool, monitor: Option<Monitor>) {
+        self.0.set_fullscreen(fullscreen, monitor)
+    }
+
+    /// Change the window's minimum drawing area size in [display points], like
+    /// [`WindowBuilder::min_size`].
+    ///
+    /// This is currently only implemented on Wayland.
+    ///
+    /// [display points]: crate::Scale
+    pub fn set_min_size(&self, size: Size) {
+        self.0.set_min_size(size)
+    }
+
+    /// Change the window's maximum drawing area size in [display points], like
+    /// [`WindowBuilder::max_size`]. `None` removes the limit.
+    ///
+    /// This is currently only implemented on Wayland.
+    ///
+    /// [display points]: crate::Scale
+    pub fn set_max_size(&self, size: Option<Size>) {
+        self.0.set_max_size(size)
+    }
+
     /// Bring this window to the front of the window stack and give it focus.
     pub fn bring_to_front_and_focus(&self) {
         self.0.bring_to_front_and_focus()
@@ -308,6 +463,13 @@ impl WindowHandle {
         self.0.set_title(title)
     }
 
+    /// Set the application id of the window, like [`WindowBuilder::app_id`].
+    ///
+    /// This is currently only implemented on Wayland.
+    pub fn set_app_id(&self, app_id: &str) {
+        self.0.set_app_id(app_id)
+    }
+
     /// Set the top-level menu for this window.
     pub fn set_menu(&self, menu: Menu) {
         self.0.set_menu(menu.into_inner())
@@ -371,6 +533,34 @@ impl WindowHandle {
         self.0.set_cursor(cursor)
     }
 
+    /// Hide or show the cursor while it's over this window, e.g. to hide it during video
+    /// playback. The cursor set with [`set_cursor`] comes back when it's shown again.
+    ///
+    /// This is currently only implemented on Wayland.
+    ///
+    /// [`set_cursor`]: WindowHandle::set_cursor
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.0.set_cursor_visible(visible)
+    }
+
+    /// Set the part of the window that takes pointer and touch input, in display points. Input
+    /// outside of it goes to whatever is behind the window, which is useful for windows that
+    /// draw their own shadows or rounded corners. `None` means the whole window takes input.
+    ///
+    /// This is currently only implemented on Wayland.
+    pub fn set_input_region(&self, region: Option<Region>) {
+        self.0.set_input_region(region)
+    }
+
+    /// Whether any part of the window is on screen. Windows which are minimized, or on another
+    /// workspace, aren't visible, and [`WinHandler::visibility_changed`] is called when that
+    /// changes.
+    ///
+    /// This is currently only implemented on Wayland, the other backends always return `true`.
+    pub fn is_visible(&self) -> bool {
+        self.0.is_visible()
+    }
+
     pub fn make_cursor(&self, desc: &CursorDesc) -> Option<Cursor> {
         self.0.make_cursor(desc)
     }
@@ -487,6 +677,20 @@ impl WindowBuilder {
         self
     }
 
+    /// Set the window's maximum drawing area size in [display points].
+    ///
+    /// Like [`min_size`], this should be considered a request to the platform. A maximum smaller
+    /// than the minimum is raised to the minimum.
+    ///
+    /// This is currently only implemented on Wayland.
+    ///
+    /// [`min_size`]: WindowBuilder::min_size
+    /// [display points]: crate::Scale
+    pub fn max_size(mut self, size: Size) -> Self {
+        self.0 = self.0.max_size(size);
+        self
+    }
+
     /// Set whether the window should be resizable.
     pub fn resizable(mut self, resizable: bool) -> Self {
         self.0 = self.0.resizable(resizable);
@@ -528,12 +732,31 @@ impl WindowBuilder {
         self
     }
 
+    /// Set the application id, which the desktop uses to group windows and to find the
+    /// `.desktop` file with the icon. It should match the name of that file, e.g.
+    /// `org.example.App`. Without one, it's made from the title.
+    ///
+    /// This is currently only implemented on Wayland.
+    pub fn app_id(mut self, app_id: impl Into<String>) -> Self {
+        self.0 = self.0.app_id(app_id);
+        self
+    }
+
     /// Set the window's menu.
     pub fn menu(mut self, menu: Menu) -> Self {
         self.0 = self.0.menu(menu.into_inner());
         self
     }
 
+    /// Make the window a dialog of `parent`, which it stays above. It's closed when its
+    /// parent is.
+    ///
+    /// This is currently only implemented on Wayland.
+    pub fn parent(mut self, parent: &WindowHandle) -> Self {
+        self.0 = self.0.parent(&parent.0);
+        self
+    }
+
     /// Sets the initial state of the window.
     pub fn window_state(mut self, state: WindowState) -> Self {
         self.0 = self.0.window_state(state);
@@ -571,6 +794,27 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn scale(&mut self, scale: Scale) {}
 
+    /// Called with the time of the frame that is about to be prepared, just before
+    /// [`prepare_paint`](WinHandler::prepare_paint).
+    ///
+    /// The time has no particular starting point, so only the difference between the times of
+    /// two frames is meaningful. Animations should advance by that difference.
+    ///
+    /// This is currently only implemented on Wayland, where it comes from the compositor's frame
+    /// callback.
+    #[allow(unused_variables)]
+    fn frame_time(&mut self, time: Duration) {}
+
+    /// Called once for each painted frame, when it is shown on screen or discarded.
+    ///
+    /// Animations can use this to keep in step with the refresh rate of the screen, and to
+    /// notice frames which were dropped.
+    ///
+    /// This is currently only implemented on Wayland, with compositors which support the
+    /// presentation-time protocol.
+    #[allow(unused_variables)]
+    fn presented(&mut self, info: PresentationInfo) {}
+
     /// Request the handler to prepare to paint the window contents.  In particular, if there are
     /// any regions that need to be repainted on the next call to `paint`, the handler should
     /// invalidate those regions by calling [`WindowHandle::invalidate_rect`] or
@@ -595,6 +839,28 @@ pub trait WinHandler {
     #[cfg(feature = "accesskit")]
     fn accesskit_tree(&mut self) -> accesskit::TreeUpdate;
 
+    /// Called before the platform surface of the window is destroyed, for example when it has to
+    /// be recreated on another output. Renderers should drop anything they made from the
+    /// window's raw handle, until [`surface_ready`] is called.
+    ///
+    /// Size and scale changes don't recreate the surface, those are reported to [`size`] and
+    /// [`scale`].
+    ///
+    /// This is currently only implemented on Wayland.
+    ///
+    /// [`surface_ready`]: WinHandler::surface_ready
+    /// [`size`]: WinHandler::size
+    /// [`scale`]: WinHandler::scale
+    fn surface_invalidated(&mut self) {}
+
+    /// Called once the window has a new platform surface, after [`surface_invalidated`]. The
+    /// window's raw handle now refers to the new surface.
+    ///
+    /// This is currently only implemented on Wayland.
+    ///
+    /// [`surface_invalidated`]: WinHandler::surface_invalidated
+    fn surface_ready(&mut self) {}
+
     /// Called when the resources need to be rebuilt.
     ///
     /// Discussion: this function is mostly motivated by using
@@ -675,6 +941,12 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn zoom(&mut self, delta: f64) {}
 
+    /// Called on a multi-finger touchpad gesture.
+    ///
+    /// Pinches are also reported through [`zoom`](WinHandler::zoom).
+    #[allow(unused_variables)]
+    fn gesture(&mut self, event: &GestureEvent) {}
+
     // While the backends transition from mouse events to pointer events, we keep these compatibility
     // shims.
 
@@ -710,6 +982,9 @@ pub trait WinHandler {
     /// move on scrolling. This polarity is consistent with the
     /// deltaX and deltaY values in a web [WheelEvent].
     ///
+    /// Some platforms also say what is scrolling, and end a scroll from a touchpad with an
+    /// event that doesn't scroll, but is marked as the end so kinetic scrolling can start.
+    ///
     /// [WheelEvent]: https://w3c.github.io/uievents/#event-type-wheel
     #[allow(unused_variables)]
     fn wheel(&mut self, event: &PointerEvent) {}
@@ -733,6 +1008,46 @@ pub trait WinHandler {
     /// Called when a pointer has left the application window.
     fn pointer_leave(&mut self) {}
 
+    /// Called when the pointer moves while it's locked by [`WindowHandle::lock_pointer`].
+    ///
+    /// `delta` is the movement with the user's pointer acceleration applied, like the movement of
+    /// an unlocked pointer. `raw` is the movement of the device without any acceleration, which
+    /// suits drawing or camera controls that need the same result for the same hand movement.
+    /// Both are in roughly display points.
+    #[allow(unused_variables)]
+    fn pointer_move_relative(&mut self, delta: Vec2, raw: Vec2) {}
+
+    /// Called when the platform takes over a pointer which is down, for example when a touch
+    /// turns into a system gesture.
+    ///
+    /// No [`pointer_up`](WinHandler::pointer_up) follows, so anything the pointer started should
+    /// be abandoned.
+    #[allow(unused_variables)]
+    fn pointer_cancel(&mut self, event: &PointerEvent) {}
+
+    /// Called when something is dragged into the window, and whenever it moves while over it.
+    ///
+    /// `pos` is in [display points](crate::Scale), and `formats` are the mime types the drag
+    /// offers, for example `text/uri-list` for files. Return the format you would like to
+    /// receive if it's dropped, or `None` to reject the drop.
+    #[allow(unused_variables)]
+    fn dnd_move(&mut self, pos: Point, formats: &[String]) -> Option<String> {
+        None
+    }
+
+    /// Called when a drag is dropped on the window, with its `data` in the `format` accepted by
+    /// the last [`dnd_move`](WinHandler::dnd_move).
+    #[allow(unused_variables)]
+    fn dnd_drop(&mut self, pos: Point, format: &str, data: Vec<u8>) {}
+
+    /// Called when a drag leaves the window, or is dropped without being accepted.
+    fn dnd_leave(&mut self) {}
+
+    /// Called when a drag started with [`WindowHandle::start_drag`] ends, with what the target
+    /// did with it, or `None` if the drag was cancelled or nothing accepted the drop.
+    #[allow(unused_variables)]
+    fn dnd_finished(&mut self, action: Option<DropAction>) {}
+
     /// Called on timer event.
     ///
     /// This is called at (approximately) the requested deadline by a
@@ -749,12 +1064,23 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn lost_focus(&mut self) {}
 
+    /// Called when the window is shown on or hidden from every screen, for example when it is
+    /// minimized or moved to another workspace. See [`WindowHandle::is_visible`].
+    ///
+    /// Animations can stop while the window is hidden. The window is repainted when it becomes
+    /// visible again.
+    ///
+    /// This is currently only implemented on Wayland.
+    #[allow(unused_variables)]
+    fn visibility_changed(&mut self, visible: bool) {}
+
     /// Called when the shell requests to close the window, for example because the user clicked
-    /// the little "X" in the titlebar.
+    /// the little "X" in the titlebar, or when the app calls [`WindowHandle::request_close`].
     ///
     /// If you want to actually close the window in response to this request, call
-    /// [`WindowHandle::close`]. If you don't implement this method, clicking the titlebar "X" will
-    /// have no effect.
+    /// [`WindowHandle::close`], which is safe to do from here. Returning without closing the
+    /// window vetoes the request. If you don't implement this method, every request is vetoed, so
+    /// clicking the titlebar "X" will have no effect.
     fn request_close(&mut self) {}
 
     /// Called when the window is being destroyed. Note that this happens